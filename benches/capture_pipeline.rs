@@ -0,0 +1,148 @@
+//! Capture path throughput: the lock-free ring buffer pipeline against the
+//! previous per-callback `Vec` + `std::sync::mpsc` design.
+//!
+//! Each iteration pushes one second of 48kHz stereo f32 audio through the
+//! device callback in 10ms blocks and pulls out 16kHz mono frames.
+//!
+//! Run with `cargo bench --bench capture_pipeline`.
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use std::sync::mpsc;
+
+use doubao_voice_input::audio::capture_pipeline;
+
+const SAMPLE_RATE: u32 = 48000;
+const CHANNELS: u16 = 2;
+/// Samples per 10ms callback, interleaved
+const CALLBACK_LEN: usize = (SAMPLE_RATE / 100) as usize * CHANNELS as usize;
+const CALLBACKS_PER_SECOND: usize = 100;
+
+fn callback_block() -> Vec<f32> {
+    (0..CALLBACK_LEN)
+        .map(|i| {
+            ((i / CHANNELS as usize) as f32 * 440.0 * std::f32::consts::TAU / SAMPLE_RATE as f32)
+                .sin()
+                * 0.5
+        })
+        .collect()
+}
+
+/// The capture path before the ring buffer, reproduced for comparison
+struct LegacyPipeline {
+    buffer: Vec<i16>,
+    tx: mpsc::Sender<Vec<i16>>,
+    rx: mpsc::Receiver<Vec<i16>>,
+    native_frame_len: usize,
+}
+
+impl LegacyPipeline {
+    fn new() -> Self {
+        let native_frame_len = (SAMPLE_RATE / 50) as usize * CHANNELS as usize;
+        let (tx, rx) = mpsc::channel();
+        Self {
+            buffer: Vec::with_capacity(native_frame_len * 2),
+            tx,
+            rx,
+            native_frame_len,
+        }
+    }
+
+    fn callback(&mut self, data: &[f32]) {
+        let samples: Vec<i16> = data.iter().map(|s| (*s * 32767.0) as i16).collect();
+        self.buffer.extend_from_slice(&samples);
+        while self.buffer.len() >= self.native_frame_len {
+            let frame: Vec<i16> = self.buffer.drain(..self.native_frame_len).collect();
+            let _ = self.tx.send(frame);
+        }
+    }
+
+    fn process(&mut self) -> usize {
+        let mut frames = 0;
+        while let Ok(frame) = self.rx.try_recv() {
+            let mono: Vec<i16> = frame
+                .chunks(CHANNELS as usize)
+                .map(|chunk| {
+                    let sum: i32 = chunk.iter().map(|&s| s as i32).sum();
+                    (sum / CHANNELS as i32) as i16
+                })
+                .collect();
+            let ratio = mono.len() as f32 / 320.0;
+            let resampled: Vec<i16> = (0..320)
+                .map(|i| mono[((i as f32 * ratio) as usize).min(mono.len() - 1)])
+                .collect();
+            let pcm_bytes: Vec<u8> = resampled.iter().flat_map(|s| s.to_le_bytes()).collect();
+            black_box(pcm_bytes);
+            frames += 1;
+        }
+        frames
+    }
+}
+
+fn bench_capture(c: &mut Criterion) {
+    let block = callback_block();
+    let mut group = c.benchmark_group("capture_1s_48k_stereo");
+    group.throughput(Throughput::Elements(
+        (CALLBACKS_PER_SECOND * CALLBACK_LEN) as u64,
+    ));
+
+    group.bench_function("vec_mpsc", |b| {
+        let mut legacy = LegacyPipeline::new();
+        b.iter(|| {
+            let mut frames = 0;
+            for _ in 0..CALLBACKS_PER_SECOND {
+                legacy.callback(black_box(&block));
+                frames += legacy.process();
+            }
+            frames
+        })
+    });
+
+    group.bench_function("ring_buffer", |b| {
+        let (mut writer, mut processor) = capture_pipeline(SAMPLE_RATE, CHANNELS).unwrap();
+        b.iter(|| {
+            let mut frames = 0;
+            for _ in 0..CALLBACKS_PER_SECOND {
+                writer.write_f32(black_box(&block));
+                while let Some(frame) = processor.next_frame() {
+                    black_box(frame);
+                    frames += 1;
+                }
+            }
+            frames
+        })
+    });
+
+    group.finish();
+
+    // The audio thread's share of the work alone: 400ms of callbacks into a
+    // fresh pipeline (the ring holds 500ms, so nothing is dropped)
+    let mut group = c.benchmark_group("callback_400ms_48k_stereo");
+    group.bench_function("vec_mpsc", |b| {
+        b.iter_batched(
+            LegacyPipeline::new,
+            |mut legacy| {
+                for _ in 0..40 {
+                    legacy.callback(black_box(&block));
+                }
+                legacy
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("ring_buffer", |b| {
+        b.iter_batched(
+            || capture_pipeline(SAMPLE_RATE, CHANNELS).unwrap(),
+            |(mut writer, processor)| {
+                for _ in 0..40 {
+                    writer.write_f32(black_box(&block));
+                }
+                (writer, processor)
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_capture);
+criterion_main!(benches);
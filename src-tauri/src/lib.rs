@@ -1,3 +1,7 @@
+use doubao_voice_input::asr::{AsrError, DeviceProfile, Language, LatencyMetrics, SessionStats};
+use doubao_voice_input::audio::{list_input_devices, DeviceFallback, InputDeviceInfo};
+use doubao_voice_input::business::VocabularyCorrector;
+use doubao_voice_input::data::{CredentialStatus, Vocabulary, VocabularyTerm};
 use doubao_voice_input::{
     AppConfig, AsrClient, AudioCapture, CredentialStore, HotkeyManager, TextInserter,
     VoiceController,
@@ -5,6 +9,7 @@ use doubao_voice_input::{
 use image::GenericImageView;
 use serde_json::json;
 use std::sync::Arc;
+use std::time::Duration;
 use tauri::{
     menu::{CheckMenuItem, Menu, MenuItem, MenuItemKind, PredefinedMenuItem},
     tray::TrayIconBuilder,
@@ -15,6 +20,15 @@ use tokio::sync::Mutex;
 /// Application state managed by Tauri, accessible from commands.
 struct AppState {
     hotkey_manager: Arc<HotkeyManager>,
+    credential_store: Arc<CredentialStore>,
+    asr_client: Arc<AsrClient>,
+    audio_capture: Arc<AudioCapture>,
+    /// Stats of the most recent dictation session
+    last_session_stats: Arc<std::sync::Mutex<Option<SessionStats>>>,
+    /// Contents of `AppConfig::vocabulary_path()`
+    vocabulary: std::sync::Mutex<Vocabulary>,
+    corrector: Arc<VocabularyCorrector>,
+    voice_controller: Arc<Mutex<VoiceController>>,
 }
 
 struct TrayMenu(pub Menu<Wry>);
@@ -24,6 +38,171 @@ async fn get_config() -> Result<AppConfig, String> {
     AppConfig::load_or_default().map_err(|e| e.to_string())
 }
 
+/// Check the device credentials with the server, renewing them if needed
+#[tauri::command]
+async fn validate_credentials(state: State<'_, AppState>) -> Result<CredentialStatus, String> {
+    check_credentials(&state.credential_store, &state.asr_client).await
+}
+
+async fn check_credentials(
+    credential_store: &CredentialStore,
+    asr_client: &AsrClient,
+) -> Result<CredentialStatus, String> {
+    let status = credential_store
+        .validate_credentials()
+        .await
+        .map_err(|e| e.to_string())?;
+    if let Some(credentials) = credential_store.current().await {
+        asr_client.set_credentials(credentials);
+    }
+    Ok(status)
+}
+
+/// Session setup latencies, to compare warm and on-demand connections
+#[tauri::command]
+fn get_latency_metrics(state: State<'_, AppState>) -> LatencyMetrics {
+    state.asr_client.latency_metrics()
+}
+
+#[tauri::command]
+fn get_session_stats(state: State<'_, AppState>) -> Option<SessionStats> {
+    state
+        .last_session_stats
+        .lock()
+        .ok()
+        .and_then(|stats| stats.clone())
+}
+
+/// The built-in device profile, as a template for custom ones
+#[tauri::command]
+fn get_default_device_profile() -> DeviceProfile {
+    DeviceProfile::default()
+}
+
+/// Input devices for the microphone selector, the system default first
+#[tauri::command]
+fn list_audio_devices() -> Result<Vec<InputDeviceInfo>, String> {
+    list_input_devices().map_err(|e| e.to_string())
+}
+
+/// Record from the named input device (`None` for the system default) from
+/// the next recording on, and save the choice as `[audio] device`
+#[tauri::command]
+fn select_audio_device(state: State<'_, AppState>, name: Option<String>) -> Result<(), String> {
+    let mut config = AppConfig::load_or_default().map_err(|e| e.to_string())?;
+    config.audio.device = name.filter(|n| !n.is_empty());
+    config.save().map_err(|e| e.to_string())?;
+    state
+        .audio_capture
+        .set_input_device(config.audio.device.clone());
+    Ok(())
+}
+
+/// The user vocabulary terms
+#[tauri::command]
+fn get_vocabulary(state: State<'_, AppState>) -> Vec<VocabularyTerm> {
+    state
+        .vocabulary
+        .lock()
+        .map(|vocabulary| vocabulary.terms().to_vec())
+        .unwrap_or_default()
+}
+
+/// Add a vocabulary term or change its weight; returns the updated terms
+#[tauri::command]
+fn add_vocabulary_term(
+    state: State<'_, AppState>,
+    term: String,
+    weight: Option<f32>,
+) -> Result<Vec<VocabularyTerm>, String> {
+    update_vocabulary(&state, |vocabulary| {
+        if vocabulary.insert(&term, weight) {
+            Ok(())
+        } else {
+            Err("词条不能为空".to_string())
+        }
+    })
+}
+
+/// Remove a vocabulary term; returns the updated terms
+#[tauri::command]
+fn remove_vocabulary_term(
+    state: State<'_, AppState>,
+    term: String,
+) -> Result<Vec<VocabularyTerm>, String> {
+    update_vocabulary(&state, |vocabulary| {
+        vocabulary.remove(&term);
+        Ok(())
+    })
+}
+
+/// Edit the vocabulary, save it and apply it from the next session
+fn update_vocabulary(
+    state: &AppState,
+    edit: impl FnOnce(&mut Vocabulary) -> Result<(), String>,
+) -> Result<Vec<VocabularyTerm>, String> {
+    let config = AppConfig::load_or_default().map_err(|e| e.to_string())?;
+    let mut vocabulary = state.vocabulary.lock().map_err(|e| e.to_string())?;
+    edit(&mut vocabulary)?;
+    vocabulary.save().map_err(|e| e.to_string())?;
+    apply_recognition(state, &config, &vocabulary);
+    Ok(vocabulary.terms().to_vec())
+}
+
+/// Apply session options (language, hotwords) and vocabulary correction
+/// from the next session on
+fn apply_recognition(state: &AppState, config: &AppConfig, vocabulary: &Vocabulary) {
+    state
+        .asr_client
+        .set_session_options(config.session_options(vocabulary));
+    state.corrector.set_vocabulary(vocabulary);
+    state.corrector.set_enabled(config.vocabulary.correction);
+}
+
+fn language_menu_text(language: Language) -> String {
+    format!("识别语言: {}", language.label())
+}
+
+/// Switch to the next recognition language from the tray; takes effect
+/// with the next session
+fn switch_language<R: Runtime>(app: &AppHandle<R>) {
+    let mut config = AppConfig::load_or_default().unwrap_or_default();
+    let language = config.general.language().next();
+    config.general.language = language.code().to_string();
+    let _ = config.save();
+
+    if let Some(state) = app.try_state::<AppState>() {
+        state.asr_client.set_language(language);
+    }
+    if let Some(tray_menu) = app.try_state::<TrayMenu>() {
+        if let Some(MenuItemKind::MenuItem(item)) = tray_menu.0.get("switch_language") {
+            let _ = item.set_text(language_menu_text(language));
+        }
+    }
+    let _ = app.emit("language-changed", language.code());
+}
+
+/// Validate the credentials and publish the outcome as `credential-status`
+async fn report_credentials<R: Runtime>(
+    handle: &AppHandle<R>,
+    credential_store: &CredentialStore,
+    asr_client: &AsrClient,
+) {
+    let payload = match check_credentials(credential_store, asr_client).await {
+        Ok(status) => json!({ "status": status }),
+        Err(e) => {
+            eprintln!("[Credentials] Validation failed: {}", e);
+            if let Some(tray_menu) = handle.try_state::<TrayMenu>() {
+                if let Some(MenuItemKind::MenuItem(item)) = tray_menu.0.get("status") {
+                    let _ = item.set_text("状态: 凭据失效");
+                }
+            }
+            json!({ "status": "invalid", "message": e })
+        }
+    };
+    let _ = handle.emit("credential-status", payload);
+}
+
 #[tauri::command]
 fn check_accessibility() -> bool {
     #[cfg(target_os = "macos")]
@@ -47,6 +226,43 @@ async fn save_config(
         .update_config(&config.hotkey)
         .map_err(|e| e.to_string())?;
 
+    // Recognition options apply from the next session
+    if let Ok(vocabulary) = state.vocabulary.lock() {
+        apply_recognition(&state, &config, &vocabulary);
+    }
+    state
+        .asr_client
+        .set_recording_dir(config.asr.record_sessions.then(AppConfig::captures_dir));
+    state
+        .asr_client
+        .set_warm_pool(config.asr.warm_pool_timeout());
+    state.asr_client.set_rollover(config.asr.rollover_after());
+    state
+        .audio_capture
+        .set_input_device(config.audio.device.clone());
+    state.audio_capture.set_vad(config.vad_settings());
+    state.audio_capture.set_opus(config.audio.opus.settings());
+    state.audio_capture.set_preroll(config.audio.preroll());
+    show_always_warm(&app, config.audio.preroll());
+    state
+        .voice_controller
+        .lock()
+        .await
+        .set_archive(config.session_archive());
+
+    // A new device profile needs a token issued under that identity
+    if state
+        .credential_store
+        .set_profile(config.device.selected_profile())
+    {
+        let store = state.credential_store.clone();
+        let client = state.asr_client.clone();
+        let handle = app.clone();
+        tauri::async_runtime::spawn(async move {
+            report_credentials(&handle, &store, &client).await;
+        });
+    }
+
     // Apply auto-start capability
     use tauri_plugin_autostart::ManagerExt;
     let autostart_manager = app.autolaunch();
@@ -66,6 +282,9 @@ async fn save_config(
     if let Some(MenuItemKind::Check(item)) = menu.get("toggle_autostart") {
         let _ = item.set_checked(config.general.auto_start);
     }
+    if let Some(MenuItemKind::MenuItem(item)) = menu.get("switch_language") {
+        let _ = item.set_text(language_menu_text(config.general.language()));
+    }
 
     // Apply side effects
     #[cfg(target_os = "macos")]
@@ -508,6 +727,109 @@ mod macos_ext {
     }
 }
 
+/// Surface a typed ASR error: tray status, indicator and an `asr-error` event
+fn report_asr_error<R: Runtime>(handle: &AppHandle<R>, error: &AsrError) {
+    eprintln!("[ASR] {} error: {}", error.kind(), error);
+
+    let status = match error {
+        AsrError::Auth { .. } => "状态: 凭据失效",
+        AsrError::Network(_) | AsrError::Timeout(_) | AsrError::Tls(_) => "状态: 网络异常",
+        _ => "状态: 识别出错",
+    };
+    if let Some(tray_menu) = handle.try_state::<TrayMenu>() {
+        if let Some(MenuItemKind::MenuItem(item)) = tray_menu.0.get("status") {
+            let _ = item.set_text(status);
+        }
+    }
+
+    if let Some(w) = handle.get_webview_window("main") {
+        let _ = w.hide();
+    }
+    let _ = handle.emit("asr-status", json!({ "status": "idle", "text": "" }));
+    let _ = handle.emit(
+        "asr-error",
+        json!({
+            "kind": error.kind(),
+            "message": error.to_string(),
+            "retryable": error.is_retryable(),
+        }),
+    );
+}
+
+/// The selected microphone is missing or was unplugged: tray status, a
+/// system notification and an `audio-device-fallback` event
+fn report_device_fallback<R: Runtime>(handle: &AppHandle<R>, fallback: &DeviceFallback) {
+    if let Some(tray_menu) = handle.try_state::<TrayMenu>() {
+        if let Some(MenuItemKind::MenuItem(item)) = tray_menu.0.get("status") {
+            let _ = item.set_text("状态: 已切换到默认麦克风");
+        }
+    }
+    let message = if fallback.during_recording {
+        format!(
+            "「{}」已断开，继续使用「{}」录音",
+            fallback.lost, fallback.fallback
+        )
+    } else {
+        format!(
+            "未找到「{}」，使用「{}」录音",
+            fallback.lost, fallback.fallback
+        )
+    };
+    show_notification("麦克风已切换", &message);
+    let _ = handle.emit("audio-device-fallback", fallback);
+}
+
+/// Tray status while idle
+fn idle_status_text(always_warm: bool) -> &'static str {
+    if always_warm {
+        "状态: 就绪 · 麦克风常开"
+    } else {
+        "状态: 就绪"
+    }
+}
+
+/// Show whether the microphone stays open between recordings: tray check
+/// mark and status, and an `always-warm` event for the settings page
+fn show_always_warm<R: Runtime>(handle: &AppHandle<R>, preroll: Option<Duration>) {
+    if let Some(tray_menu) = handle.try_state::<TrayMenu>() {
+        if let Some(MenuItemKind::Check(item)) = tray_menu.0.get("toggle_always_warm") {
+            let _ = item.set_checked(preroll.is_some());
+        }
+        if let Some(MenuItemKind::MenuItem(item)) = tray_menu.0.get("status") {
+            let _ = item.set_text(idle_status_text(preroll.is_some()));
+        }
+    }
+    let _ = handle.emit(
+        "always-warm",
+        json!({
+            "enabled": preroll.is_some(),
+            "preroll_ms": preroll.map(|p| p.as_millis() as u64),
+        }),
+    );
+}
+
+/// Turn always-warm capture on or off from the tray
+fn toggle_always_warm<R: Runtime>(app: &AppHandle<R>) {
+    let mut config = AppConfig::load_or_default().unwrap_or_default();
+    config.audio.always_warm = !config.audio.always_warm;
+    let _ = config.save();
+
+    if let Some(state) = app.try_state::<AppState>() {
+        state.audio_capture.set_preroll(config.audio.preroll());
+    }
+    show_always_warm(app, config.audio.preroll());
+}
+
+fn show_notification(title: &str, message: &str) {
+    let script = format!("display notification {:?} with title {:?}", message, title);
+    std::thread::spawn(move || {
+        let _ = std::process::Command::new("osascript")
+            .arg("-e")
+            .arg(script)
+            .status();
+    });
+}
+
 fn play_sound(path: &str) {
     let path = path.to_string();
     std::thread::spawn(move || {
@@ -525,7 +847,16 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             get_config,
             save_config,
-            check_accessibility
+            check_accessibility,
+            validate_credentials,
+            get_latency_metrics,
+            get_session_stats,
+            get_default_device_profile,
+            list_audio_devices,
+            select_audio_device,
+            get_vocabulary,
+            add_vocabulary_term,
+            remove_vocabulary_term
         ])
         .setup(|app| {
             let handle = app.handle().clone();
@@ -570,7 +901,13 @@ pub fn run() {
             // Tray Menu items (Scheme B: Control Center Style)
             let config = AppConfig::load_or_default().unwrap_or_default();
 
-            let status_i = MenuItem::with_id(app, "status", "状态: 就绪", false, None::<&str>)?;
+            let status_i = MenuItem::with_id(
+                app,
+                "status",
+                idle_status_text(config.audio.always_warm),
+                false,
+                None::<&str>,
+            )?;
             let show_i = CheckMenuItem::with_id(
                 app,
                 "show_main",
@@ -595,6 +932,21 @@ pub fn run() {
                 config.general.auto_start,
                 None::<&str>,
             )?;
+            let language_i = MenuItem::with_id(
+                app,
+                "switch_language",
+                language_menu_text(config.general.language()),
+                true,
+                None::<&str>,
+            )?;
+            let always_warm_i = CheckMenuItem::with_id(
+                app,
+                "toggle_always_warm",
+                "麦克风常开 (预录)",
+                true,
+                config.audio.always_warm,
+                None::<&str>,
+            )?;
             let settings_i = MenuItem::with_id(app, "settings", "偏好设置...", true, None::<&str>)?;
             let quit_i = MenuItem::with_id(app, "quit", "退出", true, None::<&str>)?;
 
@@ -607,6 +959,8 @@ pub fn run() {
                     &PredefinedMenuItem::separator(app)?,
                     &dock_i,
                     &autostart_i,
+                    &language_i,
+                    &always_warm_i,
                     &PredefinedMenuItem::separator(app)?,
                     &settings_i,
                     &PredefinedMenuItem::separator(app)?,
@@ -679,6 +1033,8 @@ pub fn run() {
                             }
                         }
                     }
+                    "switch_language" => switch_language(app),
+                    "toggle_always_warm" => toggle_always_warm(app),
                     "toggle_autostart" => {
                         let mut config = AppConfig::load_or_default().unwrap_or_default();
                         config.general.auto_start = !config.general.auto_start;
@@ -716,19 +1072,75 @@ async fn init_core_logic<R: Runtime>(handle: AppHandle<R>) -> anyhow::Result<()>
     let config = AppConfig::load_or_default()?;
 
     // 2. Credentials
-    let credential_store = CredentialStore::new(&config)?;
+    let credential_store = Arc::new(CredentialStore::new(&config)?);
     let credentials = credential_store.ensure_credentials().await?;
 
     // 3. Components
-    let audio_capture = Arc::new(AudioCapture::new()?);
+    let audio_capture = Arc::new(
+        AudioCapture::new()?
+            .with_input_device(config.audio.device.clone())
+            .with_vad(config.vad_settings())
+            .with_opus(config.audio.opus.settings()),
+    );
+    let handle_device = handle.clone();
+    audio_capture
+        .set_on_device_fallback(move |fallback| report_device_fallback(&handle_device, &fallback));
+    audio_capture.set_preroll(config.audio.preroll());
     let text_inserter = Arc::new(TextInserter::new());
-    let asr_client = Arc::new(AsrClient::new(credentials));
+    let vocabulary = Vocabulary::load_or_default().unwrap_or_else(|e| {
+        eprintln!("[Vocabulary] Failed to load: {}", e);
+        Vocabulary::default()
+    });
+    let asr_client = Arc::new(
+        AsrClient::new(credentials)
+            .with_endpoints(config.endpoints.resolve())
+            .with_proxy(config.network.resolve()?)
+            .with_session_options(config.session_options(&vocabulary))
+            .with_credential_store(credential_store.clone()),
+    );
+    asr_client.set_recording_dir(config.asr.record_sessions.then(AppConfig::captures_dir));
+    asr_client.set_warm_pool(config.asr.warm_pool_timeout());
+    asr_client.set_rollover(config.asr.rollover_after());
+
+    // Validate cached credentials in the background so an expired token is
+    // replaced before the first recording
+    let store_check = credential_store.clone();
+    let client_check = asr_client.clone();
+    let handle_check = handle.clone();
+    tauri::async_runtime::spawn(async move {
+        report_credentials(&handle_check, &store_check, &client_check).await;
+    });
 
-    let voice_controller = Arc::new(Mutex::new(VoiceController::new(
-        asr_client,
-        audio_capture.clone(),
-        text_inserter,
-    )));
+    let mut controller =
+        VoiceController::new(asr_client.clone(), audio_capture.clone(), text_inserter);
+    let corrector = Arc::new(VocabularyCorrector::new(&vocabulary));
+    corrector.set_enabled(config.vocabulary.correction);
+    controller.set_corrector(corrector.clone());
+    let archive = config.session_archive();
+    if let Some(Err(e)) = archive.as_ref().map(|a| a.prune()) {
+        eprintln!("[Archive] Failed to prune: {}", e);
+    }
+    controller.set_archive(archive);
+    let handle_error = handle.clone();
+    controller.set_on_error(move |e| report_asr_error(&handle_error, &e));
+    let last_session_stats = Arc::new(std::sync::Mutex::new(None));
+    let stats_slot = last_session_stats.clone();
+    let handle_stats = handle.clone();
+    controller.set_on_stats(move |stats| {
+        let _ = handle_stats.emit("session-stats", &stats);
+        if let Ok(mut last) = stats_slot.lock() {
+            *last = Some(stats);
+        }
+    });
+    let handle_auto_stop = handle.clone();
+    controller.set_on_auto_stop(move || {
+        play_sound("/System/Library/Sounds/Pop.aiff");
+        if let Some(w) = handle_auto_stop.get_webview_window("main") {
+            let _ = w.hide();
+        }
+        let _ = handle_auto_stop.emit("asr-status", json!({ "status": "idle", "text": "" }));
+    });
+    let voice_controller = Arc::new(Mutex::new(controller));
 
     // 4. Hotkeys
     let hotkey_manager = Arc::new(HotkeyManager::new(&config.hotkey)?);
@@ -884,6 +1296,9 @@ async fn init_core_logic<R: Runtime>(handle: AppHandle<R>) -> anyhow::Result<()>
 
             if let Err(e) = vc_lock.toggle().await {
                 eprintln!("Toggle error: {}", e);
+                if let Some(asr_error) = e.downcast_ref::<AsrError>() {
+                    report_asr_error(&h, asr_error);
+                }
             }
 
             let is_recording = vc_lock.is_recording();
@@ -922,6 +1337,13 @@ async fn init_core_logic<R: Runtime>(handle: AppHandle<R>) -> anyhow::Result<()>
     // Register AppState for access from commands (e.g., save_config)
     handle.manage(AppState {
         hotkey_manager: hotkey_manager.clone(),
+        credential_store,
+        asr_client,
+        audio_capture,
+        last_session_stats,
+        vocabulary: std::sync::Mutex::new(vocabulary),
+        corrector,
+        voice_controller,
     });
 
     // TODO: We need a way to get ASR interim results from VoiceController
@@ -1,39 +1,341 @@
 //! ASR WebSocket Client
 //!
 //! Handles the WebSocket connection to the Doubao ASR server.
+//!
+//! A session survives mid-stream connection drops: every Opus frame sent
+//! after the end of the last finalized segment is kept in a bounded replay
+//! buffer, and when
+//! the socket dies the client reconnects, redoes StartTask/StartSession and
+//! replays those frames so the consumer sees one continuous result stream.
+//!
+//! When a `CredentialStore` is attached, a handshake rejected for auth
+//! reasons triggers one credential refresh and a transparent retry.
+//!
+//! With a recording directory set, every session's frames are written to a
+//! capture file there (see `recorder`).
+//!
+//! Connections go through the configured proxy (see `proxy`) unless the
+//! WebSocket host is in its bypass list.
+//!
+//! With a warm pool enabled, a handshaken session is kept ready in the
+//! background (see `pool`) and handed out by `start_realtime`, so audio
+//! starts flowing without waiting for the connect and handshake.
+//!
+//! In continuous mode a long dictation is spread over several server
+//! sessions: once the current one reaches the rollover age, the next final
+//! result (a sentence boundary) switches the stream to a fresh session.
+//! Unfinalized audio is replayed there, and segment indices and times are
+//! carried on from the earlier sessions (as they are after a reconnect), so
+//! the consumer sees one uninterrupted transcript.
+//!
+//! Each session's timings and throughput are collected into a
+//! `SessionStats`, attached to the response that ends it.
 
-use anyhow::{anyhow, Result};
+use futures_util::stream::{SplitSink, SplitStream};
 use futures_util::{SinkExt, StreamExt};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::net::TcpStream;
 use tokio::sync::mpsc;
-use tokio_tungstenite::{connect_async, tungstenite::Message};
+use tokio_tungstenite::{
+    client_async_tls_with_config, connect_async, tungstenite::Message, MaybeTlsStream,
+    WebSocketStream,
+};
 use uuid::Uuid;
 
 use super::constants::*;
 use super::device::DeviceCredentials;
+use super::endpoints::Endpoints;
+use super::error::{AsrError, AsrResult};
+use super::pool::{LatencyMetrics, WarmConnection, WarmPool};
 use super::proto::FrameState;
 use super::protocol::{
     build_finish_session, build_start_session, build_start_task, build_task_request,
-    parse_response, AsrResponse, ResponseType, SessionConfig,
+    parse_response, AsrResponse, Language, ResponseType, SessionConfig, SessionOptions,
 };
+use super::proxy::ProxySettings;
+use super::recorder::SessionRecorder;
+use super::stats::SessionStats;
+use crate::data::CredentialStore;
+
+/// Unfinalized frames kept for replay after a reconnect (30s of audio)
+const REPLAY_BUFFER_FRAMES: usize = 1500;
+
+/// Frames kept for replay after a final result without timings (200ms).
+/// The server closes a segment after a stretch of silence, so the newest
+/// frames are that silence or the start of the next sentence.
+const UNTIMED_FINAL_KEEP_FRAMES: u64 = 10;
+
+/// Reconnect attempts before a dropped session is given up
+const MAX_RECONNECT_ATTEMPTS: u32 = 3;
+
+/// Base delay between reconnect attempts (multiplied by the attempt number)
+const RECONNECT_BACKOFF_MS: u64 = 200;
+
+/// Maximum time for the TCP/TLS connect and for each handshake reply
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Past the rollover age, how long to wait for a sentence boundary before
+/// rolling over mid-sentence
+const ROLLOVER_GRACE: Duration = Duration::from_secs(30);
+
+/// Delay before retrying a rollover whose new session failed to open
+const ROLLOVER_RETRY: Duration = Duration::from_secs(15);
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+type WsWrite = SplitSink<WsStream, Message>;
+type WsRead = SplitStream<WsStream>;
 
 /// ASR Client for real-time speech recognition
 pub struct AsrClient {
-    credentials: DeviceCredentials,
+    credentials: Arc<RwLock<DeviceCredentials>>,
+    endpoints: Endpoints,
+    proxy: ProxySettings,
+    credential_store: Option<Arc<CredentialStore>>,
+    /// Shared with the warm pool, which handshakes with the current options
+    session_options: Arc<RwLock<SessionOptions>>,
+    recording_dir: RwLock<Option<PathBuf>>,
+    /// Idle timeout of the warm pool; `None` disables it
+    warm_idle_timeout: RwLock<Option<Duration>>,
+    warm_pool: Mutex<Option<WarmPool>>,
+    latency: Arc<Mutex<LatencyMetrics>>,
+    /// Server session age after which continuous mode rolls over
+    rollover_after: RwLock<Option<Duration>>,
 }
 
 impl AsrClient {
     /// Create a new ASR client with credentials
     pub fn new(credentials: DeviceCredentials) -> Self {
-        Self { credentials }
+        Self {
+            credentials: Arc::new(RwLock::new(credentials)),
+            endpoints: Endpoints::default(),
+            proxy: ProxySettings::default(),
+            credential_store: None,
+            session_options: Arc::new(RwLock::new(SessionOptions::default())),
+            recording_dir: RwLock::new(None),
+            warm_idle_timeout: RwLock::new(None),
+            warm_pool: Mutex::new(None),
+            latency: Arc::new(Mutex::new(LatencyMetrics::default())),
+            rollover_after: RwLock::new(None),
+        }
     }
 
-    /// Get WebSocket URL with parameters
-    fn ws_url(&self) -> String {
-        format!(
-            "{}?aid={}&device_id={}",
-            WEBSOCKET_URL, AID, self.credentials.device_id
-        )
+    /// Use a resolved endpoint set instead of the built-in defaults
+    pub fn with_endpoints(mut self, endpoints: Endpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    /// Use a different WebSocket endpoint (e.g. a local mock server)
+    pub fn with_ws_url(mut self, ws_url: impl Into<String>) -> Self {
+        self.endpoints.websocket_url = ws_url.into();
+        self
+    }
+
+    /// Dial the WebSocket through `proxy`
+    pub fn with_proxy(mut self, proxy: ProxySettings) -> Self {
+        self.proxy = proxy;
+        self
+    }
+
+    /// Refresh credentials through `store` when the server rejects them
+    pub fn with_credential_store(mut self, store: Arc<CredentialStore>) -> Self {
+        self.credential_store = Some(store);
+        self
+    }
+
+    /// Use `options` for every session started by this client
+    pub fn with_session_options(self, options: SessionOptions) -> Self {
+        self.set_session_options(options);
+        self
+    }
+
+    /// Replace the session options (takes effect from the next session)
+    pub fn set_session_options(&self, options: SessionOptions) {
+        if let Ok(mut current) = self.session_options.write() {
+            if *current == options {
+                return;
+            }
+            *current = options;
+        }
+        self.restart_warm_pool();
+    }
+
+    /// Switch the recognition language from the next session on
+    pub fn set_language(&self, language: Language) {
+        let mut options = self.session_options();
+        options.language = language;
+        self.set_session_options(options);
+    }
+
+    /// Session options used when no per-session override is given
+    pub fn session_options(&self) -> SessionOptions {
+        match self.session_options.read() {
+            Ok(options) => options.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    /// Record every session's wire frames to a capture file in `dir`
+    pub fn with_recording_dir(self, dir: impl Into<PathBuf>) -> Self {
+        self.set_recording_dir(Some(dir.into()));
+        self
+    }
+
+    /// Enable (`Some`) or disable (`None`) session recording
+    pub fn set_recording_dir(&self, dir: Option<PathBuf>) {
+        if let Ok(mut current) = self.recording_dir.write() {
+            *current = dir;
+        }
+    }
+
+    /// Credentials currently used for new sessions
+    pub fn credentials(&self) -> DeviceCredentials {
+        read_credentials(&self.credentials)
+    }
+
+    /// Replace the credentials used for new sessions
+    pub fn set_credentials(&self, credentials: DeviceCredentials) {
+        if let Ok(mut current) = self.credentials.write() {
+            let unchanged =
+                current.device_id == credentials.device_id && current.token == credentials.token;
+            *current = credentials;
+            if unchanged {
+                return;
+            }
+        }
+        self.restart_warm_pool();
+    }
+
+    /// Keep a handshaken session ready, recycled after `idle_timeout`
+    pub fn with_warm_pool(self, idle_timeout: Duration) -> Self {
+        self.set_warm_pool(Some(idle_timeout));
+        self
+    }
+
+    /// Enable (`Some(idle_timeout)`) or disable (`None`) the warm pool
+    ///
+    /// The pool starts on the next `prewarm` or session, or right away when
+    /// called from within a Tokio runtime.
+    pub fn set_warm_pool(&self, idle_timeout: Option<Duration>) {
+        if let Ok(mut current) = self.warm_idle_timeout.write() {
+            if *current == idle_timeout {
+                return;
+            }
+            *current = idle_timeout;
+        }
+        self.restart_warm_pool();
+    }
+
+    /// Continuous mode: roll over to a new server session at the first
+    /// sentence boundary after `age`
+    pub fn with_rollover(self, age: Duration) -> Self {
+        self.set_rollover(Some(age));
+        self
+    }
+
+    /// Enable (`Some(age)`) or disable (`None`) continuous mode; takes
+    /// effect from the next session
+    pub fn set_rollover(&self, age: Option<Duration>) {
+        if let Ok(mut current) = self.rollover_after.write() {
+            *current = age;
+        }
+    }
+
+    /// Start warming a session now, if the warm pool is enabled
+    ///
+    /// Must be called within a Tokio runtime.
+    pub fn prewarm(&self) {
+        self.warm_pool();
+    }
+
+    /// Session setup latencies measured so far
+    pub fn latency_metrics(&self) -> LatencyMetrics {
+        match self.latency.lock() {
+            Ok(metrics) => metrics.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    /// Handle to the running warm pool, starting it if needed
+    fn warm_pool(&self) -> Option<WarmPool> {
+        let idle_timeout = (*self.warm_idle_timeout.read().ok()?)?;
+        let mut pool = self.warm_pool.lock().ok()?;
+        if let Some(ref running) = *pool {
+            if !running.is_closed() && running.idle_timeout() == idle_timeout {
+                return Some(running.clone());
+            }
+        }
+
+        tracing::info!("Starting ASR warm pool (idle timeout {:?})", idle_timeout);
+        let started = WarmPool::start(
+            self.connect_params(self.session_options(), None),
+            self.session_options.clone(),
+            idle_timeout,
+            self.latency.clone(),
+        );
+        *pool = Some(started.clone());
+        Some(started)
+    }
+
+    /// Drop the warm session (e.g. after a settings change) and start over
+    fn restart_warm_pool(&self) {
+        if let Ok(mut pool) = self.warm_pool.lock() {
+            *pool = None;
+        }
+        if tokio::runtime::Handle::try_current().is_ok() {
+            self.warm_pool();
+        }
+    }
+
+    /// Take the warm session if it matches what this session needs
+    async fn take_warm(&self, session_options: &SessionOptions) -> Option<WarmConnection> {
+        let warm = self.warm_pool()?.take().await?;
+        let credentials = self.credentials();
+        if warm.session_options != *session_options
+            || warm.device_id != credentials.device_id
+            || warm.connection.token != credentials.token
+        {
+            tracing::debug!("Warm session does not match this session, connecting anew");
+            return None;
+        }
+        Some(warm)
+    }
+
+    /// Parameters needed to open (and later reopen) a session
+    fn connect_params(
+        &self,
+        session_options: SessionOptions,
+        recorder: Option<Arc<SessionRecorder>>,
+    ) -> ConnectParams {
+        ConnectParams {
+            websocket_url: self.endpoints.websocket_url.clone(),
+            host: self.endpoints.websocket_host(),
+            proxy: self.proxy.clone(),
+            credentials: self.credentials.clone(),
+            credential_store: self.credential_store.clone(),
+            session_options,
+            recorder,
+        }
+    }
+
+    /// Open a capture file for a new session, if recording is enabled
+    fn create_recorder(&self) -> Option<Arc<SessionRecorder>> {
+        let dir = self.recording_dir.read().ok()?.clone()?;
+        let path = dir.join(format!("session-{}.jsonl", current_time_ms()));
+        match SessionRecorder::create(&path) {
+            Ok(recorder) => {
+                tracing::info!("Recording ASR session to {:?}", path);
+                Some(Arc::new(recorder))
+            }
+            Err(e) => {
+                // Recording is a debugging aid; never fail the session over it
+                tracing::warn!("Session recording disabled: {}", e);
+                None
+            }
+        }
     }
 
     /// Start real-time ASR session
@@ -41,151 +343,820 @@ impl AsrClient {
     /// Returns a receiver for ASR responses
     pub async fn start_realtime(
         &self,
-        mut audio_rx: mpsc::Receiver<Vec<u8>>,
-    ) -> Result<mpsc::Receiver<AsrResponse>> {
-        let url = self.ws_url();
+        audio_rx: mpsc::Receiver<Vec<u8>>,
+    ) -> AsrResult<mpsc::Receiver<AsrResponse>> {
+        self.start_realtime_with_options(audio_rx, self.session_options())
+            .await
+    }
+
+    /// Start real-time ASR session with options for this session only
+    pub async fn start_realtime_with_options(
+        &self,
+        audio_rx: mpsc::Receiver<Vec<u8>>,
+        session_options: SessionOptions,
+    ) -> AsrResult<mpsc::Receiver<AsrResponse>> {
+        self.start_realtime_with_preroll(audio_rx, session_options, Duration::ZERO)
+            .await
+    }
+
+    /// Start real-time ASR session whose audio begins `preroll` before now
+    ///
+    /// Pre-roll from always-warm capture is sent first, as the session's
+    /// first frames; timestamps count from when it was captured.
+    pub async fn start_realtime_with_preroll(
+        &self,
+        audio_rx: mpsc::Receiver<Vec<u8>>,
+        session_options: SessionOptions,
+        preroll: Duration,
+    ) -> AsrResult<mpsc::Receiver<AsrResponse>> {
+        let started = Instant::now();
+        let warm = self.take_warm(&session_options).await;
+        let params = self.connect_params(session_options, self.create_recorder());
+
+        let is_warm = warm.is_some();
+        let connection = match warm {
+            Some(warm) => {
+                // Warm sessions are recorded from their first audio frame
+                let mut connection = warm.connection;
+                connection.recorder = params.recorder.clone();
+                connection
+            }
+            None => params.connect().await?,
+        };
+
+        let setup_ms = started.elapsed().as_millis() as u64;
+        tracing::info!(
+            "ASR session ready in {}ms ({})",
+            setup_ms,
+            if is_warm { "warm" } else { "cold" }
+        );
+        if let Ok(mut metrics) = self.latency.lock() {
+            if is_warm {
+                metrics.warm.record(setup_ms);
+            } else {
+                metrics.cold.record(setup_ms);
+            }
+        }
+
+        let stats = SessionStats {
+            warm: is_warm,
+            setup_ms,
+            connect_ms: Some(connection.connect_ms),
+            handshake_ms: Some(connection.handshake_ms),
+            ..Default::default()
+        };
+
+        // Create response channel
+        let (result_tx, result_rx) = mpsc::channel::<AsrResponse>(100);
+
+        tracing::info!("Starting ASR session task");
+        let mut session =
+            RealtimeSession::new(params, connection, audio_rx, result_tx, started, stats);
+        session.start_time = session
+            .start_time
+            .saturating_sub(preroll.as_millis() as u64);
+        session.rollover_after = self.rollover_after.read().ok().and_then(|age| *age);
+        session.schedule_rollover();
+        tokio::spawn(session.run());
+
+        Ok(result_rx)
+    }
+}
+
+/// Handshake parameters for one ASR connection
+#[derive(Clone)]
+pub(super) struct ConnectParams {
+    websocket_url: String,
+    host: String,
+    proxy: ProxySettings,
+    /// Shared with the client so a refresh also applies to later sessions
+    pub(super) credentials: Arc<RwLock<DeviceCredentials>>,
+    credential_store: Option<Arc<CredentialStore>>,
+    pub(super) session_options: SessionOptions,
+    /// Shared by every connection of the session, across reconnects
+    recorder: Option<Arc<SessionRecorder>>,
+}
+
+/// An open, handshaken ASR connection
+pub(super) struct Connection {
+    pub(super) write: WsWrite,
+    pub(super) read: WsRead,
+    request_id: String,
+    pub(super) token: String,
+    recorder: Option<Arc<SessionRecorder>>,
+    /// Time taken to open the WebSocket
+    pub(super) connect_ms: u64,
+    /// Time taken by StartTask + StartSession
+    pub(super) handshake_ms: u64,
+    /// When the server session was started
+    opened_at: Instant,
+}
+
+impl Connection {
+    /// Send one protobuf frame
+    async fn send(&mut self, data: Vec<u8>) -> AsrResult<()> {
+        if let Some(ref recorder) = self.recorder {
+            recorder.record_outgoing(&data);
+        }
+        self.write.send(Message::Binary(data)).await?;
+        Ok(())
+    }
+
+    /// Note a frame received from the server
+    pub(super) fn received(&self, data: &[u8]) {
+        if let Some(ref recorder) = self.recorder {
+            recorder.record_incoming(data);
+        }
+    }
+
+    /// Send the last (silent) audio frame and FinishSession
+    async fn finish(&mut self, timestamp_ms: u64) -> AsrResult<()> {
+        let silent_frame = vec![0u8; 100];
+        let msg = build_task_request(
+            &self.request_id,
+            silent_frame,
+            FrameState::Last,
+            timestamp_ms,
+        );
+        self.send(msg).await?;
+
+        // Send FinishSession
+        let finish_msg = build_finish_session(&self.request_id, &self.token);
+        self.send(finish_msg).await?;
+        tracing::info!("Sent FinishSession");
+        Ok(())
+    }
+
+    /// Finish a session that has been rolled over, discarding its results
+    async fn retire(mut self, timestamp_ms: u64) {
+        // Its late results are not part of the transcript
+        self.recorder = None;
+        if self.finish(timestamp_ms).await.is_ok() {
+            let drain = async {
+                while let Some(Ok(msg)) = self.read.next().await {
+                    if let Message::Binary(data) = msg {
+                        if parse_response(&data).response_type == ResponseType::SessionFinished {
+                            break;
+                        }
+                    }
+                }
+            };
+            let _ = tokio::time::timeout(HANDSHAKE_TIMEOUT, drain).await;
+        }
+        let _ = self.write.close().await;
+    }
+
+    /// Read the server's reply to a handshake step
+    async fn read_handshake_response(&mut self, step: &str) -> AsrResult<AsrResponse> {
+        loop {
+            let msg = tokio::time::timeout(HANDSHAKE_TIMEOUT, self.read.next())
+                .await
+                .map_err(|_| AsrError::Timeout(format!("No reply to {}", step)))?;
+            match msg {
+                Some(Ok(Message::Binary(data))) => {
+                    self.received(&data);
+                    return Ok(parse_response(&data));
+                }
+                Some(Ok(Message::Close(_))) | None => {
+                    return Err(AsrError::Network(format!(
+                        "Connection closed during {}",
+                        step
+                    )))
+                }
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+impl ConnectParams {
+    /// Connect, refreshing the credentials once if they are rejected
+    pub(super) async fn connect(&self) -> AsrResult<Connection> {
+        let credentials = read_credentials(&self.credentials);
+        let error = match self.connect_with(&credentials).await {
+            Err(e) if e.is_auth() => e,
+            result => return result,
+        };
+        let Some(ref store) = self.credential_store else {
+            return Err(error);
+        };
+
+        tracing::warn!("ASR rejected credentials ({}), refreshing", error);
+        let fresh = match store.refresh_credentials(&credentials).await {
+            Ok(fresh) => fresh,
+            Err(e) => {
+                tracing::error!("Credential refresh failed: {}", e);
+                return Err(error);
+            }
+        };
+        if let Ok(mut current) = self.credentials.write() {
+            *current = fresh.clone();
+        }
+        self.connect_with(&fresh).await
+    }
+
+    /// Connect and run the StartTask / StartSession handshake
+    async fn connect_with(&self, credentials: &DeviceCredentials) -> AsrResult<Connection> {
         let request_id = Uuid::new_v4().to_string();
-        let token = self.credentials.token.clone();
-        let device_id = self.credentials.device_id.clone();
+        let url = format!(
+            "{}?aid={}&device_id={}",
+            self.websocket_url, credentials.profile.aid, credentials.device_id
+        );
 
         // Build request with headers
         let request = tokio_tungstenite::tungstenite::http::Request::builder()
             .uri(&url)
-            .header("User-Agent", USER_AGENT)
+            .header("User-Agent", &credentials.profile.user_agent)
             .header("proto-version", "v2")
             .header("x-custom-keepalive", "true")
-            .header("Host", "frontier-audio-ime-ws.doubao.com")
+            .header("Host", &self.host)
             .header("Connection", "Upgrade")
             .header("Upgrade", "websocket")
             .header("Sec-WebSocket-Version", "13")
-            .header("Sec-WebSocket-Key", tokio_tungstenite::tungstenite::handshake::client::generate_key())
+            .header(
+                "Sec-WebSocket-Key",
+                tokio_tungstenite::tungstenite::handshake::client::generate_key(),
+            )
             .body(())?;
 
         tracing::info!("Connecting to ASR WebSocket: {}", url);
-        let (ws_stream, _) = connect_async(request).await?;
+        let connect_started = Instant::now();
+        let ws_stream = tokio::time::timeout(HANDSHAKE_TIMEOUT, self.open_websocket(request))
+            .await
+            .map_err(|_| AsrError::Timeout("WebSocket connect".to_string()))??;
         tracing::info!("WebSocket connected successfully");
-        let (mut write, mut read) = ws_stream.split();
-
-        // Create response channel
-        let (result_tx, result_rx) = mpsc::channel::<AsrResponse>(100);
-
-        // Clone values for tasks
-        let request_id_clone = request_id.clone();
-        let token_clone = token.clone();
+        let (write, read) = ws_stream.split();
+        let mut connection = Connection {
+            write,
+            read,
+            request_id,
+            token: credentials.token.clone(),
+            recorder: self.recorder.clone(),
+            connect_ms: connect_started.elapsed().as_millis() as u64,
+            handshake_ms: 0,
+            opened_at: Instant::now(),
+        };
+        let handshake_started = Instant::now();
 
         // Send StartTask
-        tracing::debug!("Sending StartTask (request_id: {})", &request_id[..8]);
-        let start_task_msg = build_start_task(&request_id, &token);
-        write.send(Message::Binary(start_task_msg)).await?;
+        tracing::debug!(
+            "Sending StartTask (request_id: {})",
+            &connection.request_id[..8]
+        );
+        let start_task_msg = build_start_task(&connection.request_id, &credentials.token);
+        connection.send(start_task_msg).await?;
 
         // Wait for TaskStarted response
-        if let Some(Ok(Message::Binary(data))) = read.next().await {
-            let response = parse_response(&data);
-            if response.response_type == ResponseType::Error {
-                return Err(anyhow!("StartTask failed: {}", response.error_msg));
-            }
-            tracing::debug!("TaskStarted received");
+        let response = connection.read_handshake_response("StartTask").await?;
+        if response.response_type == ResponseType::Error {
+            return Err(handshake_error(response, "StartTask"));
         }
+        tracing::debug!("TaskStarted received");
 
         // Send StartSession
         tracing::debug!("Sending StartSession");
-        let session_config = SessionConfig::new(&device_id);
-        let start_session_msg = build_start_session(&request_id, &token, &session_config);
-        write.send(Message::Binary(start_session_msg)).await?;
+        let session_config = SessionConfig::new(&credentials.device_id, &self.session_options);
+        let start_session_msg =
+            build_start_session(&connection.request_id, &credentials.token, &session_config);
+        connection.send(start_session_msg).await?;
 
         // Wait for SessionStarted response
-        if let Some(Ok(Message::Binary(data))) = read.next().await {
-            let response = parse_response(&data);
-            if response.response_type == ResponseType::Error {
-                return Err(anyhow!("StartSession failed: {}", response.error_msg));
-            }
-            tracing::debug!("SessionStarted received");
-        }
-
-        // Spawn audio sending task
-        tracing::info!("Starting audio frame sender task");
-        tokio::spawn(async move {
-            let mut frame_index = 0u64;
-            let start_time = current_time_ms();
-
-            // Process audio frames until channel is closed
-            while let Some(opus_frame) = audio_rx.recv().await {
-                let frame_state = if frame_index == 0 {
-                    FrameState::First
-                } else {
-                    FrameState::Middle
-                };
-
-                let timestamp_ms = start_time + frame_index * FRAME_DURATION_MS as u64;
-                let msg = build_task_request(
-                    &request_id_clone,
-                    opus_frame,
-                    frame_state,
-                    timestamp_ms,
-                );
+        let response = connection.read_handshake_response("StartSession").await?;
+        if response.response_type == ResponseType::Error {
+            return Err(handshake_error(response, "StartSession"));
+        }
+        tracing::debug!("SessionStarted received");
+        connection.handshake_ms = handshake_started.elapsed().as_millis() as u64;
+        connection.opened_at = Instant::now();
 
-                if write.send(Message::Binary(msg)).await.is_err() {
-                    tracing::warn!("Failed to send audio frame {}", frame_index);
-                    break;
-                }
+        Ok(connection)
+    }
 
-                frame_index += 1;
-                
-                // Log every 50 frames (about 1 second)
-                if frame_index % 50 == 0 {
-                    tracing::info!("Sent {} audio frames ({:.1}s)", frame_index, frame_index as f64 * 0.02);
-                }
-            }
+    /// Dial the WebSocket, tunneling through the proxy when one applies
+    async fn open_websocket(
+        &self,
+        request: tokio_tungstenite::tungstenite::http::Request<()>,
+    ) -> AsrResult<WsStream> {
+        let Some(proxy) = self.proxy.proxy_for(&self.websocket_url) else {
+            let (ws_stream, _) = connect_async(request).await?;
+            return Ok(ws_stream);
+        };
 
-            tracing::info!("Audio channel closed, sent {} total frames", frame_index);
+        let uri = request.uri();
+        let host = uri
+            .host()
+            .ok_or_else(|| AsrError::InvalidEndpoint(format!("No host in {}", uri)))?
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string();
+        let port = uri.port_u16().unwrap_or(match uri.scheme_str() {
+            Some("wss") => 443,
+            _ => 80,
+        });
 
-            // Send last frame to signal end
-            if frame_index > 0 {
-                let timestamp_ms = start_time + frame_index * FRAME_DURATION_MS as u64;
-                let silent_frame = vec![0u8; 100];
-                let msg = build_task_request(
-                    &request_id_clone,
-                    silent_frame,
-                    FrameState::Last,
-                    timestamp_ms,
-                );
-                let _ = write.send(Message::Binary(msg)).await;
+        tracing::info!("Tunneling through proxy {}", proxy.display_url());
+        let stream = proxy.tunnel(&host, port).await?;
+        let (ws_stream, _) = client_async_tls_with_config(request, stream, None, None).await?;
+        Ok(ws_stream)
+    }
+}
 
-                // Send FinishSession
-                let finish_msg = build_finish_session(&request_id_clone, &token_clone);
-                let _ = write.send(Message::Binary(finish_msg)).await;
-                tracing::info!("Sent FinishSession");
-            }
-        });
+/// Turn an Error response to a handshake step into an `AsrError`
+fn handshake_error(response: AsrResponse, step: &str) -> AsrError {
+    tracing::warn!("{} failed: {}", step, response.error_msg);
+    response
+        .error
+        .unwrap_or_else(|| AsrError::from_status(response.status_code, format!("{} failed", step)))
+}
 
-        // Spawn response receiving task
-        let result_tx_clone = result_tx.clone();
-        tokio::spawn(async move {
-            while let Some(Ok(msg)) = read.next().await {
-                if let Message::Binary(data) = msg {
-                    let response = parse_response(&data);
+/// An Opus frame that has been sent but not yet covered by a final result
+struct SentFrame {
+    /// Position of the frame in the session's audio
+    position: u64,
+    timestamp_ms: u64,
+    data: Vec<u8>,
+}
 
-                    match response.response_type {
-                        ResponseType::Error | ResponseType::SessionFinished => {
-                            let _ = result_tx_clone.send(response).await;
+/// State of one real-time session, across reconnects and rollovers
+struct RealtimeSession {
+    params: ConnectParams,
+    connection: Connection,
+    audio_rx: mpsc::Receiver<Vec<u8>>,
+    result_tx: mpsc::Sender<AsrResponse>,
+    start_time: u64,
+    /// Frames taken from the audio channel
+    frame_index: u64,
+    /// Frames sent on the current connection
+    connection_frames: u64,
+    /// Frames not yet finalized by the server, replayed on reconnect
+    unfinalized: VecDeque<SentFrame>,
+    audio_done: bool,
+    reconnects: u32,
+    rollovers: u32,
+    /// Server session age that triggers a rollover (continuous mode)
+    rollover_after: Option<Duration>,
+    /// When the current server session should be rolled over
+    rollover_at: Option<Instant>,
+    /// Added to the segment indices of the current server session
+    index_offset: u32,
+    /// Added to the result times of the current server session: the audio
+    /// sent before it, less the frames replayed there
+    time_offset_ms: u64,
+    /// Index following the last finalized segment forwarded
+    next_index: u32,
+    /// When `start_realtime` was called
+    started: Instant,
+    first_frame_at: Option<Instant>,
+    audio_ended_at: Option<Instant>,
+    /// Connection figures and counters, completed when the session ends
+    stats: SessionStats,
+}
+
+impl RealtimeSession {
+    fn new(
+        params: ConnectParams,
+        connection: Connection,
+        audio_rx: mpsc::Receiver<Vec<u8>>,
+        result_tx: mpsc::Sender<AsrResponse>,
+        started: Instant,
+        stats: SessionStats,
+    ) -> Self {
+        Self {
+            params,
+            connection,
+            audio_rx,
+            result_tx,
+            start_time: current_time_ms(),
+            frame_index: 0,
+            connection_frames: 0,
+            unfinalized: VecDeque::with_capacity(REPLAY_BUFFER_FRAMES),
+            audio_done: false,
+            reconnects: 0,
+            rollovers: 0,
+            rollover_after: None,
+            rollover_at: None,
+            index_offset: 0,
+            time_offset_ms: 0,
+            next_index: 0,
+            started,
+            first_frame_at: None,
+            audio_ended_at: None,
+            stats,
+        }
+    }
+
+    /// Pump audio to the server and results to the consumer until the
+    /// session finishes or can no longer be recovered
+    async fn run(mut self) {
+        loop {
+            tokio::select! {
+                frame = self.audio_rx.recv(), if !self.audio_done => {
+                    let Some(opus_frame) = frame else {
+                        self.end_audio();
+                        tracing::info!("Audio channel closed, sent {} total frames", self.frame_index);
+                        if self.frame_index == 0 {
                             break;
                         }
-                        ResponseType::Heartbeat => {
-                            // Ignore heartbeats
-                            continue;
+                        if self.send_finish().await.is_err() && !self.reconnect().await {
+                            break;
                         }
-                        _ => {
-                            if result_tx_clone.send(response).await.is_err() {
+                        continue;
+                    };
+
+                    let sent = self.buffer_frame(opus_frame);
+                    if self.send_frame(sent).await.is_err() {
+                        tracing::warn!("Failed to send audio frame {}, reconnecting", self.frame_index);
+                        if !self.reconnect().await {
+                            break;
+                        }
+                        continue;
+                    }
+
+                    // No sentence boundary in sight; don't wait for the server's limit
+                    if self
+                        .rollover_at
+                        .is_some_and(|at| at.elapsed() >= ROLLOVER_GRACE)
+                    {
+                        tracing::info!("No final result within the rollover grace period");
+                        if !self.rollover().await {
+                            break;
+                        }
+                        continue;
+                    }
+
+                    // Log every 50 frames (about 1 second)
+                    if self.frame_index % 50 == 0 {
+                        tracing::info!(
+                            "Sent {} audio frames ({:.1}s)",
+                            self.frame_index,
+                            self.frame_index as f64 * 0.02
+                        );
+                    }
+                }
+                msg = self.connection.read.next() => {
+                    match msg {
+                        Some(Ok(Message::Binary(data))) => {
+                            self.connection.received(&data);
+                            if !self.handle_response(&data).await {
                                 break;
                             }
                         }
+                        Some(Ok(Message::Close(_))) | Some(Err(_)) | None => {
+                            if self.audio_done && self.unfinalized.is_empty() {
+                                // Everything was finalized; treat the close as the end
+                                tracing::info!("Connection closed after final result");
+                                self.finish(AsrResponse {
+                                    response_type: ResponseType::SessionFinished,
+                                    ..Default::default()
+                                })
+                                .await;
+                                break;
+                            }
+                            tracing::warn!(
+                                "ASR connection dropped with {} unfinalized frames, reconnecting",
+                                self.unfinalized.len()
+                            );
+                            if !self.reconnect().await {
+                                break;
+                            }
+                        }
+                        Some(Ok(_)) => {}
+                    }
+                }
+            }
+        }
+
+        tracing::info!("ASR session ended: {}", self.stats());
+    }
+
+    /// Forward a server response; returns false when the session is over
+    async fn handle_response(&mut self, data: &[u8]) -> bool {
+        let mut response = parse_response(data);
+        self.renumber(&mut response);
+
+        if matches!(
+            response.response_type,
+            ResponseType::InterimResult | ResponseType::FinalResult
+        ) && !response.text.is_empty()
+            && self.stats.first_interim_ms.is_none()
+        {
+            self.stats.first_interim_ms = self.first_frame_at.map(elapsed_ms);
+        }
+
+        match response.response_type {
+            ResponseType::Heartbeat => true,
+            ResponseType::Error | ResponseType::SessionFinished => {
+                self.finish(response).await;
+                false
+            }
+            ResponseType::FinalResult => {
+                self.trim_finalized(&response);
+                if let Some(ended) = self.audio_ended_at {
+                    self.stats.stop_to_final_ms = Some(elapsed_ms(ended));
+                }
+                if self.result_tx.send(response).await.is_err() {
+                    return false;
+                }
+                if self.rollover_at.is_some_and(|at| Instant::now() >= at) && !self.audio_done {
+                    return self.rollover().await;
+                }
+                true
+            }
+            _ => self.result_tx.send(response).await.is_ok(),
+        }
+    }
+
+    /// Drop the frames a final result covers: those ending by the end of its
+    /// last segment, or all but the newest few if the result is untimed.
+    /// The result arrives after more audio has gone out, so frames past the
+    /// segment still need replaying if the connection drops.
+    fn trim_finalized(&mut self, response: &AsrResponse) {
+        let end_ms = response.segments.iter().filter_map(|s| s.end_ms()).max();
+        let keep_from = match end_ms {
+            Some(end_ms) => end_ms / FRAME_DURATION_MS as u64,
+            None => self.frame_index.saturating_sub(UNTIMED_FINAL_KEEP_FRAMES),
+        };
+        while self
+            .unfinalized
+            .front()
+            .is_some_and(|frame| frame.position < keep_from)
+        {
+            self.unfinalized.pop_front();
+        }
+    }
+
+    /// Continue the segment numbering and timeline of earlier server
+    /// sessions
+    fn renumber(&mut self, response: &mut AsrResponse) {
+        let offset = |time: &mut Option<u64>| {
+            if let Some(ms) = time {
+                *ms += self.time_offset_ms;
+            }
+        };
+        for segment in &mut response.segments {
+            offset(&mut segment.start_time);
+            offset(&mut segment.end_time);
+            let alternatives = segment.alternatives.iter_mut().flat_map(|a| &mut a.words);
+            for word in segment.words.iter_mut().chain(alternatives) {
+                offset(&mut word.start_time);
+                offset(&mut word.end_time);
+            }
+            if let Some(index) = segment.index {
+                let index = index + self.index_offset;
+                segment.index = Some(index);
+                if segment.is_final() {
+                    self.next_index = self.next_index.max(index + 1);
+                }
+            }
+        }
+    }
+
+    /// Start streaming on a new server session
+    fn use_connection(&mut self, connection: Connection) -> Connection {
+        let previous = std::mem::replace(&mut self.connection, connection);
+        self.connection_frames = 0;
+        self.index_offset = self.next_index;
+        // Unfinalized frames are replayed first
+        let base = self
+            .unfinalized
+            .front()
+            .map_or(self.frame_index, |frame| frame.position);
+        self.time_offset_ms = base * FRAME_DURATION_MS as u64;
+        self.schedule_rollover();
+        previous
+    }
+
+    /// Plan the rollover of the current server session, in continuous mode
+    fn schedule_rollover(&mut self) {
+        self.rollover_at = self
+            .rollover_after
+            .map(|age| self.connection.opened_at + age);
+    }
+
+    /// Move the stream to a fresh server session
+    ///
+    /// Frames the last final result did not cover are replayed there, and the
+    /// old session is finished in the background with its late results
+    /// discarded. If the new session cannot be opened, the stream stays on
+    /// the current one and the rollover is retried later.
+    async fn rollover(&mut self) -> bool {
+        tracing::info!(
+            "Rolling over to a new ASR session ({} unfinalized frames)",
+            self.unfinalized.len()
+        );
+        let pending = self.unfinalized.len();
+        match self.connect_buffering(Duration::ZERO).await {
+            Ok(connection) => {
+                let previous = self.use_connection(connection);
+                let timestamp_ms = self.next_timestamp();
+                tokio::spawn(previous.retire(timestamp_ms));
+                self.rollovers += 1;
+
+                if self.replay().await.is_err() {
+                    tracing::warn!("Replay failed after rollover, reconnecting");
+                    return self.reconnect().await;
+                }
+                true
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Session rollover failed, keeping the current session: {}",
+                    e
+                );
+                self.rollover_at = Some(Instant::now() + ROLLOVER_RETRY);
+
+                // Audio buffered while connecting still has to go out
+                for index in pending.min(self.unfinalized.len())..self.unfinalized.len() {
+                    if self.send_frame(index).await.is_err() {
+                        return self.reconnect().await;
                     }
                 }
+                if self.audio_done && self.send_finish().await.is_err() {
+                    return self.reconnect().await;
+                }
+                true
             }
+        }
+    }
+
+    /// Send the response that ends the session, with the session's stats
+    async fn finish(&mut self, mut response: AsrResponse) {
+        response.stats = Some(self.stats());
+        let _ = self.result_tx.send(response).await;
+    }
+
+    /// Stats of the session so far
+    fn stats(&self) -> SessionStats {
+        let mut stats = self.stats.clone();
+        stats.frames_sent = self.frame_index;
+        stats.reconnects = self.reconnects;
+        stats.rollovers = self.rollovers;
+        stats.duration_ms = elapsed_ms(self.started);
+        let streaming_ms = match (self.first_frame_at, self.audio_ended_at) {
+            (Some(first), Some(ended)) => ended.duration_since(first).as_millis() as u64,
+            (Some(first), None) => elapsed_ms(first),
+            _ => 0,
+        };
+        stats.finish(streaming_ms);
+        stats
+    }
+
+    /// Note that the audio channel has closed
+    fn end_audio(&mut self) {
+        self.audio_done = true;
+        self.audio_ended_at = Some(Instant::now());
+    }
+
+    /// Timestamp of the next frame
+    fn next_timestamp(&self) -> u64 {
+        self.start_time + self.frame_index * FRAME_DURATION_MS as u64
+    }
+
+    /// Timestamp a new frame and remember it for replay
+    fn buffer_frame(&mut self, data: Vec<u8>) -> usize {
+        let timestamp_ms = self.next_timestamp();
+        let position = self.frame_index;
+        if self.frame_index == 0 {
+            self.first_frame_at = Some(Instant::now());
+        }
+        self.frame_index += 1;
+        self.stats.bytes_sent += data.len() as u64;
+
+        if self.unfinalized.len() == REPLAY_BUFFER_FRAMES {
+            self.unfinalized.pop_front();
+            tracing::warn!("Replay buffer full, oldest unfinalized frame discarded");
+        }
+        self.unfinalized.push_back(SentFrame {
+            position,
+            timestamp_ms,
+            data,
         });
+        self.unfinalized.len() - 1
+    }
 
-        Ok(result_rx)
+    /// Send the buffered frame at `index` on the current connection
+    async fn send_frame(&mut self, index: usize) -> AsrResult<()> {
+        let frame = &self.unfinalized[index];
+        let frame_state = if self.connection_frames == 0 {
+            FrameState::First
+        } else {
+            FrameState::Middle
+        };
+
+        let msg = build_task_request(
+            &self.connection.request_id,
+            frame.data.clone(),
+            frame_state,
+            frame.timestamp_ms,
+        );
+        self.connection.send(msg).await?;
+        self.connection_frames += 1;
+        Ok(())
+    }
+
+    /// Send the last frame and FinishSession
+    async fn send_finish(&mut self) -> AsrResult<()> {
+        let timestamp_ms = self.next_timestamp();
+        self.connection.finish(timestamp_ms).await
     }
+
+    /// Connect after `delay`, buffering audio captured in the meantime
+    async fn connect_buffering(&mut self, delay: Duration) -> AsrResult<Connection> {
+        let params = self.params.clone();
+        let connect = async move {
+            tokio::time::sleep(delay).await;
+            params.connect().await
+        };
+        tokio::pin!(connect);
+
+        loop {
+            tokio::select! {
+                result = &mut connect => return result,
+                frame = self.audio_rx.recv(), if !self.audio_done => match frame {
+                    Some(opus_frame) => {
+                        self.buffer_frame(opus_frame);
+                    }
+                    None => self.end_audio(),
+                },
+            }
+        }
+    }
+
+    /// Reopen the connection and replay unfinalized audio
+    ///
+    /// Audio keeps being buffered while reconnecting so nothing captured in
+    /// the meantime is lost. Returns false (after notifying the consumer)
+    /// when all attempts fail.
+    async fn reconnect(&mut self) -> bool {
+        let mut last_error = None;
+
+        for attempt in 1..=MAX_RECONNECT_ATTEMPTS {
+            let delay = Duration::from_millis(RECONNECT_BACKOFF_MS * attempt as u64);
+            match self.connect_buffering(delay).await {
+                Ok(connection) => {
+                    self.use_connection(connection);
+                    self.reconnects += 1;
+
+                    if self.replay().await.is_ok() {
+                        tracing::info!(
+                            "ASR reconnected (attempt {}), replayed {} frames",
+                            attempt,
+                            self.unfinalized.len()
+                        );
+                        return true;
+                    }
+                    tracing::warn!("Replay failed on reconnect attempt {}", attempt);
+                }
+                Err(e) => {
+                    tracing::warn!("ASR reconnect attempt {} failed: {}", attempt, e);
+                    // Retrying won't help with e.g. a revoked token
+                    let retryable = e.is_retryable();
+                    last_error = Some(e);
+                    if !retryable {
+                        break;
+                    }
+                }
+            }
+        }
+
+        let error = last_error.unwrap_or_else(|| {
+            AsrError::Network("Connection lost and reconnect failed".to_string())
+        });
+        tracing::error!("ASR connection lost, giving up: {}", error);
+        self.finish(AsrResponse {
+            response_type: ResponseType::Error,
+            error_msg: error.to_string(),
+            error: Some(error),
+            ..Default::default()
+        })
+        .await;
+        false
+    }
+
+    /// Resend every unfinalized frame (and the finish, if audio has ended)
+    async fn replay(&mut self) -> AsrResult<()> {
+        for index in 0..self.unfinalized.len() {
+            self.send_frame(index).await?;
+        }
+        if self.audio_done {
+            self.send_finish().await?;
+        }
+        Ok(())
+    }
+}
+
+/// Snapshot of the shared credentials
+fn read_credentials(credentials: &RwLock<DeviceCredentials>) -> DeviceCredentials {
+    match credentials.read() {
+        Ok(creds) => creds.clone(),
+        Err(poisoned) => poisoned.into_inner().clone(),
+    }
+}
+
+/// Milliseconds since `since`
+fn elapsed_ms(since: Instant) -> u64 {
+    since.elapsed().as_millis() as u64
 }
 
 /// Get current timestamp in milliseconds
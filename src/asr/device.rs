@@ -1,16 +1,19 @@
 //! Device Registration and Token Management
 //!
 //! Implements the device registration flow to obtain device_id and ASR token.
+//! Every request identifies itself with the credentials' `DeviceProfile`.
 
-use anyhow::{anyhow, Result};
-use reqwest::Client;
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::time::{SystemTime, UNIX_EPOCH};
 use uuid::Uuid;
 
-use super::constants::*;
+use super::endpoints::Endpoints;
+use super::error::{AsrError, AsrResult};
+use super::profile::DeviceProfile;
+use super::proxy::ProxySettings;
 
 /// Device credentials for ASR authentication
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,6 +24,10 @@ pub struct DeviceCredentials {
     pub openudid: String,
     pub clientudid: String,
     pub token: String,
+    /// Identity the device was registered with (built-in profile for
+    /// credentials saved before profiles existed)
+    #[serde(default)]
+    pub profile: DeviceProfile,
 }
 
 impl DeviceCredentials {
@@ -33,9 +40,16 @@ impl DeviceCredentials {
             openudid: generate_openudid(),
             clientudid: Uuid::new_v4().to_string(),
             token: String::new(),
+            profile: DeviceProfile::default(),
         }
     }
 
+    /// Use `profile` for registration and all later requests
+    pub fn with_profile(mut self, profile: DeviceProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
     /// Check if credentials are complete
     pub fn is_complete(&self) -> bool {
         !self.device_id.is_empty() && !self.token.is_empty()
@@ -117,41 +131,41 @@ struct DeviceRegisterHeader {
 }
 
 impl DeviceRegisterHeader {
-    fn new(cdid: &str, openudid: &str, clientudid: &str) -> Self {
+    fn new(profile: &DeviceProfile, cdid: &str, openudid: &str, clientudid: &str) -> Self {
         Self {
             device_id: 0,
             install_id: 0,
-            aid: AID,
-            app_name: APP_NAME.to_string(),
-            version_code: VERSION_CODE,
-            version_name: VERSION_NAME.to_string(),
-            manifest_version_code: VERSION_CODE,
-            update_version_code: VERSION_CODE,
-            channel: CHANNEL.to_string(),
-            package: PACKAGE.to_string(),
-            device_platform: DEVICE_PLATFORM.to_string(),
-            os: OS.to_string(),
-            os_api: OS_API.to_string(),
-            os_version: OS_VERSION.to_string(),
-            device_type: DEVICE_TYPE.to_string(),
-            device_brand: DEVICE_BRAND.to_string(),
-            device_model: DEVICE_MODEL.to_string(),
-            resolution: RESOLUTION.to_string(),
-            dpi: DPI.to_string(),
-            language: LANGUAGE.to_string(),
-            timezone: TIMEZONE,
-            access: ACCESS.to_string(),
-            rom: ROM.to_string(),
-            rom_version: ROM_VERSION.to_string(),
+            aid: profile.aid,
+            app_name: profile.app_name.clone(),
+            version_code: profile.version_code,
+            version_name: profile.version_name.clone(),
+            manifest_version_code: profile.version_code,
+            update_version_code: profile.version_code,
+            channel: profile.channel.clone(),
+            package: profile.package.clone(),
+            device_platform: profile.device_platform.clone(),
+            os: profile.os.clone(),
+            os_api: profile.os_api.clone(),
+            os_version: profile.os_version.clone(),
+            device_type: profile.device_type.clone(),
+            device_brand: profile.device_brand.clone(),
+            device_model: profile.device_model.clone(),
+            resolution: profile.resolution.clone(),
+            dpi: profile.dpi.clone(),
+            language: profile.language.clone(),
+            timezone: profile.timezone,
+            access: profile.access.clone(),
+            rom: profile.rom.clone(),
+            rom_version: profile.rom_version.clone(),
             openudid: openudid.to_string(),
             clientudid: clientudid.to_string(),
             cdid: cdid.to_string(),
-            region: "CN".to_string(),
-            tz_name: "Asia/Shanghai".to_string(),
-            tz_offset: 28800,
-            sim_region: "cn".to_string(),
-            carrier_region: "cn".to_string(),
-            cpu_abi: "arm64-v8a".to_string(),
+            region: profile.region.clone(),
+            tz_name: profile.tz_name.clone(),
+            tz_offset: profile.tz_offset,
+            sim_region: profile.sim_region.clone(),
+            carrier_region: profile.carrier_region.clone(),
+            cpu_abi: profile.cpu_abi.clone(),
             build_serial: "unknown".to_string(),
             not_request_sender: 0,
             sig_hash: String::new(),
@@ -203,10 +217,16 @@ struct AsrConfig {
 }
 
 /// Register a new device and get device_id
-pub async fn register_device(creds: &mut DeviceCredentials) -> Result<()> {
-    let client = Client::new();
-
-    let header = DeviceRegisterHeader::new(&creds.cdid, &creds.openudid, &creds.clientudid);
+pub async fn register_device(
+    creds: &mut DeviceCredentials,
+    endpoints: &Endpoints,
+    proxy: &ProxySettings,
+) -> AsrResult<()> {
+    let client = proxy.http_client()?;
+
+    let profile = &creds.profile;
+    let header =
+        DeviceRegisterHeader::new(profile, &creds.cdid, &creds.openudid, &creds.clientudid);
     let body = DeviceRegisterBody {
         magic_tag: "ss_app_log".to_string(),
         header,
@@ -215,46 +235,48 @@ pub async fn register_device(creds: &mut DeviceCredentials) -> Result<()> {
 
     // Build query params
     let mut params: HashMap<&str, String> = HashMap::new();
-    params.insert("device_platform", DEVICE_PLATFORM.to_string());
-    params.insert("os", OS.to_string());
+    params.insert("device_platform", profile.device_platform.clone());
+    params.insert("os", profile.os.clone());
     params.insert("ssmix", "a".to_string());
     params.insert("_rticket", current_time_ms().to_string());
     params.insert("cdid", creds.cdid.clone());
-    params.insert("channel", CHANNEL.to_string());
-    params.insert("aid", AID.to_string());
-    params.insert("app_name", APP_NAME.to_string());
-    params.insert("version_code", VERSION_CODE.to_string());
-    params.insert("version_name", VERSION_NAME.to_string());
-    params.insert("manifest_version_code", VERSION_CODE.to_string());
-    params.insert("update_version_code", VERSION_CODE.to_string());
-    params.insert("resolution", RESOLUTION.to_string());
-    params.insert("dpi", DPI.to_string());
-    params.insert("device_type", DEVICE_TYPE.to_string());
-    params.insert("device_brand", DEVICE_BRAND.to_string());
-    params.insert("language", LANGUAGE.to_string());
-    params.insert("os_api", OS_API.to_string());
-    params.insert("os_version", OS_VERSION.to_string());
-    params.insert("ac", "wifi".to_string());
+    params.insert("channel", profile.channel.clone());
+    params.insert("aid", profile.aid.to_string());
+    params.insert("app_name", profile.app_name.clone());
+    params.insert("version_code", profile.version_code.to_string());
+    params.insert("version_name", profile.version_name.clone());
+    params.insert("manifest_version_code", profile.version_code.to_string());
+    params.insert("update_version_code", profile.version_code.to_string());
+    params.insert("resolution", profile.resolution.clone());
+    params.insert("dpi", profile.dpi.clone());
+    params.insert("device_type", profile.device_type.clone());
+    params.insert("device_brand", profile.device_brand.clone());
+    params.insert("language", profile.language.clone());
+    params.insert("os_api", profile.os_api.clone());
+    params.insert("os_version", profile.os_version.clone());
+    params.insert("ac", profile.access.clone());
 
     let response = client
-        .post(REGISTER_URL)
-        .header("User-Agent", USER_AGENT)
+        .post(&endpoints.register_url)
+        .header("User-Agent", &profile.user_agent)
         .query(&params)
         .json(&body)
         .send()
         .await?;
 
     if !response.status().is_success() {
-        return Err(anyhow!(
-            "Device registration failed: {}",
-            response.status()
+        return Err(AsrError::from_http_status(
+            response.status().as_u16(),
+            "Device registration failed",
         ));
     }
 
     let result: DeviceRegisterResponse = response.json().await?;
 
     if result.device_id == 0 {
-        return Err(anyhow!("Device registration returned invalid device_id"));
+        return Err(AsrError::InvalidResponse(
+            "Device registration returned invalid device_id".to_string(),
+        ));
     }
 
     creds.device_id = result.device_id.to_string();
@@ -265,20 +287,25 @@ pub async fn register_device(creds: &mut DeviceCredentials) -> Result<()> {
 }
 
 /// Get ASR token using device_id
-pub async fn get_asr_token(creds: &mut DeviceCredentials) -> Result<()> {
-    let client = Client::new();
+pub async fn get_asr_token(
+    creds: &mut DeviceCredentials,
+    endpoints: &Endpoints,
+    proxy: &ProxySettings,
+) -> AsrResult<()> {
+    let client = proxy.http_client()?;
+    let profile = &creds.profile;
 
     let mut params: HashMap<&str, String> = HashMap::new();
-    params.insert("device_platform", DEVICE_PLATFORM.to_string());
-    params.insert("os", OS.to_string());
+    params.insert("device_platform", profile.device_platform.clone());
+    params.insert("os", profile.os.clone());
     params.insert("ssmix", "a".to_string());
     params.insert("_rticket", current_time_ms().to_string());
     params.insert("cdid", creds.cdid.clone());
-    params.insert("channel", CHANNEL.to_string());
-    params.insert("aid", AID.to_string());
-    params.insert("app_name", APP_NAME.to_string());
-    params.insert("version_code", VERSION_CODE.to_string());
-    params.insert("version_name", VERSION_NAME.to_string());
+    params.insert("channel", profile.channel.clone());
+    params.insert("aid", profile.aid.to_string());
+    params.insert("app_name", profile.app_name.clone());
+    params.insert("version_code", profile.version_code.to_string());
+    params.insert("version_name", profile.version_name.clone());
     params.insert("device_id", creds.device_id.clone());
 
     // Body is "body=null"
@@ -286,8 +313,8 @@ pub async fn get_asr_token(creds: &mut DeviceCredentials) -> Result<()> {
     let x_ss_stub = format!("{:X}", md5::compute(body_str.as_bytes()));
 
     let response = client
-        .post(SETTINGS_URL)
-        .header("User-Agent", USER_AGENT)
+        .post(&endpoints.settings_url)
+        .header("User-Agent", &profile.user_agent)
         .header("x-ss-stub", x_ss_stub)
         .query(&params)
         .body(body_str)
@@ -295,10 +322,18 @@ pub async fn get_asr_token(creds: &mut DeviceCredentials) -> Result<()> {
         .await?;
 
     if !response.status().is_success() {
-        return Err(anyhow!("Failed to get ASR token: {}", response.status()));
+        return Err(AsrError::from_http_status(
+            response.status().as_u16(),
+            "Failed to get ASR token",
+        ));
     }
 
     let result: SettingsResponse = response.json().await?;
+    if result.data.settings.asr_config.app_key.is_empty() {
+        return Err(AsrError::InvalidResponse(
+            "Settings response contained no ASR token".to_string(),
+        ));
+    }
     creds.token = result.data.settings.asr_config.app_key;
 
     tracing::info!("ASR token obtained successfully");
@@ -0,0 +1,214 @@
+//! Service Endpoints
+//!
+//! The registration, settings and WebSocket URLs used by the ASR layer.
+//! Defaults come from `constants`; they can be overridden from the config
+//! file, environment variables or the `--endpoint` CLI flag.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use tokio_tungstenite::tungstenite::http::Uri;
+
+use super::constants::{REGISTER_URL, SETTINGS_URL, WEBSOCKET_URL};
+
+/// Environment variable overriding the device registration URL
+pub const ENV_REGISTER_URL: &str = "DOUBAO_REGISTER_URL";
+/// Environment variable overriding the settings (token) URL
+pub const ENV_SETTINGS_URL: &str = "DOUBAO_SETTINGS_URL";
+/// Environment variable overriding the ASR WebSocket URL
+pub const ENV_WEBSOCKET_URL: &str = "DOUBAO_WEBSOCKET_URL";
+/// Environment variable overriding the WebSocket `Host` header
+pub const ENV_WEBSOCKET_HOST: &str = "DOUBAO_WEBSOCKET_HOST";
+
+/// Resolved set of service endpoints
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Endpoints {
+    pub register_url: String,
+    pub settings_url: String,
+    pub websocket_url: String,
+    /// `Host` header for the WebSocket handshake; derived from
+    /// `websocket_url` when not set
+    pub websocket_host: Option<String>,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Self {
+            register_url: REGISTER_URL.to_string(),
+            settings_url: SETTINGS_URL.to_string(),
+            websocket_url: WEBSOCKET_URL.to_string(),
+            websocket_host: None,
+        }
+    }
+}
+
+impl Endpoints {
+    /// Apply overrides from the `DOUBAO_*` environment variables
+    pub fn apply_env(&mut self) {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+
+        if let Some(url) = var(ENV_REGISTER_URL) {
+            self.register_url = url;
+        }
+        if let Some(url) = var(ENV_SETTINGS_URL) {
+            self.settings_url = url;
+        }
+        if let Some(url) = var(ENV_WEBSOCKET_URL) {
+            self.websocket_url = url;
+        }
+        if let Some(host) = var(ENV_WEBSOCKET_HOST) {
+            self.websocket_host = Some(host);
+        }
+    }
+
+    /// Apply a single `--endpoint` override
+    ///
+    /// Accepts `register=URL`, `settings=URL`, `websocket=URL` (or `ws=URL`)
+    /// and `host=HOST`. A bare `ws://` / `wss://` URL sets the WebSocket URL.
+    /// Empty values and URLs without a matching scheme and host are rejected.
+    pub fn apply_override(&mut self, spec: &str) -> Result<()> {
+        let spec = spec.trim();
+        if spec.starts_with("ws://") || spec.starts_with("wss://") {
+            self.websocket_url = checked_url(spec, &["ws", "wss"])?;
+            return Ok(());
+        }
+
+        let (key, value) = spec
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Invalid endpoint override '{}', expected KEY=URL", spec))?;
+        let value = value.trim();
+
+        match key.trim() {
+            "register" => self.register_url = checked_url(value, &["http", "https"])?,
+            "settings" => self.settings_url = checked_url(value, &["http", "https"])?,
+            "websocket" | "ws" => self.websocket_url = checked_url(value, &["ws", "wss"])?,
+            "host" => {
+                if value.is_empty() || value.contains(char::is_whitespace) || value.contains('/') {
+                    return Err(anyhow!("Invalid endpoint host '{}'", value));
+                }
+                self.websocket_host = Some(value.to_string());
+            }
+            other => {
+                return Err(anyhow!(
+                    "Unknown endpoint '{}' (expected register, settings, websocket or host)",
+                    other
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// `Host` header to send with the WebSocket handshake
+    pub fn websocket_host(&self) -> String {
+        if let Some(ref host) = self.websocket_host {
+            return host.clone();
+        }
+        self.websocket_url
+            .parse::<Uri>()
+            .ok()
+            .and_then(|uri| uri.authority().map(|a| a.to_string()))
+            .unwrap_or_default()
+    }
+}
+
+/// `url` if it parses with one of `schemes` and a host
+fn checked_url(url: &str, schemes: &[&str]) -> Result<String> {
+    let uri: Uri = url
+        .parse()
+        .map_err(|e| anyhow!("Invalid endpoint URL '{}': {}", url, e))?;
+    let scheme = uri.scheme_str().unwrap_or_default();
+    if !schemes.contains(&scheme) {
+        return Err(anyhow!(
+            "Invalid endpoint URL '{}', expected {}://",
+            url,
+            schemes.join(":// or ")
+        ));
+    }
+    if uri.host().unwrap_or_default().is_empty() {
+        return Err(anyhow!("Invalid endpoint URL '{}': no host", url));
+    }
+    Ok(url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::EndpointsConfig;
+
+    #[test]
+    fn overrides_set_each_endpoint() {
+        let mut endpoints = Endpoints::default();
+        endpoints
+            .apply_override("register=http://127.0.0.1:9000/register")
+            .unwrap();
+        endpoints
+            .apply_override(" settings = https://relay.example.com/settings ")
+            .unwrap();
+        endpoints
+            .apply_override("ws=ws://127.0.0.1:9000/ws")
+            .unwrap();
+        endpoints.apply_override("host=asr.example.com").unwrap();
+
+        assert_eq!(endpoints.register_url, "http://127.0.0.1:9000/register");
+        assert_eq!(endpoints.settings_url, "https://relay.example.com/settings");
+        assert_eq!(endpoints.websocket_url, "ws://127.0.0.1:9000/ws");
+        assert_eq!(endpoints.websocket_host(), "asr.example.com");
+    }
+
+    #[test]
+    fn bare_websocket_urls_set_the_websocket_endpoint() {
+        let mut endpoints = Endpoints::default();
+        endpoints
+            .apply_override("wss://relay.example.com:8443/ocean/api/v1/ws")
+            .unwrap();
+        assert_eq!(
+            endpoints.websocket_url,
+            "wss://relay.example.com:8443/ocean/api/v1/ws"
+        );
+        // The Host header follows the URL unless set
+        assert_eq!(endpoints.websocket_host(), "relay.example.com:8443");
+        assert_eq!(endpoints.register_url, Endpoints::default().register_url);
+    }
+
+    #[test]
+    fn invalid_overrides_are_rejected() {
+        let mut endpoints = Endpoints::default();
+        for spec in [
+            "register=",
+            "host=",
+            "ws=  ",
+            "register=not a url",
+            "settings=ws://127.0.0.1/settings",
+            "websocket=http://127.0.0.1/ws",
+            "register=http://",
+            "host=relay.example.com/ws",
+            "relay=http://127.0.0.1",
+            "http://127.0.0.1",
+        ] {
+            assert!(endpoints.apply_override(spec).is_err(), "{}", spec);
+        }
+        assert_eq!(endpoints, Endpoints::default());
+    }
+
+    #[test]
+    fn cli_overrides_env_which_overrides_config() {
+        let config = EndpointsConfig {
+            register_url: Some("http://config.example.com/register".to_string()),
+            settings_url: Some("http://config.example.com/settings".to_string()),
+            websocket_url: Some("ws://config.example.com/ws".to_string()),
+            websocket_host: None,
+        };
+        // The only test touching these variables
+        std::env::set_var(ENV_SETTINGS_URL, "http://env.example.com/settings");
+        std::env::set_var(ENV_WEBSOCKET_URL, "ws://env.example.com/ws");
+        let mut endpoints = config.resolve();
+        std::env::remove_var(ENV_SETTINGS_URL);
+        std::env::remove_var(ENV_WEBSOCKET_URL);
+        endpoints
+            .apply_override("ws=ws://cli.example.com/ws")
+            .unwrap();
+
+        assert_eq!(endpoints.register_url, "http://config.example.com/register");
+        assert_eq!(endpoints.settings_url, "http://env.example.com/settings");
+        assert_eq!(endpoints.websocket_url, "ws://cli.example.com/ws");
+    }
+}
@@ -0,0 +1,233 @@
+//! ASR Error Types
+//!
+//! Typed errors for the ASR layer, so callers can tell a dead network from
+//! an expired token and react (retry, re-register, ...) instead of only
+//! printing a message.
+
+use thiserror::Error;
+use tokio_tungstenite::tungstenite;
+
+/// Result type used throughout the ASR layer
+pub type AsrResult<T> = std::result::Result<T, AsrError>;
+
+/// Errors produced by device registration and ASR sessions
+#[derive(Debug, Clone, Error)]
+pub enum AsrError {
+    /// Could not reach the server, or the connection broke
+    #[error("Network error: {0}")]
+    Network(String),
+
+    /// TLS handshake or certificate failure
+    #[error("TLS error: {0}")]
+    Tls(String),
+
+    /// Token rejected, revoked or expired (TaskFailed/SessionFailed or HTTP 401/403)
+    #[error("Authentication failed ({status_code}): {message}")]
+    Auth { status_code: i32, message: String },
+
+    /// Server frame could not be decoded as protobuf
+    #[error("Failed to decode server response: {0}")]
+    Decode(#[from] prost::DecodeError),
+
+    /// Server refused the request for a reason other than authentication
+    #[error("Server rejected request ({status_code}): {message}")]
+    Rejected { status_code: i32, message: String },
+
+    /// Server did not answer in time
+    #[error("Timed out: {0}")]
+    Timeout(String),
+
+    /// Server answered with a payload we could not interpret
+    #[error("Invalid server response: {0}")]
+    InvalidResponse(String),
+
+    /// The configured endpoint URL is malformed
+    #[error("Invalid endpoint: {0}")]
+    InvalidEndpoint(String),
+
+    /// The proxy refused the tunnel (bad credentials, blocked target, ...)
+    #[error("Proxy error: {0}")]
+    Proxy(String),
+}
+
+impl AsrError {
+    /// Classify a TaskFailed / SessionFailed status from the ASR server
+    ///
+    /// The server does not document its codes; 401/403-style codes, either
+    /// bare or as the prefix of the 8-digit form (e.g. `40100001`), are
+    /// treated as authentication failures. The message is free text and is
+    /// not used for classification.
+    pub fn from_status(status_code: i32, message: impl Into<String>) -> Self {
+        let message = message.into();
+        let http_like = if status_code >= 10_000_000 {
+            status_code / 100_000
+        } else {
+            status_code
+        };
+
+        if matches!(http_like, 401 | 403) {
+            AsrError::Auth {
+                status_code,
+                message,
+            }
+        } else {
+            AsrError::Rejected {
+                status_code,
+                message,
+            }
+        }
+    }
+
+    /// Classify a non-success HTTP status from the registration/settings API
+    pub fn from_http_status(status: u16, context: &str) -> Self {
+        let message = format!("{}: HTTP {}", context, status);
+        match status {
+            401 | 403 => AsrError::Auth {
+                status_code: status as i32,
+                message,
+            },
+            408 | 504 => AsrError::Timeout(message),
+            _ => AsrError::Rejected {
+                status_code: status as i32,
+                message,
+            },
+        }
+    }
+
+    /// Whether the credentials should be refreshed before retrying
+    pub fn is_auth(&self) -> bool {
+        matches!(self, AsrError::Auth { .. })
+    }
+
+    /// Whether retrying the same request later may succeed
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, AsrError::Network(_) | AsrError::Timeout(_))
+    }
+
+    /// Short machine-readable kind, e.g. for UI events
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AsrError::Network(_) => "network",
+            AsrError::Tls(_) => "tls",
+            AsrError::Auth { .. } => "auth",
+            AsrError::Decode(_) => "decode",
+            AsrError::Rejected { .. } => "rejected",
+            AsrError::Timeout(_) => "timeout",
+            AsrError::InvalidResponse(_) => "invalid_response",
+            AsrError::InvalidEndpoint(_) => "invalid_endpoint",
+            AsrError::Proxy(_) => "proxy",
+        }
+    }
+}
+
+impl From<tungstenite::Error> for AsrError {
+    fn from(e: tungstenite::Error) -> Self {
+        match e {
+            tungstenite::Error::Tls(e) => AsrError::Tls(e.to_string()),
+            tungstenite::Error::Io(e) if e.kind() == std::io::ErrorKind::TimedOut => {
+                AsrError::Timeout(e.to_string())
+            }
+            tungstenite::Error::Http(response) => {
+                AsrError::from_http_status(response.status().as_u16(), "WebSocket upgrade")
+            }
+            tungstenite::Error::Url(e) => AsrError::InvalidEndpoint(e.to_string()),
+            tungstenite::Error::HttpFormat(e) => AsrError::InvalidEndpoint(e.to_string()),
+            other => AsrError::Network(other.to_string()),
+        }
+    }
+}
+
+impl From<tungstenite::http::Error> for AsrError {
+    fn from(e: tungstenite::http::Error) -> Self {
+        AsrError::InvalidEndpoint(e.to_string())
+    }
+}
+
+impl From<reqwest::Error> for AsrError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            return AsrError::Timeout(e.to_string());
+        }
+        if e.is_decode() {
+            return AsrError::InvalidResponse(e.to_string());
+        }
+        if e.is_builder() {
+            return AsrError::InvalidEndpoint(e.to_string());
+        }
+        if let Some(status) = e.status() {
+            return AsrError::from_http_status(status.as_u16(), "HTTP request");
+        }
+
+        // reqwest does not expose TLS failures directly; look through the source chain
+        let mut source = std::error::Error::source(&e);
+        while let Some(err) = source {
+            let text = err.to_string().to_lowercase();
+            if text.contains("certificate") || text.contains("tls") {
+                return AsrError::Tls(e.to_string());
+            }
+            source = err.source();
+        }
+        AsrError::Network(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auth_is_classified_by_status_code() {
+        for code in [401, 403, 40100001, 40300002] {
+            assert!(AsrError::from_status(code, "denied").is_auth(), "{}", code);
+        }
+        for code in [400, 404, 500, 40000001, 45000001, 50000000] {
+            let err = AsrError::from_status(code, "denied");
+            assert!(
+                matches!(err, AsrError::Rejected { status_code, .. } if status_code == code),
+                "{}",
+                code
+            );
+        }
+    }
+
+    #[test]
+    fn messages_do_not_affect_classification() {
+        for message in [
+            "token length exceeds limit",
+            "author field missing",
+            "audio expired before upload",
+            "permission to record not granted",
+        ] {
+            assert!(
+                !AsrError::from_status(45000001, message).is_auth(),
+                "{}",
+                message
+            );
+        }
+        let err = AsrError::from_status(40100001, "invalid token");
+        assert_eq!(
+            err.to_string(),
+            "Authentication failed (40100001): invalid token"
+        );
+    }
+
+    #[test]
+    fn http_statuses_map_to_kinds() {
+        assert_eq!(AsrError::from_http_status(401, "register").kind(), "auth");
+        assert_eq!(AsrError::from_http_status(403, "register").kind(), "auth");
+        assert_eq!(
+            AsrError::from_http_status(408, "register").kind(),
+            "timeout"
+        );
+        assert_eq!(
+            AsrError::from_http_status(504, "register").kind(),
+            "timeout"
+        );
+        let err = AsrError::from_http_status(500, "settings");
+        assert_eq!(err.kind(), "rejected");
+        assert_eq!(
+            err.to_string(),
+            "Server rejected request (500): settings: HTTP 500"
+        );
+    }
+}
@@ -0,0 +1,378 @@
+//! Mock ASR Server
+//!
+//! A local stand-in for the Doubao ASR WebSocket service, used to exercise
+//! `AsrClient` and the protocol builders/parsers without network access.
+//! Built only for tests, or with the `mock-server` feature.
+//!
+//! The server speaks the same protobuf framing as the real service:
+//! StartTask → TaskStarted, StartSession → SessionStarted, TaskRequest frames
+//! → scripted recognition results, FinishSession → SessionFinished.
+
+use anyhow::Result;
+use futures_util::{SinkExt, StreamExt};
+use prost::Message as _;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::{accept_async, tungstenite::Message};
+
+use super::proto::{AsrRequest, AsrResponse as AsrResponseProto, FrameState};
+
+/// Reply to a handshake step (StartTask / StartSession)
+#[derive(Debug, Clone)]
+pub enum MockReply {
+    /// Acknowledge normally (TaskStarted / SessionStarted)
+    Ok,
+    /// Reply with TaskFailed / SessionFailed and close the connection
+    Fail { status_code: i32, message: String },
+    /// Close the connection without replying
+    Drop,
+}
+
+/// A recognition result emitted once enough audio frames have arrived
+#[derive(Debug, Clone)]
+pub struct ScriptedResult {
+    /// Number of audio frames received on this connection before sending
+    pub after_frames: u32,
+    /// Raw `result_json` payload
+    pub result_json: String,
+}
+
+impl ScriptedResult {
+    /// Interim (still changing) result for a single segment
+    pub fn interim(after_frames: u32, text: &str) -> Self {
+        Self {
+            after_frames,
+            result_json: serde_json::json!({
+                "results": [{ "text": text, "is_interim": true }],
+                "extra": {}
+            })
+            .to_string(),
+        }
+    }
+
+    /// Final (definite) result for a single segment
+    pub fn final_result(after_frames: u32, text: &str) -> Self {
+        Self {
+            after_frames,
+            result_json: serde_json::json!({
+                "results": [{
+                    "text": text,
+                    "is_interim": false,
+                    "is_vad_finished": true,
+                    "extra": { "nonstream_result": true }
+                }],
+                "extra": {}
+            })
+            .to_string(),
+        }
+    }
+
+    /// Arbitrary `result_json` payload
+    pub fn raw(after_frames: u32, result_json: impl Into<String>) -> Self {
+        Self {
+            after_frames,
+            result_json: result_json.into(),
+        }
+    }
+}
+
+/// Behaviour of the mock server for one WebSocket connection
+#[derive(Debug, Clone)]
+pub struct MockScript {
+    /// Delay applied before every server reply
+    pub latency: Duration,
+    /// Reply to StartTask
+    pub start_task: MockReply,
+    /// Reply to StartSession
+    pub start_session: MockReply,
+    /// Results emitted as audio frames arrive
+    pub results: Vec<ScriptedResult>,
+    /// Abruptly drop the connection after this many audio frames
+    pub drop_after_frames: Option<u32>,
+    /// Send a heartbeat (result_json without `results`) every N audio frames
+    pub heartbeat_every: Option<u32>,
+}
+
+impl Default for MockScript {
+    fn default() -> Self {
+        Self {
+            latency: Duration::ZERO,
+            start_task: MockReply::Ok,
+            start_session: MockReply::Ok,
+            results: Vec::new(),
+            drop_after_frames: None,
+            heartbeat_every: None,
+        }
+    }
+}
+
+impl MockScript {
+    /// Script that recognizes `text`: one interim result, then a final one
+    pub fn recognize(text: &str) -> Self {
+        let half: String = text.chars().take(text.chars().count() / 2).collect();
+        Self {
+            results: vec![
+                ScriptedResult::interim(5, &half),
+                ScriptedResult::final_result(10, text),
+            ],
+            ..Default::default()
+        }
+    }
+}
+
+/// A request received by the mock server
+#[derive(Debug, Clone)]
+pub struct ReceivedRequest {
+    /// Zero-based index of the connection the request arrived on
+    pub connection: usize,
+    pub method_name: String,
+    pub request_id: String,
+    pub token: String,
+    pub payload: String,
+    pub frame_state: i32,
+    pub audio_len: usize,
+}
+
+/// Local mock of the Doubao ASR WebSocket server
+///
+/// Each accepted connection uses the next script in the list; once the list
+/// is exhausted the last script is reused. The server stops when dropped.
+pub struct MockAsrServer {
+    addr: SocketAddr,
+    received: Arc<Mutex<Vec<ReceivedRequest>>>,
+    connections: Arc<AtomicUsize>,
+    handle: JoinHandle<()>,
+}
+
+impl MockAsrServer {
+    /// Start a server that uses the same script for every connection
+    pub async fn start(script: MockScript) -> Result<Self> {
+        Self::start_with_scripts(vec![script]).await
+    }
+
+    /// Start a server with one script per connection
+    pub async fn start_with_scripts(scripts: Vec<MockScript>) -> Result<Self> {
+        let scripts = if scripts.is_empty() {
+            vec![MockScript::default()]
+        } else {
+            scripts
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let connections = Arc::new(AtomicUsize::new(0));
+
+        let received_clone = received.clone();
+        let connections_clone = connections.clone();
+        let handle = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let index = connections_clone.fetch_add(1, Ordering::SeqCst);
+                let script = scripts[index.min(scripts.len() - 1)].clone();
+                let received = received_clone.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, index, script, received).await {
+                        tracing::debug!("[MockAsr] connection {} ended: {}", index, e);
+                    }
+                });
+            }
+        });
+
+        tracing::info!("[MockAsr] listening on {}", addr);
+        Ok(Self {
+            addr,
+            received,
+            connections,
+            handle,
+        })
+    }
+
+    /// Address the server is bound to
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// WebSocket URL to dial (same path as the real service)
+    pub fn url(&self) -> String {
+        format!("ws://{}/ocean/api/v1/ws", self.addr)
+    }
+
+    /// Number of connections accepted so far
+    pub fn connection_count(&self) -> usize {
+        self.connections.load(Ordering::SeqCst)
+    }
+
+    /// Snapshot of every request received so far
+    pub fn received(&self) -> Vec<ReceivedRequest> {
+        self.received.lock().map(|r| r.clone()).unwrap_or_default()
+    }
+}
+
+impl Drop for MockAsrServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// Serve one WebSocket connection according to `script`
+async fn handle_connection(
+    stream: TcpStream,
+    connection: usize,
+    script: MockScript,
+    received: Arc<Mutex<Vec<ReceivedRequest>>>,
+) -> Result<()> {
+    let mut ws = accept_async(stream).await?;
+    let mut frames = 0u32;
+    let mut next_result = 0usize;
+
+    while let Some(msg) = ws.next().await {
+        let data = match msg? {
+            Message::Binary(data) => data,
+            Message::Close(_) => break,
+            _ => continue,
+        };
+
+        let request = AsrRequest::decode(data.as_slice())?;
+        if let Ok(mut log) = received.lock() {
+            log.push(ReceivedRequest {
+                connection,
+                method_name: request.method_name.clone(),
+                request_id: request.request_id.clone(),
+                token: request.token.clone(),
+                payload: request.payload.clone(),
+                frame_state: request.frame_state,
+                audio_len: request.audio_data.len(),
+            });
+        }
+
+        let mut replies = Vec::new();
+        match request.method_name.as_str() {
+            "StartTask" => match &script.start_task {
+                MockReply::Ok => replies.push(response(&request, "TaskStarted", 20000000, "")),
+                MockReply::Fail {
+                    status_code,
+                    message,
+                } => {
+                    replies.push(response(&request, "TaskFailed", *status_code, message));
+                    send_all(&mut ws, &script, replies).await?;
+                    let _ = ws.close(None).await;
+                    return Ok(());
+                }
+                MockReply::Drop => return Ok(()),
+            },
+            "StartSession" => match &script.start_session {
+                MockReply::Ok => replies.push(response(&request, "SessionStarted", 20000000, "")),
+                MockReply::Fail {
+                    status_code,
+                    message,
+                } => {
+                    replies.push(response(&request, "SessionFailed", *status_code, message));
+                    send_all(&mut ws, &script, replies).await?;
+                    let _ = ws.close(None).await;
+                    return Ok(());
+                }
+                MockReply::Drop => return Ok(()),
+            },
+            "TaskRequest" => {
+                if request.frame_state != FrameState::Last as i32 {
+                    frames += 1;
+                }
+
+                if script.drop_after_frames.is_some_and(|n| frames >= n) {
+                    tracing::debug!(
+                        "[MockAsr] dropping connection {} after {} frames",
+                        connection,
+                        frames
+                    );
+                    return Ok(());
+                }
+
+                if script
+                    .heartbeat_every
+                    .is_some_and(|n| frames.checked_rem(n) == Some(0))
+                {
+                    let heartbeat = serde_json::json!({ "extra": { "packet_number": frames } });
+                    replies.push(result(&request, &heartbeat.to_string()));
+                }
+
+                while let Some(scripted) = script.results.get(next_result) {
+                    if scripted.after_frames > frames {
+                        break;
+                    }
+                    replies.push(result(&request, &scripted.result_json));
+                    next_result += 1;
+                }
+            }
+            "FinishSession" => {
+                // Flush anything the script still has pending
+                for scripted in &script.results[next_result..] {
+                    replies.push(result(&request, &scripted.result_json));
+                }
+                replies.push(response(&request, "SessionFinished", 20000000, ""));
+                send_all(&mut ws, &script, replies).await?;
+                let _ = ws.close(None).await;
+                return Ok(());
+            }
+            other => {
+                tracing::warn!("[MockAsr] unexpected method: {}", other);
+            }
+        }
+
+        send_all(&mut ws, &script, replies).await?;
+    }
+
+    Ok(())
+}
+
+async fn send_all(
+    ws: &mut tokio_tungstenite::WebSocketStream<TcpStream>,
+    script: &MockScript,
+    replies: Vec<Vec<u8>>,
+) -> Result<()> {
+    for reply in replies {
+        if !script.latency.is_zero() {
+            tokio::time::sleep(script.latency).await;
+        }
+        ws.send(Message::Binary(reply)).await?;
+    }
+    Ok(())
+}
+
+/// Build a control response (TaskStarted, SessionFailed, ...)
+fn response(
+    request: &AsrRequest,
+    message_type: &str,
+    status_code: i32,
+    status_message: &str,
+) -> Vec<u8> {
+    AsrResponseProto {
+        request_id: request.request_id.clone(),
+        task_id: request.request_id.clone(),
+        service_name: "ASR".to_string(),
+        message_type: message_type.to_string(),
+        status_code,
+        status_message: status_message.to_string(),
+        result_json: String::new(),
+        unknown_field_9: 0,
+    }
+    .encode_to_vec()
+}
+
+/// Build a recognition result response
+fn result(request: &AsrRequest, result_json: &str) -> Vec<u8> {
+    AsrResponseProto {
+        request_id: request.request_id.clone(),
+        task_id: request.request_id.clone(),
+        service_name: "ASR".to_string(),
+        message_type: String::new(),
+        status_code: 20000000,
+        status_message: String::new(),
+        result_json: result_json.to_string(),
+        unknown_field_9: 0,
+    }
+    .encode_to_vec()
+}
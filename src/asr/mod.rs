@@ -5,12 +5,32 @@
 mod client;
 mod constants;
 mod device;
+mod endpoints;
+mod error;
+#[cfg(any(test, feature = "mock-server"))]
+pub mod mock_server;
+mod pool;
+mod profile;
 mod protocol;
+mod proxy;
+mod recorder;
+mod result;
+mod stats;
 
 pub use client::AsrClient;
 pub use constants::*;
 pub use device::{DeviceCredentials, register_device, get_asr_token};
-pub use protocol::{AsrResponse, ResponseType};
+pub use endpoints::Endpoints;
+pub use error::{AsrError, AsrResult};
+pub use pool::{LatencyMetrics, LatencyStat};
+pub use profile::{DeviceProfile, DEFAULT_PROFILE_NAME};
+pub use protocol::{AsrResponse, Hotword, Language, ResponseType, SessionOptions};
+pub use proxy::{split_no_proxy, Proxy, ProxyScheme, ProxySettings};
+pub use recorder::{load_capture, replay_capture, CapturedFrame, Direction, SessionRecorder};
+pub use result::{
+    Alternative, PayloadExtra, RecognitionSegment, ResultPayload, SegmentExtra, WordTiming,
+};
+pub use stats::SessionStats;
 
 // Include the generated protobuf code
 pub mod proto {
@@ -0,0 +1,262 @@
+//! Warm Connection Pool
+//!
+//! Keeps one handshaken session (WebSocket connect + StartTask +
+//! StartSession) ready so a hotkey press can stream audio immediately.
+//!
+//! A background task owns the idle connection: it drains heartbeats, closes
+//! the session after the idle timeout and opens a fresh one, reconnects when
+//! the server drops it, and opens a replacement as soon as one is taken.
+
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot};
+use tokio_tungstenite::tungstenite::Message;
+
+use super::client::{ConnectParams, Connection};
+use super::protocol::{parse_response, ResponseType, SessionOptions};
+
+/// Upper bound for the delay between failed warm-up attempts
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// A session that completed its handshake before it was needed
+pub(super) struct WarmConnection {
+    pub connection: Connection,
+    /// Options the session was started with
+    pub session_options: SessionOptions,
+    pub device_id: String,
+}
+
+type TakeRequest = oneshot::Sender<Option<WarmConnection>>;
+
+/// Handle to the background task keeping a session warm
+///
+/// Dropping every handle stops the task and closes the idle connection.
+#[derive(Clone)]
+pub(super) struct WarmPool {
+    idle_timeout: Duration,
+    requests: mpsc::Sender<TakeRequest>,
+}
+
+impl WarmPool {
+    /// Spawn the keeper task; must be called within a Tokio runtime
+    pub fn start(
+        params: ConnectParams,
+        session_options: Arc<RwLock<SessionOptions>>,
+        idle_timeout: Duration,
+        metrics: Arc<Mutex<LatencyMetrics>>,
+    ) -> Self {
+        let (requests, requests_rx) = mpsc::channel(4);
+        let keeper = Keeper {
+            params,
+            session_options,
+            idle_timeout,
+            metrics,
+            requests: requests_rx,
+        };
+        tokio::spawn(keeper.run());
+
+        Self {
+            idle_timeout,
+            requests,
+        }
+    }
+
+    pub fn idle_timeout(&self) -> Duration {
+        self.idle_timeout
+    }
+
+    /// Whether the keeper task has stopped (e.g. the token was revoked)
+    pub fn is_closed(&self) -> bool {
+        self.requests.is_closed()
+    }
+
+    /// Take the warm session, if one is ready right now
+    ///
+    /// Never waits for a handshake in progress; the caller connects on its
+    /// own instead.
+    pub async fn take(&self) -> Option<WarmConnection> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.requests.send(reply_tx).await.ok()?;
+        reply_rx.await.ok().flatten()
+    }
+}
+
+/// Background owner of the idle connection
+struct Keeper {
+    params: ConnectParams,
+    session_options: Arc<RwLock<SessionOptions>>,
+    idle_timeout: Duration,
+    metrics: Arc<Mutex<LatencyMetrics>>,
+    requests: mpsc::Receiver<TakeRequest>,
+}
+
+impl Keeper {
+    async fn run(mut self) {
+        let mut failures = 0u32;
+
+        loop {
+            let session_options = match self.session_options.read() {
+                Ok(options) => options.clone(),
+                Err(poisoned) => poisoned.into_inner().clone(),
+            };
+            let mut params = self.params.clone();
+            params.session_options = session_options.clone();
+
+            let started = Instant::now();
+            let connect = params.connect();
+            tokio::pin!(connect);
+            let result = loop {
+                tokio::select! {
+                    result = &mut connect => break result,
+                    request = self.requests.recv() => match request {
+                        // Nothing ready yet
+                        Some(reply) => {
+                            let _ = reply.send(None);
+                        }
+                        None => return,
+                    },
+                }
+            };
+
+            let connection = match result {
+                Ok(connection) => connection,
+                Err(e) => {
+                    failures += 1;
+                    tracing::warn!("Warm ASR connection failed (attempt {}): {}", failures, e);
+                    if !e.is_retryable() {
+                        // Sessions started on demand will surface the error
+                        tracing::warn!("Warm pool stopped");
+                        return;
+                    }
+                    let delay = Duration::from_secs(1 << failures.min(6)).min(MAX_RETRY_DELAY);
+                    if !self.idle(delay).await {
+                        return;
+                    }
+                    continue;
+                }
+            };
+            failures = 0;
+
+            let handshake_ms = started.elapsed().as_millis() as u64;
+            tracing::info!("Warm ASR session ready (handshake {}ms)", handshake_ms);
+            if let Ok(mut metrics) = self.metrics.lock() {
+                metrics.background_handshake.record(handshake_ms);
+            }
+
+            let warm = WarmConnection {
+                device_id: read_device_id(&params),
+                connection,
+                session_options,
+            };
+            if !self.hold(warm).await {
+                return;
+            }
+        }
+    }
+
+    /// Keep `warm` alive until it is taken, expires or is dropped by the
+    /// server; returns false when the pool has been shut down
+    async fn hold(&mut self, mut warm: WarmConnection) -> bool {
+        let expiry = tokio::time::sleep(self.idle_timeout);
+        tokio::pin!(expiry);
+
+        loop {
+            tokio::select! {
+                request = self.requests.recv() => {
+                    let Some(reply) = request else {
+                        let _ = warm.connection.write.close().await;
+                        return false;
+                    };
+                    match reply.send(Some(warm)) {
+                        Ok(()) => return true,
+                        // The caller gave up waiting; keep holding
+                        Err(returned) => match returned {
+                            Some(returned) => warm = returned,
+                            None => return true,
+                        },
+                    }
+                }
+                msg = warm.connection.read.next() => match msg {
+                    Some(Ok(Message::Binary(data))) => {
+                        warm.connection.received(&data);
+                        let response = parse_response(&data);
+                        if response.response_type == ResponseType::Error {
+                            tracing::debug!("Warm session ended by server: {}", response.error_msg);
+                            return true;
+                        }
+                    }
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => {
+                        tracing::debug!("Warm connection closed, reopening");
+                        return true;
+                    }
+                    Some(Ok(_)) => {}
+                },
+                _ = &mut expiry => {
+                    tracing::debug!("Warm session idle for {:?}, recycling", self.idle_timeout);
+                    let _ = warm.connection.write.close().await;
+                    return true;
+                }
+            }
+        }
+    }
+
+    /// Wait `delay` while turning away take requests; false on shutdown
+    async fn idle(&mut self, delay: Duration) -> bool {
+        let sleep = tokio::time::sleep(delay);
+        tokio::pin!(sleep);
+        loop {
+            tokio::select! {
+                _ = &mut sleep => return true,
+                request = self.requests.recv() => match request {
+                    Some(reply) => {
+                        let _ = reply.send(None);
+                    }
+                    None => return false,
+                },
+            }
+        }
+    }
+}
+
+fn read_device_id(params: &ConnectParams) -> String {
+    match params.credentials.read() {
+        Ok(creds) => creds.device_id.clone(),
+        Err(poisoned) => poisoned.into_inner().device_id.clone(),
+    }
+}
+
+/// Running latency figures, in milliseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct LatencyStat {
+    pub count: u32,
+    pub last_ms: u64,
+    pub total_ms: u64,
+    pub max_ms: u64,
+}
+
+impl LatencyStat {
+    pub fn record(&mut self, ms: u64) {
+        self.count += 1;
+        self.last_ms = ms;
+        self.total_ms += ms;
+        self.max_ms = self.max_ms.max(ms);
+    }
+
+    /// Mean of the recorded values, if any
+    pub fn average_ms(&self) -> Option<u64> {
+        (self.count > 0).then(|| self.total_ms / self.count as u64)
+    }
+}
+
+/// Time from `start_realtime` until a session is ready for audio
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LatencyMetrics {
+    /// Sessions served from the warm pool
+    pub warm: LatencyStat,
+    /// Sessions that had to connect and handshake on demand
+    pub cold: LatencyStat,
+    /// Handshakes done ahead of time by the pool
+    pub background_handshake: LatencyStat,
+}
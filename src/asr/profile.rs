@@ -0,0 +1,95 @@
+//! Device Profiles
+//!
+//! The app and device identity presented to the registration, settings and
+//! ASR endpoints. The built-in profile (from `constants`) impersonates a
+//! Pixel 7 Pro running the Doubao IME; others can be defined in the config
+//! file, e.g. to follow an app version bump without a rebuild.
+//!
+//! A device is registered under one profile and keeps using it, so the
+//! profile is stored together with its `DeviceCredentials`.
+
+use serde::{Deserialize, Serialize};
+
+use super::constants::*;
+
+/// Name of the built-in profile
+pub const DEFAULT_PROFILE_NAME: &str = "default";
+
+/// App and device identity; fields missing from a config entry fall back to
+/// the built-in profile
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeviceProfile {
+    /// Name shown in the settings and used to select the profile
+    pub name: String,
+
+    // App
+    pub aid: u32,
+    pub app_name: String,
+    pub version_code: u32,
+    pub version_name: String,
+    pub channel: String,
+    pub package: String,
+    pub user_agent: String,
+
+    // Device
+    pub device_platform: String,
+    pub os: String,
+    pub os_api: String,
+    pub os_version: String,
+    pub device_type: String,
+    pub device_brand: String,
+    pub device_model: String,
+    pub resolution: String,
+    pub dpi: String,
+    pub cpu_abi: String,
+    pub rom: String,
+    pub rom_version: String,
+    pub access: String,
+
+    // Locale
+    pub language: String,
+    pub region: String,
+    pub sim_region: String,
+    pub carrier_region: String,
+    /// Hours from UTC
+    pub timezone: i32,
+    pub tz_name: String,
+    /// Seconds from UTC
+    pub tz_offset: i32,
+}
+
+impl Default for DeviceProfile {
+    fn default() -> Self {
+        Self {
+            name: DEFAULT_PROFILE_NAME.to_string(),
+            aid: AID,
+            app_name: APP_NAME.to_string(),
+            version_code: VERSION_CODE,
+            version_name: VERSION_NAME.to_string(),
+            channel: CHANNEL.to_string(),
+            package: PACKAGE.to_string(),
+            user_agent: USER_AGENT.to_string(),
+            device_platform: DEVICE_PLATFORM.to_string(),
+            os: OS.to_string(),
+            os_api: OS_API.to_string(),
+            os_version: OS_VERSION.to_string(),
+            device_type: DEVICE_TYPE.to_string(),
+            device_brand: DEVICE_BRAND.to_string(),
+            device_model: DEVICE_MODEL.to_string(),
+            resolution: RESOLUTION.to_string(),
+            dpi: DPI.to_string(),
+            cpu_abi: "arm64-v8a".to_string(),
+            rom: ROM.to_string(),
+            rom_version: ROM_VERSION.to_string(),
+            access: ACCESS.to_string(),
+            language: LANGUAGE.to_string(),
+            region: "CN".to_string(),
+            sim_region: "cn".to_string(),
+            carrier_region: "cn".to_string(),
+            timezone: TIMEZONE,
+            tz_name: "Asia/Shanghai".to_string(),
+            tz_offset: TIMEZONE * 3600,
+        }
+    }
+}
@@ -3,10 +3,13 @@
 //! Handles building and parsing ASR protocol messages.
 
 use prost::Message;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use super::error::AsrError;
 use super::proto::{AsrRequest, AsrResponse as AsrResponseProto, FrameState};
+use super::result::{RecognitionSegment, ResultPayload};
+use super::stats::SessionStats;
 
 /// Response types from ASR server
 #[derive(Debug, Clone, PartialEq)]
@@ -31,7 +34,15 @@ pub struct AsrResponse {
     pub vad_start: bool,
     pub vad_finished: bool,
     pub packet_number: i32,
+    pub status_code: i32,
     pub error_msg: String,
+    /// Recognized segments with timing and alternatives, in order; `text`
+    /// is their concatenation
+    pub segments: Vec<RecognitionSegment>,
+    /// Typed error, set when `response_type` is `Error`
+    pub error: Option<AsrError>,
+    /// Set on the response that ends a live session
+    pub stats: Option<SessionStats>,
     pub raw_json: Option<Value>,
 }
 
@@ -44,12 +55,116 @@ impl Default for AsrResponse {
             vad_start: false,
             vad_finished: false,
             packet_number: -1,
+            status_code: 0,
             error_msg: String::new(),
+            segments: Vec::new(),
+            error: None,
+            stats: None,
             raw_json: None,
         }
     }
 }
 
+/// User-tunable options sent with StartSession
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionOptions {
+    pub enable_punctuation: bool,
+    pub enable_speech_rejection: bool,
+    pub enable_asr_twopass: bool,
+    pub enable_asr_threepass: bool,
+    pub input_mode: String,
+    /// Package name reported as the app being typed into
+    pub app_name: String,
+    /// User vocabulary sent as the session's hotword context
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hotwords: Vec<Hotword>,
+    /// Recognition language; also selects how results are formatted
+    #[serde(default)]
+    pub language: Language,
+}
+
+/// Recognition language (`general.language` in the config)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    #[serde(rename = "zh-CN")]
+    ZhCn,
+    #[serde(rename = "en-US")]
+    EnUs,
+    /// Chinese with embedded English, the IME's default model
+    #[serde(rename = "mixed")]
+    Mixed,
+}
+
+impl Language {
+    pub const ALL: [Language; 3] = [Language::ZhCn, Language::EnUs, Language::Mixed];
+
+    /// Config value, e.g. "zh-CN"
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::ZhCn => "zh-CN",
+            Language::EnUs => "en-US",
+            Language::Mixed => "mixed",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|l| l.code().eq_ignore_ascii_case(code.trim()))
+    }
+
+    /// Name shown in menus
+    pub fn label(self) -> &'static str {
+        match self {
+            Language::ZhCn => "中文",
+            Language::EnUs => "English",
+            Language::Mixed => "中英混合",
+        }
+    }
+
+    /// The language after this one, for switching in a cycle
+    pub fn next(self) -> Self {
+        match self {
+            Language::ZhCn => Language::EnUs,
+            Language::EnUs => Language::Mixed,
+            Language::Mixed => Language::ZhCn,
+        }
+    }
+
+    /// Value of the StartSession `language` field; mixed leaves it unset so
+    /// the server uses its default model
+    fn session_code(self) -> Option<String> {
+        match self {
+            Language::Mixed => None,
+            language => Some(language.code().to_string()),
+        }
+    }
+}
+
+/// Term the recognizer should favor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hotword {
+    pub word: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<f32>,
+}
+
+impl Default for SessionOptions {
+    fn default() -> Self {
+        Self {
+            enable_punctuation: true,
+            enable_speech_rejection: false,
+            enable_asr_twopass: true,
+            enable_asr_threepass: true,
+            input_mode: "tool".to_string(),
+            app_name: "com.android.chrome".to_string(),
+            hotwords: Vec::new(),
+            language: Language::default(),
+        }
+    }
+}
+
 /// Session configuration for ASR
 #[derive(Debug, Serialize)]
 pub struct SessionConfig {
@@ -57,6 +172,10 @@ pub struct SessionConfig {
     pub enable_punctuation: bool,
     pub enable_speech_rejection: bool,
     pub extra: SessionExtra,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub corpus: Option<SessionCorpus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -76,24 +195,47 @@ pub struct SessionExtra {
     pub input_mode: String,
 }
 
+/// Recognition context, in the layout of Volcengine's streaming ASR API:
+/// `context` is itself a JSON string such as `{"hotwords":[{"word":"..."}]}`.
+/// Whether the IME endpoint honors it is unverified, so it is only sent
+/// when hotwords are enabled.
+#[derive(Debug, Serialize)]
+pub struct SessionCorpus {
+    pub context: String,
+}
+
+impl SessionCorpus {
+    fn from_hotwords(hotwords: &[Hotword]) -> Option<Self> {
+        if hotwords.is_empty() {
+            return None;
+        }
+        let context = serde_json::json!({ "hotwords": hotwords });
+        Some(Self {
+            context: context.to_string(),
+        })
+    }
+}
+
 impl SessionConfig {
-    pub fn new(device_id: &str) -> Self {
+    pub fn new(device_id: &str, options: &SessionOptions) -> Self {
         Self {
             audio_info: AudioInfo {
                 channel: 1,
                 format: "speech_opus".to_string(),
                 sample_rate: 16000,
             },
-            enable_punctuation: true,
-            enable_speech_rejection: false,
+            enable_punctuation: options.enable_punctuation,
+            enable_speech_rejection: options.enable_speech_rejection,
             extra: SessionExtra {
-                app_name: "com.android.chrome".to_string(),
+                app_name: options.app_name.clone(),
                 cell_compress_rate: 8,
                 did: device_id.to_string(),
-                enable_asr_threepass: true,
-                enable_asr_twopass: true,
-                input_mode: "tool".to_string(),
+                enable_asr_threepass: options.enable_asr_threepass,
+                enable_asr_twopass: options.enable_asr_twopass,
+                input_mode: options.input_mode.clone(),
             },
+            corpus: SessionCorpus::from_hotwords(&options.hotwords),
+            language: options.language.session_code(),
         }
     }
 }
@@ -173,6 +315,7 @@ pub fn parse_response(data: &[u8]) -> AsrResponse {
             return AsrResponse {
                 response_type: ResponseType::Error,
                 error_msg: format!("Decode error: {}", e),
+                error: Some(AsrError::Decode(e)),
                 ..Default::default()
             };
         }
@@ -205,7 +348,12 @@ pub fn parse_response(data: &[u8]) -> AsrResponse {
         "TaskFailed" | "SessionFailed" => {
             return AsrResponse {
                 response_type: ResponseType::Error,
+                status_code: pb.status_code,
                 error_msg: status_message.clone(),
+                error: Some(AsrError::from_status(
+                    pb.status_code,
+                    status_message.clone(),
+                )),
                 ..Default::default()
             };
         }
@@ -230,25 +378,30 @@ pub fn parse_response(data: &[u8]) -> AsrResponse {
         }
     };
 
-    let results = json_data.get("results");
-    let extra = json_data.get("extra").cloned().unwrap_or(Value::Null);
+    let payload = match ResultPayload::deserialize(&json_data) {
+        Ok(payload) => payload,
+        Err(e) => {
+            tracing::warn!("Unexpected result_json layout: {}", e);
+            return AsrResponse {
+                response_type: ResponseType::Unknown,
+                raw_json: Some(json_data),
+                ..Default::default()
+            };
+        }
+    };
 
     // No results - might be heartbeat
-    if results.is_none() {
-        let packet_number = extra
-            .get("packet_number")
-            .and_then(|v| v.as_i64())
-            .unwrap_or(-1) as i32;
+    let Some(segments) = payload.results else {
         return AsrResponse {
             response_type: ResponseType::Heartbeat,
-            packet_number,
+            packet_number: payload.extra.packet_number.unwrap_or(-1) as i32,
             raw_json: Some(json_data),
             ..Default::default()
         };
-    }
+    };
 
     // Check for VAD start
-    if extra.get("vad_start").and_then(|v| v.as_bool()).unwrap_or(false) {
+    if payload.extra.vad_start {
         return AsrResponse {
             response_type: ResponseType::VadStart,
             vad_start: true,
@@ -257,41 +410,24 @@ pub fn parse_response(data: &[u8]) -> AsrResponse {
         };
     }
 
-    // Parse recognition results
-    let results = results.unwrap();
-    let mut text = String::new();
-    let mut is_interim = true;
-    let mut vad_finished = false;
-    let mut nonstream_result = false;
-
-    if let Some(results_array) = results.as_array() {
-        for r in results_array {
-            if let Some(t) = r.get("text").and_then(|v| v.as_str()) {
-                text = t.to_string();
-            }
-            if r.get("is_interim").and_then(|v| v.as_bool()) == Some(false) {
-                is_interim = false;
-            }
-            if r.get("is_vad_finished").and_then(|v| v.as_bool()) == Some(true) {
-                vad_finished = true;
-            }
-            if r.get("extra")
-                .and_then(|e| e.get("nonstream_result"))
-                .and_then(|v| v.as_bool())
-                == Some(true)
-            {
-                nonstream_result = true;
-            }
-        }
+    // Parse recognition results: segments keep their own finality, the
+    // response is only final once every segment in it is
+    let mut segments = segments;
+    if segments.iter().all(|s| s.index.is_some()) {
+        segments.sort_by_key(|s| s.index);
     }
+    let text: String = segments.iter().map(|s| s.text.as_str()).collect();
+    let vad_finished = segments.last().is_some_and(|s| s.is_vad_finished);
+    let all_final = !segments.is_empty() && segments.iter().all(|s| s.is_final());
 
     // Determine response type
-    if nonstream_result || (!is_interim && vad_finished) {
+    if all_final {
         AsrResponse {
             response_type: ResponseType::FinalResult,
             text,
             is_final: true,
             vad_finished,
+            segments,
             raw_json: Some(json_data),
             ..Default::default()
         }
@@ -300,6 +436,7 @@ pub fn parse_response(data: &[u8]) -> AsrResponse {
             response_type: ResponseType::InterimResult,
             text,
             is_final: false,
+            segments,
             raw_json: Some(json_data),
             ..Default::default()
         }
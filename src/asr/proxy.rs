@@ -0,0 +1,370 @@
+//! Proxy Support
+//!
+//! Outbound proxy for the registration/settings HTTP requests and the ASR
+//! WebSocket. HTTP proxies are used through `CONNECT` tunnels, SOCKS5
+//! proxies through a SOCKS handshake (`socks5h://` resolves the target
+//! host on the proxy). Hosts matching `no_proxy` are dialed directly.
+//!
+//! Settings come from the `[network]` config section, falling back to the
+//! standard `HTTPS_PROXY` / `ALL_PROXY` / `HTTP_PROXY` / `NO_PROXY`
+//! environment variables.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use reqwest::Url;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_socks::tcp::Socks5Stream;
+
+use super::error::{AsrError, AsrResult};
+
+/// Largest `CONNECT` reply header accepted from an HTTP proxy
+const MAX_CONNECT_REPLY: usize = 8192;
+
+/// How the proxy is spoken to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyScheme {
+    /// HTTP proxy, tunneling with `CONNECT`
+    Http,
+    /// SOCKS5, target resolved locally
+    Socks5,
+    /// SOCKS5, target resolved by the proxy
+    Socks5h,
+}
+
+/// A single proxy server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Proxy {
+    pub scheme: ProxyScheme,
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl Proxy {
+    /// Parse `http://[user:pass@]host[:port]` or `socks5[h]://...`
+    ///
+    /// A URL without a scheme is taken as an HTTP proxy.
+    pub fn parse(url: &str) -> AsrResult<Self> {
+        let url = url.trim();
+        let with_scheme = if url.contains("://") {
+            url.to_string()
+        } else {
+            format!("http://{}", url)
+        };
+        let parsed = Url::parse(&with_scheme)
+            .map_err(|e| AsrError::InvalidEndpoint(format!("Proxy '{}': {}", url, e)))?;
+
+        let (scheme, default_port) = match parsed.scheme() {
+            "http" => (ProxyScheme::Http, 8080),
+            "socks5" => (ProxyScheme::Socks5, 1080),
+            "socks5h" => (ProxyScheme::Socks5h, 1080),
+            other => {
+                return Err(AsrError::InvalidEndpoint(format!(
+                    "Unsupported proxy scheme '{}' (expected http, socks5 or socks5h)",
+                    other
+                )))
+            }
+        };
+        let host = parsed
+            .host_str()
+            .filter(|h| !h.is_empty())
+            .ok_or_else(|| AsrError::InvalidEndpoint(format!("Proxy '{}' has no host", url)))?
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string();
+
+        Ok(Self {
+            scheme,
+            host,
+            port: parsed.port().unwrap_or(default_port),
+            username: Some(parsed.username())
+                .filter(|u| !u.is_empty())
+                .map(percent_decode),
+            password: parsed.password().map(percent_decode),
+        })
+    }
+
+    /// Use these credentials instead of any given in the URL
+    pub fn with_auth(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.username = Some(username.into());
+        self.password = Some(password.into());
+        self
+    }
+
+    /// Proxy URL without credentials, for logging
+    pub fn display_url(&self) -> String {
+        let scheme = match self.scheme {
+            ProxyScheme::Http => "http",
+            ProxyScheme::Socks5 => "socks5",
+            ProxyScheme::Socks5h => "socks5h",
+        };
+        format!("{}://{}:{}", scheme, self.host, self.port)
+    }
+
+    /// Open a TCP stream to `host:port` through the proxy
+    pub async fn tunnel(&self, host: &str, port: u16) -> AsrResult<TcpStream> {
+        let stream = TcpStream::connect((self.host.as_str(), self.port))
+            .await
+            .map_err(|e| {
+                AsrError::Network(format!("Proxy {} unreachable: {}", self.display_url(), e))
+            })?;
+
+        match self.scheme {
+            ProxyScheme::Http => self.http_connect(stream, host, port).await,
+            ProxyScheme::Socks5 => {
+                let addr = tokio::net::lookup_host((host, port))
+                    .await
+                    .ok()
+                    .and_then(|mut addrs| addrs.next())
+                    .ok_or_else(|| AsrError::Network(format!("Cannot resolve {}", host)))?;
+                self.socks5_connect(stream, addr).await
+            }
+            ProxyScheme::Socks5h => self.socks5_connect(stream, (host, port)).await,
+        }
+    }
+
+    /// `CONNECT host:port` on an HTTP proxy
+    async fn http_connect(
+        &self,
+        mut stream: TcpStream,
+        host: &str,
+        port: u16,
+    ) -> AsrResult<TcpStream> {
+        let authority = if host.contains(':') {
+            format!("[{}]:{}", host, port)
+        } else {
+            format!("{}:{}", host, port)
+        };
+        let mut request = format!(
+            "CONNECT {0} HTTP/1.1\r\nHost: {0}\r\nProxy-Connection: keep-alive\r\n",
+            authority
+        );
+        if let Some(ref username) = self.username {
+            let password = self.password.as_deref().unwrap_or("");
+            let credentials = BASE64.encode(format!("{}:{}", username, password));
+            request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", credentials));
+        }
+        request.push_str("\r\n");
+        stream
+            .write_all(request.as_bytes())
+            .await
+            .map_err(network)?;
+
+        // Read byte by byte so nothing past the header is consumed
+        let mut reply = Vec::with_capacity(256);
+        while !reply.ends_with(b"\r\n\r\n") {
+            if reply.len() >= MAX_CONNECT_REPLY {
+                return Err(AsrError::Proxy("CONNECT reply too large".to_string()));
+            }
+            let mut byte = [0u8; 1];
+            if stream.read(&mut byte).await.map_err(network)? == 0 {
+                return Err(AsrError::Network(
+                    "Proxy closed the connection during CONNECT".to_string(),
+                ));
+            }
+            reply.push(byte[0]);
+        }
+
+        let reply = String::from_utf8_lossy(&reply);
+        let status_line = reply.lines().next().unwrap_or_default();
+        let status = status_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|code| code.parse::<u16>().ok());
+        match status {
+            Some(200..=299) => Ok(stream),
+            Some(407) => Err(AsrError::Proxy(
+                "Proxy authentication required (check the proxy username/password)".to_string(),
+            )),
+            _ => Err(AsrError::Proxy(format!(
+                "CONNECT {} refused: {}",
+                authority, status_line
+            ))),
+        }
+    }
+
+    async fn socks5_connect<'t, T>(&self, stream: TcpStream, target: T) -> AsrResult<TcpStream>
+    where
+        T: tokio_socks::IntoTargetAddr<'t>,
+    {
+        let result = match self.username {
+            Some(ref username) => {
+                let password = self.password.as_deref().unwrap_or("");
+                Socks5Stream::connect_with_password_and_socket(stream, target, username, password)
+                    .await
+            }
+            None => Socks5Stream::connect_with_socket(stream, target).await,
+        };
+        result
+            .map(Socks5Stream::into_inner)
+            .map_err(|e| AsrError::Proxy(format!("SOCKS5: {}", e)))
+    }
+}
+
+/// Proxy and bypass list used for all outbound connections
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProxySettings {
+    /// `None` connects directly
+    pub proxy: Option<Proxy>,
+    /// Hosts (and their subdomains) reached without the proxy; `*` matches all
+    pub no_proxy: Vec<String>,
+}
+
+impl ProxySettings {
+    /// Route everything through `proxy`
+    pub fn new(proxy: Proxy) -> Self {
+        Self {
+            proxy: Some(proxy),
+            no_proxy: Vec::new(),
+        }
+    }
+
+    /// Add hosts that bypass the proxy
+    pub fn with_no_proxy<I, S>(mut self, hosts: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.no_proxy.extend(hosts.into_iter().map(Into::into));
+        self
+    }
+
+    /// Settings from `HTTPS_PROXY` / `ALL_PROXY` / `HTTP_PROXY` and `NO_PROXY`
+    /// (upper- or lowercase)
+    pub fn from_env() -> AsrResult<Self> {
+        let var = |name: &str| {
+            std::env::var(name)
+                .or_else(|_| std::env::var(name.to_lowercase()))
+                .ok()
+                .filter(|v| !v.trim().is_empty())
+        };
+
+        let proxy = ["HTTPS_PROXY", "ALL_PROXY", "HTTP_PROXY"]
+            .iter()
+            .find_map(|name| var(name))
+            .map(|url| Proxy::parse(&url))
+            .transpose()?;
+        Ok(Self {
+            proxy,
+            no_proxy: var("NO_PROXY")
+                .map(|list| split_no_proxy(&list))
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Proxy to use for `url`, or `None` to connect directly
+    pub fn proxy_for(&self, url: &str) -> Option<&Proxy> {
+        let proxy = self.proxy.as_ref()?;
+        let host = Url::parse(url).ok()?.host_str()?.to_lowercase();
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if self.bypasses(host) {
+            None
+        } else {
+            Some(proxy)
+        }
+    }
+
+    fn bypasses(&self, host: &str) -> bool {
+        self.no_proxy.iter().any(|entry| {
+            let entry = entry.trim().to_lowercase();
+            // Ports in NO_PROXY entries are ignored
+            let entry = match entry.rsplit_once(':') {
+                Some((name, port)) if !name.contains(':') && port.parse::<u16>().is_ok() => {
+                    name.to_string()
+                }
+                _ => entry,
+            };
+            let entry = entry.trim_start_matches("*.").trim_start_matches('.');
+            entry == "*"
+                || host == entry
+                || (host.len() > entry.len()
+                    && host.ends_with(entry)
+                    && host.as_bytes()[host.len() - entry.len() - 1] == b'.')
+        })
+    }
+
+    /// HTTP client honoring these settings
+    pub fn http_client(&self) -> AsrResult<reqwest::Client> {
+        // Never let reqwest pick up the environment on its own
+        let builder = reqwest::Client::builder().no_proxy();
+        let builder = match self.proxy {
+            Some(ref proxy) => {
+                let settings = self.clone();
+                let url = proxy_url(proxy);
+                builder.proxy(reqwest::Proxy::custom(move |target| {
+                    settings.proxy_for(target.as_str()).map(|_| url.clone())
+                }))
+            }
+            None => builder,
+        };
+        Ok(builder.build()?)
+    }
+}
+
+/// Split a comma- or space-separated `NO_PROXY` list
+pub fn split_no_proxy(list: &str) -> Vec<String> {
+    list.split([',', ' '])
+        .map(str::trim)
+        .filter(|h| !h.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Proxy URL (with credentials) in the form reqwest expects
+fn proxy_url(proxy: &Proxy) -> String {
+    let auth = match proxy.username {
+        Some(ref username) => format!(
+            "{}:{}@",
+            percent_encode(username),
+            percent_encode(proxy.password.as_deref().unwrap_or(""))
+        ),
+        None => String::new(),
+    };
+    let host = if proxy.host.contains(':') {
+        format!("[{}]", proxy.host)
+    } else {
+        proxy.host.clone()
+    };
+    let scheme = match proxy.scheme {
+        ProxyScheme::Http => "http",
+        ProxyScheme::Socks5 => "socks5",
+        ProxyScheme::Socks5h => "socks5h",
+    };
+    format!("{}://{}{}:{}", scheme, auth, host, proxy.port)
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = value.get(i + 1..i + 3).unwrap_or_default();
+            if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn network(e: std::io::Error) -> AsrError {
+    AsrError::Network(e.to_string())
+}
@@ -0,0 +1,203 @@
+//! Session Recorder
+//!
+//! Opt-in wire-level capture of ASR sessions. Every protobuf frame sent to or
+//! received from the server is appended to a JSON-lines file together with
+//! its direction and the time since the session started, so a problematic
+//! session can be replayed offline through `parse_response` and the
+//! `VoiceController` and kept as a regression fixture.
+//!
+//! Tokens are blanked out of recorded requests; audio is kept as-is.
+
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use prost::Message as _;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+use super::proto::{AsrRequest, AsrResponse as AsrResponseProto};
+use super::protocol::{parse_response, AsrResponse, ResponseType};
+
+/// Which way a captured frame travelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// Client → server (`AsrRequest`)
+    Out,
+    /// Server → client (`AsrResponse`)
+    In,
+}
+
+/// One line of a capture file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapturedFrame {
+    /// Milliseconds since the recorder was created
+    pub t_ms: u64,
+    pub dir: Direction,
+    /// Method name or message type, for reading captures by eye
+    #[serde(default)]
+    pub kind: String,
+    /// Base64 of the raw protobuf frame
+    pub data: String,
+}
+
+impl CapturedFrame {
+    /// Raw protobuf bytes of the frame
+    pub fn bytes(&self) -> Result<Vec<u8>> {
+        BASE64
+            .decode(&self.data)
+            .map_err(|e| anyhow!("Invalid frame data at {}ms: {}", self.t_ms, e))
+    }
+}
+
+/// Writes the frames of one session to a capture file
+pub struct SessionRecorder {
+    path: PathBuf,
+    started: Instant,
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl SessionRecorder {
+    /// Create (or truncate) the capture file at `path`
+    pub fn create(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = File::create(&path)
+            .with_context(|| format!("Failed to create capture file {:?}", path))?;
+
+        Ok(Self {
+            path,
+            started: Instant::now(),
+            writer: Mutex::new(BufWriter::new(file)),
+        })
+    }
+
+    /// Path of the capture file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Record a frame sent to the server
+    pub fn record_outgoing(&self, data: &[u8]) {
+        let (kind, data) = match AsrRequest::decode(data) {
+            Ok(mut request) => {
+                request.token.clear();
+                (request.method_name.clone(), request.encode_to_vec())
+            }
+            Err(_) => (String::new(), data.to_vec()),
+        };
+        self.write(Direction::Out, kind, &data);
+    }
+
+    /// Record a frame received from the server
+    pub fn record_incoming(&self, data: &[u8]) {
+        let kind = match AsrResponseProto::decode(data) {
+            Ok(response) if response.message_type.is_empty() => "Result".to_string(),
+            Ok(response) => response.message_type,
+            Err(_) => String::new(),
+        };
+        self.write(Direction::In, kind, data);
+    }
+
+    fn write(&self, dir: Direction, kind: String, data: &[u8]) {
+        let frame = CapturedFrame {
+            t_ms: self.started.elapsed().as_millis() as u64,
+            dir,
+            kind,
+            data: BASE64.encode(data),
+        };
+        let Ok(line) = serde_json::to_string(&frame) else {
+            return;
+        };
+
+        if let Ok(mut writer) = self.writer.lock() {
+            // A failed write must never break the live session
+            if writeln!(writer, "{}", line)
+                .and_then(|_| writer.flush())
+                .is_err()
+            {
+                tracing::warn!("Failed to write to capture file {:?}", self.path);
+            }
+        }
+    }
+}
+
+/// Read every frame of a capture file
+pub fn load_capture(path: impl AsRef<Path>) -> Result<Vec<CapturedFrame>> {
+    let path = path.as_ref();
+    let file =
+        File::open(path).with_context(|| format!("Failed to open capture file {:?}", path))?;
+
+    let mut frames = Vec::new();
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let frame: CapturedFrame = serde_json::from_str(&line)
+            .with_context(|| format!("Invalid capture line {} in {:?}", index + 1, path))?;
+        frames.push(frame);
+    }
+    Ok(frames)
+}
+
+/// Feed the server side of a capture back as a stream of `AsrResponse`s
+///
+/// Responses are filtered the same way as in a live session (handshake
+/// replies and heartbeats are dropped, the stream ends at SessionFinished or
+/// Error), so the receiver can be handed to `VoiceController::start_replay`.
+/// With `paced`, the original timing between frames is reproduced.
+pub fn replay_capture(
+    frames: Vec<CapturedFrame>,
+    paced: bool,
+) -> Result<mpsc::Receiver<AsrResponse>> {
+    let incoming = frames
+        .into_iter()
+        .filter(|f| f.dir == Direction::In)
+        .map(|f| Ok((f.t_ms, f.bytes()?)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let (result_tx, result_rx) = mpsc::channel::<AsrResponse>(100);
+    tokio::spawn(async move {
+        let mut last_t_ms = incoming.first().map(|(t, _)| *t).unwrap_or(0);
+
+        for (t_ms, data) in incoming {
+            if paced && t_ms > last_t_ms {
+                tokio::time::sleep(Duration::from_millis(t_ms - last_t_ms)).await;
+            }
+            last_t_ms = t_ms;
+
+            let response = parse_response(&data);
+            match response.response_type {
+                ResponseType::TaskStarted
+                | ResponseType::SessionStarted
+                | ResponseType::Heartbeat => continue,
+                ResponseType::Error | ResponseType::SessionFinished => {
+                    let _ = result_tx.send(response).await;
+                    return;
+                }
+                _ => {
+                    if result_tx.send(response).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+
+        // Capture ended without SessionFinished (e.g. the app was killed)
+        let _ = result_tx
+            .send(AsrResponse {
+                response_type: ResponseType::SessionFinished,
+                ..Default::default()
+            })
+            .await;
+    });
+
+    Ok(result_rx)
+}
@@ -0,0 +1,258 @@
+//! Recognition Result Types
+//!
+//! Typed view of the `result_json` payload carried by ASR responses.
+//! The schema is not documented, so every field is optional, unknown
+//! fields are ignored and a field of an unexpected type (a number sent as a
+//! string, a `null` list, ...) is read leniently instead of failing the whole
+//! payload; times are milliseconds from the start of the session.
+
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+/// Top-level `result_json` object
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResultPayload {
+    /// Absent on heartbeats
+    #[serde(default, deserialize_with = "lenient_segments")]
+    pub results: Option<Vec<RecognitionSegment>>,
+    #[serde(default, deserialize_with = "null_default")]
+    pub extra: PayloadExtra,
+}
+
+/// Session-level `extra` of a result payload
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PayloadExtra {
+    #[serde(default, deserialize_with = "lenient_bool")]
+    pub vad_start: bool,
+    #[serde(default, deserialize_with = "lenient_i64")]
+    pub packet_number: Option<i64>,
+}
+
+/// One recognized segment (roughly a sentence)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RecognitionSegment {
+    #[serde(default, deserialize_with = "null_default")]
+    pub text: String,
+    /// Position of the segment within the session, when the server sends it
+    #[serde(default, deserialize_with = "lenient_index")]
+    pub index: Option<u32>,
+    #[serde(default, deserialize_with = "lenient_ms")]
+    pub start_time: Option<u64>,
+    #[serde(default, deserialize_with = "lenient_ms")]
+    pub end_time: Option<u64>,
+    /// Missing means the segment may still change
+    #[serde(default, deserialize_with = "lenient_opt_bool")]
+    pub is_interim: Option<bool>,
+    #[serde(default, deserialize_with = "lenient_bool")]
+    pub is_vad_finished: bool,
+    #[serde(default, deserialize_with = "null_default")]
+    pub words: Vec<WordTiming>,
+    #[serde(default, deserialize_with = "null_default")]
+    pub alternatives: Vec<Alternative>,
+    #[serde(default, deserialize_with = "null_default")]
+    pub extra: SegmentExtra,
+}
+
+/// Per-segment `extra`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SegmentExtra {
+    /// Set on results produced by the second (non-streaming) pass
+    #[serde(default, deserialize_with = "lenient_bool")]
+    pub nonstream_result: bool,
+}
+
+/// Timing of a single word or character
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WordTiming {
+    #[serde(default, alias = "text", deserialize_with = "null_default")]
+    pub word: String,
+    #[serde(default, deserialize_with = "lenient_ms")]
+    pub start_time: Option<u64>,
+    #[serde(default, deserialize_with = "lenient_ms")]
+    pub end_time: Option<u64>,
+}
+
+/// An alternative hypothesis for a segment
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Alternative {
+    #[serde(default, deserialize_with = "null_default")]
+    pub text: String,
+    #[serde(default, deserialize_with = "lenient_f64")]
+    pub confidence: Option<f64>,
+    #[serde(default, deserialize_with = "null_default")]
+    pub words: Vec<WordTiming>,
+}
+
+impl RecognitionSegment {
+    /// Whether the server has committed to this segment's text
+    pub fn is_final(&self) -> bool {
+        self.extra.nonstream_result || (self.is_interim == Some(false) && self.is_vad_finished)
+    }
+
+    /// Start of the segment, from its own time or its first word's
+    pub fn start_ms(&self) -> Option<u64> {
+        self.start_time
+            .or_else(|| self.words().first().and_then(|w| w.start_time))
+    }
+
+    /// End of the segment, from its own time or its last word's
+    pub fn end_ms(&self) -> Option<u64> {
+        self.end_time
+            .or_else(|| self.words().last().and_then(|w| w.end_time))
+    }
+
+    /// Word timings, taken from the best alternative if the segment has none
+    pub fn words(&self) -> &[WordTiming] {
+        if !self.words.is_empty() {
+            return &self.words;
+        }
+        self.alternatives
+            .first()
+            .map(|a| a.words.as_slice())
+            .unwrap_or(&[])
+    }
+}
+
+/// A scalar as the server may send it: numbers and flags sometimes arrive
+/// as strings, anything else is ignored
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Scalar {
+    Bool(bool),
+    Number(f64),
+    Text(String),
+    Other(IgnoredAny),
+}
+
+impl Scalar {
+    fn number(&self) -> Option<f64> {
+        let value = match self {
+            Scalar::Number(v) => *v,
+            Scalar::Text(s) => s.trim().parse().ok()?,
+            _ => return None,
+        };
+        value.is_finite().then_some(value)
+    }
+
+    fn flag(&self) -> Option<bool> {
+        match self {
+            Scalar::Bool(b) => Some(*b),
+            Scalar::Number(v) => Some(*v != 0.0),
+            Scalar::Text(s) => match s.trim() {
+                "true" | "1" => Some(true),
+                "false" | "0" => Some(false),
+                _ => None,
+            },
+            Scalar::Other(_) => None,
+        }
+    }
+}
+
+fn scalar<'de, D>(deserializer: D) -> Result<Option<Scalar>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<Scalar>::deserialize(deserializer)
+}
+
+/// Accept integer or fractional millisecond values, as numbers or strings;
+/// negative or unreadable values become `None`
+fn lenient_ms<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(scalar(deserializer)?
+        .and_then(|v| v.number())
+        .filter(|v| *v >= 0.0)
+        .map(|v| v.round() as u64))
+}
+
+/// Segment index; negative, fractional or unreadable values become `None`
+fn lenient_index<'de, D>(deserializer: D) -> Result<Option<u32>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(scalar(deserializer)?
+        .and_then(|v| v.number())
+        .filter(|v| *v >= 0.0 && v.fract() == 0.0 && *v <= u32::MAX as f64)
+        .map(|v| v as u32))
+}
+
+fn lenient_i64<'de, D>(deserializer: D) -> Result<Option<i64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(scalar(deserializer)?
+        .and_then(|v| v.number())
+        .filter(|v| v.fract() == 0.0)
+        .map(|v| v as i64))
+}
+
+fn lenient_f64<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(scalar(deserializer)?.and_then(|v| v.number()))
+}
+
+fn lenient_opt_bool<'de, D>(deserializer: D) -> Result<Option<bool>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(scalar(deserializer)?.and_then(|v| v.flag()))
+}
+
+fn lenient_bool<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(lenient_opt_bool(deserializer)?.unwrap_or(false))
+}
+
+/// Treat `null` like a missing field
+fn null_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// Segments one by one, so a segment of an unexpected shape keeps at least
+/// its text and finality instead of failing its siblings
+fn lenient_segments<'de, D>(deserializer: D) -> Result<Option<Vec<RecognitionSegment>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let Some(values) = Option::<Vec<Value>>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    let segments = values
+        .iter()
+        .map(|value| {
+            RecognitionSegment::deserialize(value).unwrap_or_else(|e| {
+                tracing::warn!("Unexpected segment layout: {}", e);
+                text_only_segment(value)
+            })
+        })
+        .collect();
+    Ok(Some(segments))
+}
+
+fn text_only_segment(value: &Value) -> RecognitionSegment {
+    let flag = |pointer: &str| value.pointer(pointer).and_then(Value::as_bool);
+    RecognitionSegment {
+        text: value
+            .get("text")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        is_interim: flag("/is_interim"),
+        is_vad_finished: flag("/is_vad_finished").unwrap_or(false),
+        extra: SegmentExtra {
+            nonstream_result: flag("/extra/nonstream_result").unwrap_or(false),
+        },
+        ..Default::default()
+    }
+}
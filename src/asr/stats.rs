@@ -0,0 +1,80 @@
+//! Session Statistics
+//!
+//! Latency and throughput figures for one dictation session. `AsrClient`
+//! fills in the connection and result timings and attaches them to the
+//! response that ends the session; `VoiceController` adds the frames the
+//! capture side had to drop.
+
+use serde::Serialize;
+use std::fmt;
+
+/// Per-session latency and throughput, times in milliseconds
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SessionStats {
+    /// Session came from the warm pool
+    pub warm: bool,
+    /// From `start_realtime` until audio could be streamed
+    pub setup_ms: u64,
+    /// WebSocket connect (TCP, TLS, proxy and upgrade); done ahead of time
+    /// for warm sessions
+    pub connect_ms: Option<u64>,
+    /// StartTask + StartSession round trips
+    pub handshake_ms: Option<u64>,
+    /// From the first audio frame sent until the first recognized text
+    pub first_interim_ms: Option<u64>,
+    /// From the end of the audio until the last final result
+    pub stop_to_final_ms: Option<u64>,
+    /// Audio frames sent to the server (replays after a reconnect excluded)
+    pub frames_sent: u64,
+    /// Audio frames dropped before reaching the client ("Channel full")
+    pub frames_dropped: u64,
+    /// Opus payload bytes sent
+    pub bytes_sent: u64,
+    /// Average audio upload rate over the streaming time
+    pub bytes_per_second: u64,
+    /// From session start until its last response
+    pub duration_ms: u64,
+    pub reconnects: u32,
+    /// Server sessions replaced in continuous mode
+    pub rollovers: u32,
+}
+
+impl SessionStats {
+    /// Compute the upload rate once streaming is over
+    pub(super) fn finish(&mut self, streaming_ms: u64) {
+        self.bytes_per_second = (self.bytes_sent * 1000)
+            .checked_div(streaming_ms)
+            .unwrap_or(0);
+    }
+}
+
+impl fmt::Display for SessionStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |value: Option<u64>| match value {
+            Some(ms) => format!("{}ms", ms),
+            None => "-".to_string(),
+        };
+        write!(
+            f,
+            "setup {}ms ({}), connect {}, handshake {}, first interim {}, stop→final {}, \
+             {} frames sent, {} dropped, {} B/s, {}ms total",
+            self.setup_ms,
+            if self.warm { "warm" } else { "cold" },
+            ms(self.connect_ms),
+            ms(self.handshake_ms),
+            ms(self.first_interim_ms),
+            ms(self.stop_to_final_ms),
+            self.frames_sent,
+            self.frames_dropped,
+            self.bytes_per_second,
+            self.duration_ms
+        )?;
+        if self.reconnects > 0 {
+            write!(f, ", {} reconnects", self.reconnects)?;
+        }
+        if self.rollovers > 0 {
+            write!(f, ", {} rollovers", self.rollovers)?;
+        }
+        Ok(())
+    }
+}
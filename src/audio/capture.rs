@@ -1,24 +1,150 @@
-//! Audio Capture using cpal
+//! Audio Capture
+//!
+//! An `AudioSource` (by default the input device, through cpal) feeds a
+//! lock-free ring buffer (see `pipeline`); a capture thread turns it into
+//! 20ms 16kHz mono frames and Opus-encodes them.
+//!
+//! Recording uses the source set with `set_source`, otherwise the device
+//! selected with `set_input_device` or the system default. If that device is
+//! missing at start, or disappears mid-recording, capture switches to the
+//! default device and reports a `DeviceFallback`.
+//!
+//! With VAD settings (`set_vad`) every frame goes through an `EnergyVad`:
+//! leading silence can be held back until speech starts, and enough
+//! trailing silence raises `silence_timed_out` for the controller to stop.
+//!
+//! Normally the microphone is opened by `start` and closed after `stop`.
+//! With a pre-roll (`set_preroll`) capture is always warm: one thread keeps
+//! the stream open and the last few hundred ms in memory, and `start` hands
+//! it a recording that begins with that audio.
 
 use anyhow::{anyhow, Result};
-use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::SampleFormat;
+use cpal::traits::{DeviceTrait, HostTrait};
+use serde::Serialize;
+use std::collections::VecDeque;
 use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
-use std::sync::mpsc as std_mpsc;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc as tokio_mpsc;
 
-use super::encoder::OpusEncoder;
+use super::encoder::{OpusEncoder, OpusSettings};
+use super::source::{AudioSource, CpalSource};
+use super::vad::{EnergyVad, VadSettings};
+use super::{FRAME_DURATION_MS, OPUS_CHANNELS, OPUS_SAMPLE_RATE};
+
+/// How long the capture thread sleeps when no whole frame is buffered yet
+const POLL_INTERVAL: Duration = Duration::from_millis(FRAME_DURATION_MS as u64 / 4);
+
+/// Without audio for this long, check whether the device is still there
+/// (not every backend reports a disconnect through the stream)
+const DEVICE_STALL_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Recording switched to the default input because the selected device
+/// could not be used
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceFallback {
+    /// The device that is missing or was disconnected
+    pub lost: String,
+    /// The device recording continues on
+    pub fallback: String,
+    /// Disconnected mid-recording, rather than missing at start
+    pub during_recording: bool,
+}
 
-// Opus encoder always uses 16kHz mono
-const OPUS_SAMPLE_RATE: u32 = 16000;
-const OPUS_CHANNELS: u16 = 1;
-const FRAME_DURATION_MS: u32 = 20;
+type DeviceFallbackCallback = Arc<dyn Fn(DeviceFallback) + Send + Sync + 'static>;
+type DeviceFallbackSlot = Arc<RwLock<Option<DeviceFallbackCallback>>>;
 
 pub struct AudioCapture {
+    status: CaptureStatus,
+    /// Name of the device to record from; `None` for the system default
+    input_device: RwLock<Option<String>>,
+    /// Records from this instead of an input device when set
+    source: RwLock<Option<Arc<dyn AudioSource>>>,
+    on_device_fallback: DeviceFallbackSlot,
+    /// `None` disables local VAD
+    vad: RwLock<Option<VadSettings>>,
+    opus: RwLock<OpusSettings>,
+    /// Pre-roll kept by always-warm capture; `None` opens the microphone
+    /// only while recording
+    preroll: RwLock<Option<Duration>>,
+    /// The always-warm capture thread, if running
+    warm: Mutex<Option<WarmCapture>>,
+    /// Pre-roll at the head of the current (or last) recording, in ms
+    preroll_sent_ms: AtomicU64,
+}
+
+/// Recording state shared with the capture thread
+#[derive(Clone)]
+struct CaptureStatus {
     is_recording: Arc<AtomicBool>,
     current_volume: Arc<AtomicU32>,
+    /// Frames of the current (or last) recording lost to a full channel
+    dropped_frames: Arc<AtomicU64>,
+    /// The current recording reached its VAD silence timeout
+    silence_timed_out: Arc<AtomicBool>,
+}
+
+/// What a capture thread records from
+struct CaptureSettings {
+    source: Arc<dyn AudioSource>,
+    on_device_fallback: DeviceFallbackSlot,
+}
+
+/// A recording requested by `start`
+struct PendingRecording {
+    tokio_tx: tokio_mpsc::Sender<Vec<u8>>,
+    vad: Option<VadSettings>,
+    opus: OpusSettings,
+}
+
+/// What a capture thread runs for
+enum CaptureMode {
+    /// One recording; the stream closes when it stops
+    Recording(PendingRecording),
+    /// Always warm: the stream stays open until stopped and recordings are
+    /// handed over through `WarmCapture`
+    Warm(WarmCapture),
+}
+
+/// Handle to an always-warm capture thread
+#[derive(Clone)]
+struct WarmCapture {
+    /// Keeps the thread (and the stream) running
+    running: Arc<AtomicBool>,
+    handoff: Arc<Mutex<Handoff>>,
+}
+
+/// Shared between `start` and the always-warm capture thread
+struct Handoff {
+    /// The most recent audio while no recording is active
+    preroll: RecentFrames,
+    /// Set by `start`, taken by the thread with its next frame
+    pending: Option<PendingRecording>,
+    /// The thread is sending a recording
+    recording: bool,
+    /// The thread has exited
+    closed: bool,
+}
+
+impl WarmCapture {
+    fn has_pending(&self) -> bool {
+        self.handoff.lock().is_ok_and(|h| h.pending.is_some())
+    }
+
+    /// Stop accepting recordings and discard the buffered audio; ends a
+    /// recording that was handed over but never finished
+    fn close(&self, status: &CaptureStatus) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Ok(mut handoff) = self.handoff.lock() {
+            handoff.closed = true;
+            handoff.preroll.frames.clear();
+            if handoff.pending.take().is_some() || handoff.recording {
+                status.is_recording.store(false, Ordering::SeqCst);
+            }
+            handoff.recording = false;
+        }
+    }
 }
 
 impl AudioCapture {
@@ -37,27 +163,284 @@ impl AudioCapture {
         }
 
         Ok(Self {
-            is_recording: Arc::new(AtomicBool::new(false)),
-            current_volume: Arc::new(AtomicU32::new(0)),
+            status: CaptureStatus {
+                is_recording: Arc::new(AtomicBool::new(false)),
+                current_volume: Arc::new(AtomicU32::new(0)),
+                dropped_frames: Arc::new(AtomicU64::new(0)),
+                silence_timed_out: Arc::new(AtomicBool::new(false)),
+            },
+            input_device: RwLock::new(None),
+            source: RwLock::new(None),
+            on_device_fallback: Arc::new(RwLock::new(None)),
+            vad: RwLock::new(None),
+            opus: RwLock::new(OpusSettings::default()),
+            preroll: RwLock::new(None),
+            warm: Mutex::new(None),
+            preroll_sent_ms: AtomicU64::new(0),
+        })
+    }
+
+    /// Record from the named device (`[audio] device`)
+    pub fn with_input_device(self, name: Option<String>) -> Self {
+        self.set_input_device(name);
+        self
+    }
+
+    /// Select the device to record from, by name; `None` for the system
+    /// default. Takes effect with the next recording.
+    pub fn set_input_device(&self, name: Option<String>) {
+        let name = name.filter(|n| !n.trim().is_empty());
+        tracing::info!("Input device: {}", name.as_deref().unwrap_or("(default)"));
+        let changed = match self.input_device.write() {
+            Ok(mut device) => std::mem::replace(&mut *device, name) != *device,
+            Err(_) => false,
+        };
+        // A warm stream moves to the new device right away
+        if changed && self.is_warm() {
+            self.restart_warm();
+        }
+    }
+
+    /// The selected device name, `None` for the system default
+    pub fn input_device(&self) -> Option<String> {
+        self.input_device.read().ok().and_then(|d| d.clone())
+    }
+
+    /// Record from `source` instead of an input device
+    pub fn with_source(self, source: Arc<dyn AudioSource>) -> Self {
+        self.set_source(Some(source));
+        self
+    }
+
+    /// Record from `source`, or from the selected input device for `None`.
+    /// Takes effect with the next recording.
+    pub fn set_source(&self, source: Option<Arc<dyn AudioSource>>) {
+        if let Some(ref source) = source {
+            tracing::info!("Audio source: {}", source.name());
+        }
+        if let Ok(mut current) = self.source.write() {
+            *current = source;
+        }
+        if self.is_warm() {
+            self.restart_warm();
+        }
+    }
+
+    /// The source set with `set_source`, `None` when recording from an
+    /// input device
+    pub fn source(&self) -> Option<Arc<dyn AudioSource>> {
+        self.source.read().ok().and_then(|s| s.clone())
+    }
+
+    /// Set callback for switches to the default device
+    pub fn set_on_device_fallback<F>(&self, callback: F)
+    where
+        F: Fn(DeviceFallback) + Send + Sync + 'static,
+    {
+        if let Ok(mut slot) = self.on_device_fallback.write() {
+            *slot = Some(Arc::new(callback));
+        }
+    }
+
+    /// Run local VAD with these settings (`[audio.vad]`)
+    pub fn with_vad(self, vad: Option<VadSettings>) -> Self {
+        self.set_vad(vad);
+        self
+    }
+
+    /// Enable or disable local VAD from the next recording on
+    pub fn set_vad(&self, vad: Option<VadSettings>) {
+        if let Ok(mut current) = self.vad.write() {
+            *current = vad;
+        }
+    }
+
+    /// The local VAD settings, `None` when disabled
+    pub fn vad(&self) -> Option<VadSettings> {
+        self.vad.read().ok().and_then(|v| v.clone())
+    }
+
+    /// Encode with these settings (`[audio.opus]`)
+    pub fn with_opus(self, settings: OpusSettings) -> Self {
+        self.set_opus(settings);
+        self
+    }
+
+    /// Change the encoder settings from the next recording on
+    pub fn set_opus(&self, settings: OpusSettings) {
+        tracing::info!("Opus encoder: {}", settings.describe());
+        if let Ok(mut current) = self.opus.write() {
+            *current = settings;
+        }
+    }
+
+    pub fn opus(&self) -> OpusSettings {
+        self.opus.read().map(|o| o.clone()).unwrap_or_default()
+    }
+
+    /// Keep the microphone open with this much pre-roll (`[audio]
+    /// always_warm`)
+    pub fn with_preroll(self, preroll: Option<Duration>) -> Self {
+        self.set_preroll(preroll);
+        self
+    }
+
+    /// Start always-warm capture, keeping the last `preroll` of audio in
+    /// memory for the next recording; `None` stops it and discards the
+    /// buffered audio
+    pub fn set_preroll(&self, preroll: Option<Duration>) {
+        if self.preroll() == preroll && self.is_warm() == preroll.is_some() {
+            return;
+        }
+        if let Ok(mut current) = self.preroll.write() {
+            *current = preroll;
+        }
+        self.restart_warm();
+    }
+
+    /// The configured pre-roll, `None` when not always warm
+    pub fn preroll(&self) -> Option<Duration> {
+        self.preroll.read().ok().and_then(|p| *p)
+    }
+
+    /// The microphone is open between recordings (always-warm capture)
+    pub fn is_warm(&self) -> bool {
+        self.warm.lock().is_ok_and(|warm| {
+            warm.as_ref()
+                .is_some_and(|w| w.running.load(Ordering::SeqCst))
         })
     }
 
+    /// Audio from before `start` at the head of the current (or last)
+    /// recording
+    pub fn preroll_sent(&self) -> Duration {
+        Duration::from_millis(self.preroll_sent_ms.load(Ordering::SeqCst))
+    }
+
+    /// The current recording has been silent for longer than the VAD
+    /// silence (or no-speech) timeout
+    pub fn silence_timed_out(&self) -> bool {
+        self.status.silence_timed_out.load(Ordering::SeqCst)
+    }
+
     pub fn is_recording(&self) -> bool {
-        self.is_recording.load(Ordering::SeqCst)
+        self.status.is_recording.load(Ordering::SeqCst)
     }
 
     pub fn get_volume(&self) -> u32 {
-        self.current_volume.load(Ordering::SeqCst)
+        self.status.current_volume.load(Ordering::SeqCst)
+    }
+
+    /// Frames dropped in the current (or last) recording because the
+    /// consumer fell behind
+    pub fn dropped_frames(&self) -> u64 {
+        self.status.dropped_frames.load(Ordering::SeqCst)
     }
 
     pub fn start(&self) -> Result<tokio_mpsc::Receiver<Vec<u8>>> {
-        if self.is_recording.swap(true, Ordering::SeqCst) {
+        if self.status.is_recording.swap(true, Ordering::SeqCst) {
             return Err(anyhow!("Already recording"));
         }
 
         let (tokio_tx, tokio_rx) = tokio_mpsc::channel::<Vec<u8>>(100);
-        let is_recording = self.is_recording.clone();
-        let current_volume = self.current_volume.clone();
+        self.status.dropped_frames.store(0, Ordering::SeqCst);
+        self.status.silence_timed_out.store(false, Ordering::SeqCst);
+        let pending = PendingRecording {
+            tokio_tx,
+            vad: self.vad(),
+            opus: self.opus(),
+        };
+
+        let pending = match self.hand_to_warm(pending) {
+            Ok(preroll) => {
+                self.preroll_sent_ms
+                    .store(preroll.as_millis() as u64, Ordering::SeqCst);
+                tracing::info!("Audio capture started ({}ms pre-roll)", preroll.as_millis());
+                return Ok(tokio_rx);
+            }
+            Err(pending) => pending,
+        };
+
+        self.preroll_sent_ms.store(0, Ordering::SeqCst);
+        self.spawn_capture(CaptureMode::Recording(pending));
+        tracing::info!("Audio capture started");
+        Ok(tokio_rx)
+    }
+
+    pub fn stop(&self) {
+        self.status.is_recording.store(false, Ordering::SeqCst);
+        tracing::info!("Audio capture stopped");
+    }
+
+    /// Give a recording to the always-warm thread, returning how much
+    /// pre-roll it starts with; hands the recording back if there is no
+    /// warm thread
+    fn hand_to_warm(
+        &self,
+        pending: PendingRecording,
+    ) -> std::result::Result<Duration, PendingRecording> {
+        let Ok(warm) = self.warm.lock() else {
+            return Err(pending);
+        };
+        let Some(ref warm) = *warm else {
+            return Err(pending);
+        };
+        let Ok(mut handoff) = warm.handoff.lock() else {
+            return Err(pending);
+        };
+        if handoff.closed {
+            return Err(pending);
+        }
+        // Exactly these frames are flushed: the thread takes the recording
+        // before buffering its next frame
+        let preroll =
+            Duration::from_millis(handoff.preroll.frames.len() as u64 * FRAME_DURATION_MS as u64);
+        handoff.pending = Some(pending);
+        Ok(preroll)
+    }
+
+    /// Stop the always-warm thread, and start a new one if pre-roll is
+    /// configured. A recording in progress finishes on the old thread.
+    fn restart_warm(&self) {
+        let preroll = self.preroll();
+        let Ok(mut warm) = self.warm.lock() else {
+            return;
+        };
+        if let Some(old) = warm.take() {
+            old.running.store(false, Ordering::SeqCst);
+        }
+
+        if let Some(preroll) = preroll {
+            let capture = WarmCapture {
+                running: Arc::new(AtomicBool::new(true)),
+                handoff: Arc::new(Mutex::new(Handoff {
+                    preroll: RecentFrames::new(preroll),
+                    pending: None,
+                    recording: false,
+                    closed: false,
+                })),
+            };
+            *warm = Some(capture.clone());
+            drop(warm);
+            tracing::info!("Always-warm capture on, {}ms pre-roll", preroll.as_millis());
+            self.spawn_capture(CaptureMode::Warm(capture));
+        } else {
+            tracing::info!("Always-warm capture off");
+        }
+    }
+
+    fn spawn_capture(&self, mode: CaptureMode) {
+        let status = self.status.clone();
+        let settings = CaptureSettings {
+            source: self
+                .source()
+                .unwrap_or_else(|| Arc::new(CpalSource::new(self.input_device()))),
+            on_device_fallback: self.on_device_fallback.clone(),
+        };
+        let warm = match mode {
+            CaptureMode::Warm(ref warm) => Some(warm.clone()),
+            CaptureMode::Recording(_) => None,
+        };
 
         thread::spawn(move || {
             println!("[AudioCapture] >>> Thread spawned <<<");
@@ -65,7 +448,7 @@ impl AudioCapture {
             let _ = std::io::stdout().flush();
 
             let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                run_audio_capture(tokio_tx, is_recording.clone(), current_volume.clone())
+                run_audio_capture(&status, settings, mode)
             }));
 
             match result {
@@ -80,234 +463,362 @@ impl AudioCapture {
                 }
             }
 
-            is_recording.store(false, Ordering::SeqCst);
+            match warm {
+                Some(warm) => warm.close(&status),
+                None => status.is_recording.store(false, Ordering::SeqCst),
+            }
             println!("[AudioCapture] Thread exiting");
             let _ = std::io::stdout().flush();
         });
-
-        tracing::info!("Audio capture started");
-        Ok(tokio_rx)
     }
+}
 
-    pub fn stop(&self) {
-        self.is_recording.store(false, Ordering::SeqCst);
-        tracing::info!("Audio capture stopped");
+impl Drop for AudioCapture {
+    fn drop(&mut self) {
+        if let Ok(mut warm) = self.warm.lock() {
+            if let Some(warm) = warm.take() {
+                warm.running.store(false, Ordering::SeqCst);
+            }
+        }
     }
 }
 
-fn run_audio_capture(
+/// Encodes 16kHz frames and passes them on to the ASR client
+struct FrameSender {
+    encoder: OpusEncoder,
     tokio_tx: tokio_mpsc::Sender<Vec<u8>>,
-    is_recording: Arc<AtomicBool>,
-    current_volume: Arc<AtomicU32>,
-) -> Result<()> {
-    let host = cpal::default_host();
-    let device = host
-        .default_input_device()
-        .ok_or_else(|| anyhow!("No input device available"))?;
-
-    println!(
-        "[AudioCapture] Device: {}",
-        device.name().unwrap_or_default()
-    );
-
-    // Get the device's default config - USE THIS EXACTLY
-    let supported_config = device.default_input_config()?;
-    println!("[AudioCapture] Device config: {:?}", supported_config);
+    dropped_frames: Arc<AtomicU64>,
+    frame_count: u64,
+}
 
-    let native_sample_rate = supported_config.sample_rate().0;
-    let native_channels = supported_config.channels();
-    let sample_format = supported_config.sample_format();
+impl FrameSender {
+    fn send(&mut self, pcm: &[i16]) {
+        match self.encoder.encode_samples(pcm) {
+            Ok(opus_frame) => {
+                let count = self.frame_count;
+                self.frame_count += 1;
+                if count == 0 {
+                    println!("[Audio] First frame captured and encoded!");
+                }
+                if count > 0 && count % 50 == 0 {
+                    println!(
+                        "[AudioCapture] Frames: {} ({:.1}s)",
+                        count,
+                        count as f32 * 0.02
+                    );
+                }
 
-    println!(
-        "[AudioCapture] Native: {}Hz, {} channels, {:?}",
-        native_sample_rate, native_channels, sample_format
-    );
+                if self.tokio_tx.try_send(opus_frame).is_err() {
+                    self.dropped_frames.fetch_add(1, Ordering::SeqCst);
+                    println!("[AudioCapture] Channel full, dropping frame");
+                }
+            }
+            Err(e) => {
+                if self.frame_count == 0 {
+                    println!("[AudioCapture] First encode error: {}", e);
+                }
+            }
+        }
+    }
+}
 
-    // Use the device's EXACT config (don't override channels!)
-    let config = supported_config.config();
-    println!("[AudioCapture] Using config: {:?}", config);
+/// The most recent frames, up to a fixed duration
+struct RecentFrames {
+    frames: VecDeque<Vec<i16>>,
+    capacity: usize,
+}
 
-    // Create Opus encoder (16kHz mono)
-    let mut encoder = match OpusEncoder::new(OPUS_SAMPLE_RATE, OPUS_CHANNELS) {
-        Ok(enc) => {
-            println!("[AudioCapture] Opus encoder created (16kHz mono)");
-            enc
-        }
-        Err(e) => {
-            println!("[AudioCapture] Opus encoder FAILED: {}", e);
-            return Err(e);
+impl RecentFrames {
+    fn new(duration: Duration) -> Self {
+        let capacity = (duration.as_millis() as usize / FRAME_DURATION_MS as usize).max(1);
+        Self {
+            frames: VecDeque::with_capacity(capacity),
+            capacity,
         }
-    };
-
-    // Calculate frame sizes
-    let samples_per_frame_native =
-        (native_sample_rate * FRAME_DURATION_MS / 1000) as usize * native_channels as usize;
-    let samples_per_frame_opus = (OPUS_SAMPLE_RATE * FRAME_DURATION_MS / 1000) as usize; // mono
+    }
 
-    println!(
-        "[AudioCapture] Samples/frame: native={} ({}ch), opus={} (mono)",
-        samples_per_frame_native, native_channels, samples_per_frame_opus
-    );
+    /// Keep a copy of `pcm`, dropping the oldest frame when full
+    fn push(&mut self, pcm: &[i16]) {
+        let mut frame = if self.frames.len() == self.capacity {
+            self.frames.pop_front().unwrap_or_default()
+        } else {
+            Vec::with_capacity(pcm.len())
+        };
+        frame.clear();
+        frame.extend_from_slice(pcm);
+        self.frames.push_back(frame);
+    }
+}
 
-    let (std_tx, std_rx) = std_mpsc::channel::<Vec<i16>>();
+/// One recording on the capture thread: VAD, leading-silence hold-back
+/// and encoding
+struct Recording {
+    sender: FrameSender,
+    vad: Option<EnergyVad>,
+    /// Frames held back until speech starts
+    leading: Option<RecentFrames>,
+    silence_timed_out: Arc<AtomicBool>,
+    /// Samples the stream had overrun when the recording started
+    overrun_base: u64,
+}
 
-    let is_recording_clone = is_recording.clone();
-    let frame_counter = Arc::new(AtomicU64::new(0));
-    let frame_counter_clone = frame_counter.clone();
-    let native_channels_clone = native_channels;
+impl Recording {
+    fn new(pending: PendingRecording, status: &CaptureStatus, overrun_base: u64) -> Result<Self> {
+        // Create Opus encoder (16kHz mono)
+        let encoder =
+            match OpusEncoder::with_settings(OPUS_SAMPLE_RATE, OPUS_CHANNELS, &pending.opus) {
+                Ok(enc) => {
+                    println!(
+                        "[AudioCapture] Opus encoder created (16kHz mono, {})",
+                        pending.opus.describe()
+                    );
+                    enc
+                }
+                Err(e) => {
+                    println!("[AudioCapture] Opus encoder FAILED: {}", e);
+                    return Err(e);
+                }
+            };
+
+        let vad = pending.vad.map(|settings| {
+            println!(
+                "[VAD] Threshold {:.0}dBFS, hangover {}ms",
+                settings.speech_threshold_db,
+                settings.hangover.as_millis()
+            );
+            EnergyVad::new(settings)
+        });
+        let leading = vad
+            .as_ref()
+            .filter(|v| v.settings().hold_leading_silence)
+            .map(|v| RecentFrames::new(v.settings().leading_padding));
 
-    let err_fn = |err| {
-        println!("[AudioCapture] Stream error: {}", err);
-    };
+        println!("[Mic] Recording started...");
+        Ok(Self {
+            sender: FrameSender {
+                encoder,
+                tokio_tx: pending.tokio_tx,
+                dropped_frames: status.dropped_frames.clone(),
+                frame_count: 0,
+            },
+            vad,
+            leading,
+            silence_timed_out: status.silence_timed_out.clone(),
+            overrun_base,
+        })
+    }
 
-    let stream = match sample_format {
-        SampleFormat::I16 => {
-            println!("[AudioCapture] Building I16 stream");
-            let mut buffer = Vec::<i16>::with_capacity(samples_per_frame_native * 2);
+    /// Run a frame through VAD and encode it, unless it is leading silence
+    /// being held back
+    fn push(&mut self, pcm: &[i16]) {
+        self.process(pcm, true);
+    }
 
-            device.build_input_stream(
-                &config,
-                move |data: &[i16], _: &cpal::InputCallbackInfo| {
-                    if !is_recording_clone.load(Ordering::SeqCst) {
-                        return;
-                    }
+    /// Run a pre-roll frame through VAD and encode it; pre-roll is never
+    /// held back, as the session's start time already accounts for it
+    fn push_preroll(&mut self, pcm: &[i16]) {
+        self.process(pcm, false);
+    }
 
-                    buffer.extend_from_slice(data);
+    fn process(&mut self, pcm: &[i16], may_hold: bool) {
+        // Hold back leading silence; once speech starts, send the padding
+        // before it and everything after
+        let mut hold = false;
+        if let Some(ref mut vad) = self.vad {
+            let was_speaking = vad.is_speaking();
+            let speaking = vad.process(pcm);
+            if speaking != was_speaking {
+                tracing::debug!("[VAD] {}", if speaking { "speech" } else { "silence" });
+            }
+            if vad.timed_out() && !self.silence_timed_out.swap(true, Ordering::SeqCst) {
+                println!(
+                    "[VAD] {:.1}s of silence, requesting stop",
+                    vad.trailing_silence().as_secs_f32()
+                );
+            }
 
-                    while buffer.len() >= samples_per_frame_native {
-                        let frame: Vec<i16> = buffer.drain(..samples_per_frame_native).collect();
-                        let _ = std_tx.send(frame);
+            if let Some(ref mut held) = self.leading {
+                hold = may_hold && !vad.heard_speech();
+                if hold {
+                    held.push(pcm);
+                } else {
+                    for frame in held.frames.drain(..) {
+                        self.sender.send(&frame);
                     }
-                },
-                err_fn,
-                None,
-            )?
+                }
+            }
         }
-        SampleFormat::F32 => {
-            println!("[AudioCapture] Building F32 stream");
-            let mut buffer = Vec::<i16>::with_capacity(samples_per_frame_native * 2);
-
-            device.build_input_stream(
-                &config,
-                move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                    if !is_recording_clone.load(Ordering::SeqCst) {
-                        return;
-                    }
 
-                    // Convert f32 to i16
-                    let samples: Vec<i16> = data.iter().map(|s| (*s * 32767.0) as i16).collect();
-                    buffer.extend_from_slice(&samples);
-
-                    while buffer.len() >= samples_per_frame_native {
-                        let frame: Vec<i16> = buffer.drain(..samples_per_frame_native).collect();
-                        let _ = std_tx.send(frame);
-                    }
-                },
-                err_fn,
-                None,
-            )?
+        if !hold {
+            self.sender.send(pcm);
         }
-        format => {
-            return Err(anyhow!("Unsupported format: {:?}", format));
+    }
+
+    /// Log the totals; dropping the sender ends the audio stream
+    fn finish(self, overrun_samples: u64) {
+        let total = self.sender.frame_count;
+        println!("[AudioCapture] Total frames: {}", total);
+        println!(
+            "[Mic] Stopped. {} frames ({:.1}s), {} dropped, {} samples overrun",
+            total,
+            total as f32 * 0.02,
+            self.sender.dropped_frames.load(Ordering::SeqCst),
+            overrun_samples.saturating_sub(self.overrun_base)
+        );
+    }
+}
+
+fn run_audio_capture(
+    status: &CaptureStatus,
+    settings: CaptureSettings,
+    mode: CaptureMode,
+) -> Result<()> {
+    let CaptureSettings {
+        source,
+        on_device_fallback,
+    } = settings;
+
+    let mut input = match source.open() {
+        Ok(input) => input,
+        Err(e) => {
+            let Some(fallback) = source.fallback() else {
+                return Err(e);
+            };
+            println!("[AudioCapture] {}", e);
+            let input = fallback.open()?;
+            report_fallback(&on_device_fallback, &source.name(), input.name(), false);
+            input
         }
     };
 
-    stream.play()?;
-    println!("[AudioCapture] Stream playing!");
-    println!("[Mic] Recording started...");
+    let (mut recording, warm) = match mode {
+        CaptureMode::Recording(pending) => (Some(Recording::new(pending, status, 0)?), None),
+        CaptureMode::Warm(warm) => (None, Some(warm)),
+    };
+    // A single recording keeps the stream open only while it lasts
+    let keep_open = match warm {
+        Some(ref warm) => warm.running.clone(),
+        None => status.is_recording.clone(),
+    };
+
+    if warm.is_some() {
+        println!("[Mic] Always-warm capture started");
+    }
+
+    let mut overrun_samples = 0u64;
+    let mut last_audio = Instant::now();
 
     // Process frames: convert to mono 16kHz and encode
-    while is_recording.load(Ordering::SeqCst) {
-        match std_rx.recv_timeout(std::time::Duration::from_millis(100)) {
-            Ok(frame) => {
-                // Step 1: Convert stereo to mono (if needed)
-                let mono_frame: Vec<i16> = if native_channels_clone > 1 {
-                    // Average channels
-                    frame
-                        .chunks(native_channels_clone as usize)
-                        .map(|chunk| {
-                            let sum: i32 = chunk.iter().map(|&s| s as i32).sum();
-                            (sum / native_channels_clone as i32) as i16
-                        })
-                        .collect()
-                } else {
-                    frame
-                };
+    loop {
+        // Finish a stopped recording even if no more audio arrives; a new
+        // recording handed over already means the last one stopped
+        let superseded = warm.as_ref().is_some_and(|w| w.has_pending());
+        if recording.is_some() && (superseded || !status.is_recording.load(Ordering::SeqCst)) {
+            if let Some(done) = recording.take() {
+                done.finish(overrun_samples + input.processor.overrun_samples());
+            }
+            if let Some(Ok(mut handoff)) = warm.as_ref().map(|w| w.handoff.lock()) {
+                handoff.recording = false;
+            }
+        }
+        if recording.is_none() && !keep_open.load(Ordering::SeqCst) {
+            break;
+        }
 
-                // Calculate RMS Volume
-                let rms = if !mono_frame.is_empty() {
-                    let sum_sq: f64 = mono_frame.iter().map(|&s| (s as f64) * (s as f64)).sum();
-                    (sum_sq / mono_frame.len() as f64).sqrt()
-                } else {
-                    0.0
-                };
-                // Normalize to 0-100 (assume 10000 is max speech volume)
-                let vol = (rms / 100.0).min(100.0) as u32;
-                current_volume.store(vol, Ordering::SeqCst);
-
-                // Step 2: Resample to 16kHz (if needed)
-                let mono_samples_per_native_frame =
-                    samples_per_frame_native / native_channels_clone as usize;
-                let resampled: Vec<i16> = if mono_samples_per_native_frame != samples_per_frame_opus
-                {
-                    let ratio =
-                        mono_samples_per_native_frame as f32 / samples_per_frame_opus as f32;
-                    (0..samples_per_frame_opus)
-                        .map(|i| {
-                            let src_idx = ((i as f32 * ratio) as usize).min(mono_frame.len() - 1);
-                            mono_frame[src_idx]
-                        })
-                        .collect()
-                } else {
-                    mono_frame
-                };
-
-                // Step 3: Convert to bytes
-                let pcm_bytes: Vec<u8> = resampled.iter().flat_map(|s| s.to_le_bytes()).collect();
-
-                // Step 4: Encode to Opus
-                match encoder.encode(&pcm_bytes) {
-                    Ok(opus_frame) => {
-                        let count = frame_counter_clone.fetch_add(1, Ordering::SeqCst);
-                        if count == 0 {
-                            println!("[Audio] First frame captured and encoded!");
-                        }
-                        if count > 0 && count % 50 == 0 {
-                            println!(
-                                "[AudioCapture] Frames: {} ({:.1}s)",
-                                count,
-                                count as f32 * 0.02
-                            );
-                        }
-
-                        if tokio_tx.try_send(opus_frame).is_err() {
-                            println!("[AudioCapture] Channel full, dropping frame");
-                        }
-                    }
-                    Err(e) => {
-                        if frame_counter_clone.load(Ordering::SeqCst) == 0 {
-                            println!("[AudioCapture] First encode error: {}", e);
-                        }
-                    }
+        let stalled = last_audio.elapsed() >= DEVICE_STALL_TIMEOUT;
+        if stalled {
+            last_audio = Instant::now();
+        }
+        if input.is_lost() || (stalled && !input.is_available()) {
+            println!("[AudioCapture] Device '{}' disconnected", input.name());
+            let lost = input.name().to_string();
+            overrun_samples += input.processor.overrun_samples();
+            drop(input);
+
+            input = source
+                .fallback()
+                .ok_or_else(|| anyhow!("Audio source '{}' was lost", lost))?
+                .open()?;
+            report_fallback(
+                &on_device_fallback,
+                &lost,
+                input.name(),
+                recording.is_some(),
+            );
+            continue;
+        }
+
+        // A finished source (end of file or stdin) ends the recording once
+        // everything it delivered has been sent
+        let ended = input.is_ended();
+        let overrun = overrun_samples + input.processor.overrun_samples();
+        let Some(pcm) = input.processor.next_frame() else {
+            if ended {
+                println!("[AudioCapture] Source '{}' ended", input.name());
+                status.is_recording.store(false, Ordering::SeqCst);
+                if let Some(ref warm) = warm {
+                    warm.running.store(false, Ordering::SeqCst);
                 }
             }
-            Err(std_mpsc::RecvTimeoutError::Timeout) => {
-                // Normal timeout
-            }
-            Err(std_mpsc::RecvTimeoutError::Disconnected) => {
-                println!("[AudioCapture] Channel disconnected");
+            thread::sleep(POLL_INTERVAL);
+            continue;
+        };
+        last_audio = Instant::now();
+
+        // Between recordings, keep the pre-roll; a new recording starts
+        // with it
+        if recording.is_none() {
+            let Some(ref warm) = warm else {
                 break;
+            };
+            let Ok(mut handoff) = warm.handoff.lock() else {
+                break;
+            };
+            let Some(pending) = handoff.pending.take() else {
+                handoff.preroll.push(pcm);
+                continue;
+            };
+            let mut started = Recording::new(pending, status, overrun)?;
+            for frame in handoff.preroll.frames.drain(..) {
+                started.push_preroll(&frame);
             }
+            handoff.recording = true;
+            recording = Some(started);
+        }
+
+        if let Some(ref mut active) = recording {
+            active.push(pcm);
+            status
+                .current_volume
+                .store(input.processor.volume(), Ordering::SeqCst);
         }
     }
 
-    let total = frame_counter.load(Ordering::SeqCst);
-    println!("[AudioCapture] Total frames: {}", total);
+    Ok(())
+}
+
+fn report_fallback(
+    callback: &DeviceFallbackSlot,
+    lost: &str,
+    fallback: &str,
+    during_recording: bool,
+) {
+    let fallback = DeviceFallback {
+        lost: lost.to_string(),
+        fallback: fallback.to_string(),
+        during_recording,
+    };
     println!(
-        "[Mic] Stopped. {} frames ({:.1}s)",
-        total,
-        total as f32 * 0.02
+        "[AudioCapture] Input device '{}' unavailable, using default '{}'",
+        fallback.lost, fallback.fallback
     );
-
-    Ok(())
+    tracing::warn!(
+        "Input device '{}' unavailable, falling back to '{}'",
+        fallback.lost,
+        fallback.fallback
+    );
+    let callback = callback.read().ok().and_then(|c| c.clone());
+    if let Some(callback) = callback {
+        callback(fallback);
+    }
 }
@@ -0,0 +1,354 @@
+//! Audio File Decoding
+//!
+//! Streams interleaved i16 samples out of WAV (integer or float PCM), Ogg
+//! Opus and MP3 files, at the file's own sample rate and channel count. The
+//! format is taken from the file's magic bytes, not its extension.
+
+use anyhow::{anyhow, Context, Result};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{Decoder, DecoderOptions};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use super::pipeline::check_native_format;
+
+/// Opus always decodes at 48kHz here; pre-skip is counted in 48kHz samples
+const OPUS_DECODE_RATE: u32 = 48000;
+
+/// Longest Opus packet: 120ms at 48kHz, per channel
+const OPUS_MAX_PACKET_SAMPLES: usize = 5760;
+
+/// A decoded audio file, read incrementally
+pub struct AudioFileReader {
+    decoder: FileDecoder,
+    sample_rate: u32,
+    channels: u16,
+}
+
+enum FileDecoder {
+    Wav {
+        reader: hound::WavReader<BufReader<File>>,
+        format: hound::SampleFormat,
+        bits_per_sample: u16,
+    },
+    OggOpus(OggOpusDecoder),
+    Mp3(Mp3Decoder),
+}
+
+impl AudioFileReader {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut file =
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let mut magic = [0u8; 4];
+        file.read_exact(&mut magic)
+            .with_context(|| format!("{} is too short for an audio file", path.display()))?;
+        file.seek(SeekFrom::Start(0))?;
+
+        let reader = match &magic {
+            b"RIFF" => {
+                let reader = hound::WavReader::new(BufReader::new(file))
+                    .map_err(|e| anyhow!("Invalid WAV file: {}", e))?;
+                let spec = reader.spec();
+                Self {
+                    sample_rate: spec.sample_rate,
+                    channels: spec.channels,
+                    decoder: FileDecoder::Wav {
+                        reader,
+                        format: spec.sample_format,
+                        bits_per_sample: spec.bits_per_sample,
+                    },
+                }
+            }
+            b"OggS" => {
+                let decoder = OggOpusDecoder::new(BufReader::new(file))?;
+                Self {
+                    sample_rate: OPUS_DECODE_RATE,
+                    channels: decoder.channels as u16,
+                    decoder: FileDecoder::OggOpus(decoder),
+                }
+            }
+            // An ID3 tag or an MPEG audio frame sync
+            [b'I', b'D', b'3', _] | [0xFF, 0xE0..=0xFF, _, _] => {
+                let decoder = Mp3Decoder::new(file)?;
+                Self {
+                    sample_rate: decoder.sample_rate,
+                    channels: decoder.channels as u16,
+                    decoder: FileDecoder::Mp3(decoder),
+                }
+            }
+            _ => {
+                return Err(anyhow!(
+                    "Unsupported audio file {} (expected WAV, Ogg Opus or MP3)",
+                    path.display()
+                ))
+            }
+        };
+        // A header claiming a rate or channel count the pipeline cannot take
+        // would otherwise surface later as an empty frame or a huge buffer
+        check_native_format(reader.sample_rate, reader.channels)
+            .with_context(|| format!("Cannot read {}", path.display()))?;
+        Ok(reader)
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    /// Fill `buf` with interleaved samples; returns how many were written,
+    /// 0 at the end of the file
+    pub fn read(&mut self, buf: &mut [i16]) -> Result<usize> {
+        match self.decoder {
+            FileDecoder::Wav {
+                ref mut reader,
+                format,
+                bits_per_sample,
+            } => {
+                let mut n = 0;
+                match format {
+                    hound::SampleFormat::Int => {
+                        // Scale any integer width to 16 bits
+                        let shift = bits_per_sample as i32 - 16;
+                        for (out, sample) in buf.iter_mut().zip(reader.samples::<i32>()) {
+                            let sample = sample.map_err(|e| anyhow!("WAV read error: {}", e))?;
+                            *out = if shift >= 0 {
+                                (sample >> shift) as i16
+                            } else {
+                                (sample << -shift) as i16
+                            };
+                            n += 1;
+                        }
+                    }
+                    hound::SampleFormat::Float => {
+                        for (out, sample) in buf.iter_mut().zip(reader.samples::<f32>()) {
+                            let sample = sample.map_err(|e| anyhow!("WAV read error: {}", e))?;
+                            *out = (sample.clamp(-1.0, 1.0) * 32767.0) as i16;
+                            n += 1;
+                        }
+                    }
+                }
+                Ok(n)
+            }
+            FileDecoder::OggOpus(ref mut decoder) => decoder.read(buf),
+            FileDecoder::Mp3(ref mut decoder) => decoder.read(buf),
+        }
+    }
+}
+
+/// Ogg Opus (RFC 7845) with channel mapping family 0: mono or stereo
+struct OggOpusDecoder {
+    packets: ogg::PacketReader<BufReader<File>>,
+    decoder: opus::Decoder,
+    channels: usize,
+    /// Decoded samples, interleaved; `decoded[position..]` not returned yet
+    decoded: Vec<i16>,
+    position: usize,
+    /// Samples per channel still to drop from the start of the stream
+    pre_skip: usize,
+}
+
+impl OggOpusDecoder {
+    fn new(reader: BufReader<File>) -> Result<Self> {
+        let mut packets = ogg::PacketReader::new(reader);
+        let head = packets
+            .read_packet_expected()
+            .map_err(|e| anyhow!("Invalid Ogg file: {}", e))?;
+        let head = head.data;
+        if head.len() < 19 || &head[..8] != b"OpusHead" {
+            return Err(anyhow!("Ogg file does not contain an Opus stream"));
+        }
+        let channels = head[9] as usize;
+        let pre_skip = u16::from_le_bytes([head[10], head[11]]) as usize;
+        if head[18] != 0 || !(1..=2).contains(&channels) {
+            return Err(anyhow!(
+                "Unsupported Opus channel mapping ({} channels)",
+                channels
+            ));
+        }
+        // OpusTags
+        packets
+            .read_packet_expected()
+            .map_err(|e| anyhow!("Invalid Ogg Opus file: {}", e))?;
+
+        let decoder = opus::Decoder::new(
+            OPUS_DECODE_RATE,
+            if channels == 2 {
+                opus::Channels::Stereo
+            } else {
+                opus::Channels::Mono
+            },
+        )?;
+        Ok(Self {
+            packets,
+            decoder,
+            channels,
+            decoded: vec![0; OPUS_MAX_PACKET_SAMPLES * channels],
+            position: OPUS_MAX_PACKET_SAMPLES * channels,
+            pre_skip,
+        })
+    }
+
+    fn read(&mut self, buf: &mut [i16]) -> Result<usize> {
+        let mut n = 0;
+        while n < buf.len() {
+            if self.position == self.decoded.len() && !self.decode_next()? {
+                break;
+            }
+            let available = &self.decoded[self.position..];
+            let count = available.len().min(buf.len() - n);
+            buf[n..n + count].copy_from_slice(&available[..count]);
+            self.position += count;
+            n += count;
+        }
+        Ok(n)
+    }
+
+    /// Decode the next packet into `decoded`; false at the end of the stream
+    fn decode_next(&mut self) -> Result<bool> {
+        loop {
+            let Some(packet) = self
+                .packets
+                .read_packet()
+                .map_err(|e| anyhow!("Ogg read error: {}", e))?
+            else {
+                return Ok(false);
+            };
+
+            self.decoded
+                .resize(OPUS_MAX_PACKET_SAMPLES * self.channels, 0);
+            let samples = self
+                .decoder
+                .decode(&packet.data, &mut self.decoded, false)?;
+            self.decoded.truncate(samples * self.channels);
+
+            let skip = self.pre_skip.min(samples);
+            self.pre_skip -= skip;
+            self.position = skip * self.channels;
+            if self.position < self.decoded.len() {
+                return Ok(true);
+            }
+        }
+    }
+}
+
+/// MPEG-1/2 Layer III, decoded with symphonia
+struct Mp3Decoder {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    sample_rate: u32,
+    channels: usize,
+    /// Samples of the last decoded packet; `decoded.samples()[position..]`
+    /// not returned yet
+    decoded: Option<SampleBuffer<i16>>,
+    position: usize,
+}
+
+impl Mp3Decoder {
+    fn new(file: File) -> Result<Self> {
+        let stream = MediaSourceStream::new(Box::new(file), Default::default());
+        let mut hint = Hint::new();
+        hint.with_extension("mp3");
+        let probed = symphonia::default::get_probe()
+            .format(
+                &hint,
+                stream,
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )
+            .map_err(|e| anyhow!("Invalid MP3 file: {}", e))?;
+        let format = probed.format;
+        let track = format
+            .default_track()
+            .ok_or_else(|| anyhow!("MP3 file has no audio track"))?;
+        let params = &track.codec_params;
+        let sample_rate = params
+            .sample_rate
+            .ok_or_else(|| anyhow!("MP3 file has no sample rate"))?;
+        let channels = params.channels.map_or(1, |c| c.count());
+        let decoder = symphonia::default::get_codecs()
+            .make(params, &DecoderOptions::default())
+            .map_err(|e| anyhow!("Unsupported MP3 stream: {}", e))?;
+
+        Ok(Self {
+            track_id: track.id,
+            format,
+            decoder,
+            sample_rate,
+            channels,
+            decoded: None,
+            position: 0,
+        })
+    }
+
+    fn read(&mut self, buf: &mut [i16]) -> Result<usize> {
+        let mut n = 0;
+        while n < buf.len() {
+            let remaining = self.decoded.as_ref().map_or(0, |d| d.len() - self.position);
+            if remaining == 0 && !self.decode_next()? {
+                break;
+            }
+            let Some(decoded) = self.decoded.as_ref() else {
+                break;
+            };
+            let available = &decoded.samples()[self.position..];
+            let count = available.len().min(buf.len() - n);
+            buf[n..n + count].copy_from_slice(&available[..count]);
+            self.position += count;
+            n += count;
+        }
+        Ok(n)
+    }
+
+    /// Decode the next packet into `decoded`; false at the end of the stream
+    fn decode_next(&mut self) -> Result<bool> {
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(SymphoniaError::IoError(e))
+                    if e.kind() == std::io::ErrorKind::UnexpectedEof =>
+                {
+                    return Ok(false)
+                }
+                Err(e) => return Err(anyhow!("MP3 read error: {}", e)),
+            };
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            let audio = match self.decoder.decode(&packet) {
+                Ok(audio) => audio,
+                // A corrupt frame is skipped, as players do
+                Err(SymphoniaError::DecodeError(e)) => {
+                    tracing::debug!("Skipping undecodable MP3 frame: {}", e);
+                    continue;
+                }
+                Err(e) => return Err(anyhow!("MP3 decode error: {}", e)),
+            };
+            if audio.frames() == 0 {
+                continue;
+            }
+            let decoded = match self.decoded {
+                Some(ref mut decoded) if decoded.capacity() >= audio.capacity() * self.channels => {
+                    decoded
+                }
+                _ => self
+                    .decoded
+                    .insert(SampleBuffer::new(audio.capacity() as u64, *audio.spec())),
+            };
+            decoded.copy_interleaved_ref(audio);
+            self.position = 0;
+            return Ok(true);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! Input Device Selection
+//!
+//! Enumerates the host's input devices with the stream configs they
+//! support, and resolves the `[audio] device` setting (a device name) to a
+//! cpal device, falling back to the system default when it is missing.
+
+use anyhow::{anyhow, Result};
+use cpal::traits::{DeviceTrait, HostTrait};
+use serde::Serialize;
+
+/// An input device as shown in the settings page
+#[derive(Debug, Clone, Serialize)]
+pub struct InputDeviceInfo {
+    pub name: String,
+    /// The system default input
+    pub is_default: bool,
+    /// The config used when recording from this device
+    pub default_config: Option<InputConfig>,
+    pub supported_configs: Vec<InputConfigRange>,
+}
+
+/// A concrete stream config
+#[derive(Debug, Clone, Serialize)]
+pub struct InputConfig {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub sample_format: String,
+}
+
+/// A range of sample rates supported with one channel count and format
+#[derive(Debug, Clone, Serialize)]
+pub struct InputConfigRange {
+    pub channels: u16,
+    pub min_sample_rate: u32,
+    pub max_sample_rate: u32,
+    pub sample_format: String,
+}
+
+/// Every input device of the default host, the system default first
+pub fn list_input_devices() -> Result<Vec<InputDeviceInfo>> {
+    let host = cpal::default_host();
+    let default_name = host.default_input_device().and_then(|d| d.name().ok());
+
+    let mut devices: Vec<InputDeviceInfo> = host
+        .input_devices()
+        .map_err(|e| anyhow!("Failed to enumerate input devices: {}", e))?
+        .filter_map(|device| {
+            let name = device.name().ok()?;
+            let default_config = device.default_input_config().ok().map(|c| InputConfig {
+                channels: c.channels(),
+                sample_rate: c.sample_rate().0,
+                sample_format: format!("{:?}", c.sample_format()),
+            });
+            let supported_configs = device
+                .supported_input_configs()
+                .map(|configs| {
+                    configs
+                        .map(|c| InputConfigRange {
+                            channels: c.channels(),
+                            min_sample_rate: c.min_sample_rate().0,
+                            max_sample_rate: c.max_sample_rate().0,
+                            sample_format: format!("{:?}", c.sample_format()),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            Some(InputDeviceInfo {
+                is_default: default_name.as_deref() == Some(name.as_str()),
+                name,
+                default_config,
+                supported_configs,
+            })
+        })
+        .collect();
+    devices.sort_by_key(|d| !d.is_default);
+    Ok(devices)
+}
+
+/// The input device named `name`, if it is currently connected
+pub(crate) fn find_input_device(host: &cpal::Host, name: &str) -> Option<cpal::Device> {
+    host.input_devices()
+        .ok()?
+        .find(|d| d.name().map(|n| n == name).unwrap_or(false))
+}
+
+/// The system default input device
+pub(crate) fn default_input_device(host: &cpal::Host) -> Result<cpal::Device> {
+    host.default_input_device()
+        .ok_or_else(|| anyhow!("No input device available"))
+}
@@ -3,7 +3,92 @@
 //! Encodes PCM audio data to Opus format.
 
 use anyhow::{anyhow, Result};
-use opus::{Application, Channels, Encoder};
+use opus::{Application, Bitrate, Channels, Encoder};
+
+/// Opus coding mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OpusApplication {
+    /// General audio, the most faithful to the input
+    #[default]
+    Audio,
+    /// Tuned for speech intelligibility, the better choice at low bitrates
+    Voip,
+}
+
+impl OpusApplication {
+    /// Parse `audio` or `voip`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "audio" => Some(Self::Audio),
+            "voip" => Some(Self::Voip),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Audio => "audio",
+            Self::Voip => "voip",
+        }
+    }
+}
+
+/// Encoder tuning (`[audio.opus]`); the defaults leave libopus's own
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpusSettings {
+    pub application: OpusApplication,
+    /// Target bitrate in bits per second; `None` lets libopus choose
+    pub bitrate: Option<u32>,
+    /// Variable bitrate; off encodes every packet at the target bitrate
+    pub vbr: bool,
+    /// 0 (least CPU) to 10 (best quality); `None` keeps the libopus default
+    pub complexity: Option<u8>,
+    /// In-band forward error correction: each packet carries a coarse copy
+    /// of the previous one
+    pub fec: bool,
+    /// Expected packet loss (0-100), which FEC spends bits against
+    pub packet_loss_percent: u8,
+    /// Discontinuous transmission: near-empty packets while silent
+    pub dtx: bool,
+}
+
+impl Default for OpusSettings {
+    fn default() -> Self {
+        Self {
+            application: OpusApplication::Audio,
+            bitrate: None,
+            vbr: true,
+            complexity: None,
+            fec: false,
+            packet_loss_percent: 0,
+            dtx: false,
+        }
+    }
+}
+
+impl OpusSettings {
+    /// Short description for logs, e.g. `voip 24kbps cbr c5 fec(10%) dtx`
+    pub fn describe(&self) -> String {
+        let mut parts = vec![self.application.name().to_string()];
+        parts.push(match self.bitrate {
+            Some(bps) => format!("{}kbps", bps / 1000),
+            None => "auto".to_string(),
+        });
+        if !self.vbr {
+            parts.push("cbr".to_string());
+        }
+        if let Some(complexity) = self.complexity {
+            parts.push(format!("c{}", complexity));
+        }
+        if self.fec {
+            parts.push(format!("fec({}%)", self.packet_loss_percent));
+        }
+        if self.dtx {
+            parts.push("dtx".to_string());
+        }
+        parts.join(" ")
+    }
+}
 
 /// Opus encoder wrapper
 pub struct OpusEncoder {
@@ -11,20 +96,33 @@ pub struct OpusEncoder {
     sample_rate: u32,
     channels: u16,
     frame_size: usize,
+    /// Scratch space for the encoded packet, reused across frames
+    output: Vec<u8>,
 }
 
 impl OpusEncoder {
     /// Create a new Opus encoder
     pub fn new(sample_rate: u32, channels: u16) -> Result<Self> {
+        Self::with_settings(sample_rate, channels, &OpusSettings::default())
+    }
+
+    /// Create an Opus encoder tuned by `settings`
+    pub fn with_settings(sample_rate: u32, channels: u16, settings: &OpusSettings) -> Result<Self> {
         let channels_enum = match channels {
             1 => Channels::Mono,
             2 => Channels::Stereo,
             _ => return Err(anyhow!("Invalid channel count: {}", channels)),
         };
 
-        let encoder = Encoder::new(sample_rate, channels_enum, Application::Audio)
+        let application = match settings.application {
+            OpusApplication::Audio => Application::Audio,
+            OpusApplication::Voip => Application::Voip,
+        };
+        let mut encoder = Encoder::new(sample_rate, channels_enum, application)
             .map_err(|e| anyhow!("Failed to create Opus encoder: {:?}", e))?;
-        
+        apply_settings(&mut encoder, settings)
+            .map_err(|e| anyhow!("Invalid Opus settings ({}): {:?}", settings.describe(), e))?;
+
         // Frame size for 20ms at the given sample rate
         let frame_size = (sample_rate * 20 / 1000) as usize;
 
@@ -33,6 +131,7 @@ impl OpusEncoder {
             sample_rate,
             channels,
             frame_size,
+            output: vec![0u8; 4000], // Max Opus frame size
         })
     }
 
@@ -46,7 +145,13 @@ impl OpusEncoder {
             .chunks_exact(2)
             .map(|chunk| i16::from_le_bytes([chunk[0], chunk[1]]))
             .collect();
+        self.encode_samples(&samples)
+    }
 
+    /// Encode one frame of PCM samples to Opus
+    ///
+    /// Only the returned packet is allocated.
+    pub fn encode_samples(&mut self, samples: &[i16]) -> Result<Vec<u8>> {
         // Ensure we have the right number of samples
         let expected_samples = self.frame_size * self.channels as usize;
         if samples.len() < expected_samples {
@@ -58,13 +163,12 @@ impl OpusEncoder {
         }
 
         // Encode to Opus
-        let mut output = vec![0u8; 4000]; // Max Opus frame size
-        let encoded_len = self.encoder
-            .encode(&samples[..expected_samples], &mut output)
+        let encoded_len = self
+            .encoder
+            .encode(&samples[..expected_samples], &mut self.output)
             .map_err(|e| anyhow!("Opus encode error: {:?}", e))?;
-        
-        output.truncate(encoded_len);
-        Ok(output)
+
+        Ok(self.output[..encoded_len].to_vec())
     }
 
     /// Get the frame size in samples
@@ -82,3 +186,18 @@ impl OpusEncoder {
         self.channels
     }
 }
+
+fn apply_settings(encoder: &mut Encoder, settings: &OpusSettings) -> opus::Result<()> {
+    if let Some(bitrate) = settings.bitrate {
+        // libopus accepts 6-510kbps
+        encoder.set_bitrate(Bitrate::Bits(bitrate.clamp(6000, 510_000) as i32))?;
+    }
+    encoder.set_vbr(settings.vbr)?;
+    if let Some(complexity) = settings.complexity {
+        encoder.set_complexity(complexity.min(10) as i32)?;
+    }
+    encoder.set_inband_fec(settings.fec)?;
+    encoder.set_packet_loss_perc(settings.packet_loss_percent.min(100) as i32)?;
+    encoder.set_dtx(settings.dtx)?;
+    Ok(())
+}
@@ -1,7 +1,33 @@
 //! Audio capture and processing module
 
 mod capture;
+mod decode;
+mod device;
 mod encoder;
+mod ogg_writer;
+mod opus_bench;
+mod pipeline;
+mod resampler;
+mod source;
+mod vad;
 
-pub use capture::AudioCapture;
-pub use encoder::OpusEncoder;
+pub use capture::{AudioCapture, DeviceFallback};
+pub use decode::AudioFileReader;
+pub use device::{list_input_devices, InputConfig, InputConfigRange, InputDeviceInfo};
+pub use encoder::{OpusApplication, OpusEncoder, OpusSettings};
+pub use ogg_writer::OggOpusWriter;
+pub use opus_bench::{
+    bench_opus, opus_bench_presets, read_fixture, speech_like_fixture, OpusBenchResult,
+};
+pub use pipeline::{capture_pipeline, CaptureWriter, FrameProcessor};
+pub use resampler::FrameResampler;
+pub use source::{
+    source_from_spec, AudioSource, CpalSource, FileSource, Signal, SourceState, SourceStream,
+    StdinSource, SyntheticSource,
+};
+pub use vad::{frame_level_db, EnergyVad, VadSettings};
+
+// Opus encoder always uses 16kHz mono
+const OPUS_SAMPLE_RATE: u32 = 16000;
+const OPUS_CHANNELS: u16 = 1;
+const FRAME_DURATION_MS: u32 = 20;
@@ -0,0 +1,111 @@
+//! Ogg Opus Writer
+//!
+//! Wraps the encoder's raw Opus packets in an Ogg Opus file (RFC 7845):
+//! an OpusHead and an OpusTags page, then the audio packets with granule
+//! positions counted at 48kHz. Pages are closed every second so a file cut
+//! short by a crash still plays up to its last full page.
+
+use anyhow::{Context, Result};
+use ogg::{PacketWriteEndInfo, PacketWriter};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Granule positions always count 48kHz samples
+const GRANULE_RATE: u64 = 48000;
+
+/// libopus encoder delay (6.5ms at 48kHz) for decoders to skip
+const PRE_SKIP: u16 = 312;
+
+/// Packets per Ogg page
+const PAGE_PACKETS: u64 = 50;
+
+const VENDOR: &str = concat!("doubao-voice-input ", env!("CARGO_PKG_VERSION"));
+
+/// Streams Opus packets into an Ogg Opus file
+pub struct OggOpusWriter {
+    writer: PacketWriter<BufWriter<File>>,
+    serial: u32,
+    /// 48kHz samples in one packet
+    packet_samples: u64,
+    packets: u64,
+    /// The newest packet, held back until it is known whether it is the last
+    pending: Option<Vec<u8>>,
+}
+
+impl OggOpusWriter {
+    /// Create `path` for packets of `frame_ms` encoded from `sample_rate`
+    pub fn create(
+        path: impl AsRef<Path>,
+        sample_rate: u32,
+        channels: u16,
+        frame_ms: u32,
+    ) -> Result<Self> {
+        let path = path.as_ref();
+        let file =
+            File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut writer = PacketWriter::new(BufWriter::new(file));
+        let serial = rand::random();
+
+        let mut head = Vec::with_capacity(19);
+        head.extend_from_slice(b"OpusHead");
+        head.push(1);
+        head.push(channels as u8);
+        head.extend_from_slice(&PRE_SKIP.to_le_bytes());
+        head.extend_from_slice(&sample_rate.to_le_bytes());
+        // Output gain, channel mapping family 0
+        head.extend_from_slice(&0i16.to_le_bytes());
+        head.push(0);
+        writer.write_packet(head.into(), serial, PacketWriteEndInfo::EndPage, 0)?;
+
+        let mut tags = Vec::new();
+        tags.extend_from_slice(b"OpusTags");
+        tags.extend_from_slice(&(VENDOR.len() as u32).to_le_bytes());
+        tags.extend_from_slice(VENDOR.as_bytes());
+        tags.extend_from_slice(&0u32.to_le_bytes());
+        writer.write_packet(tags.into(), serial, PacketWriteEndInfo::EndPage, 0)?;
+
+        Ok(Self {
+            writer,
+            serial,
+            packet_samples: GRANULE_RATE * frame_ms as u64 / 1000,
+            packets: 0,
+            pending: None,
+        })
+    }
+
+    /// Append one Opus packet
+    pub fn write_packet(&mut self, packet: &[u8]) -> Result<()> {
+        if let Some(previous) = self.pending.replace(packet.to_vec()) {
+            let end = if (self.packets + 1).is_multiple_of(PAGE_PACKETS) {
+                PacketWriteEndInfo::EndPage
+            } else {
+                PacketWriteEndInfo::NormalPacket
+            };
+            self.write(previous, end)?;
+        }
+        Ok(())
+    }
+
+    /// Packets written so far
+    pub fn packets(&self) -> u64 {
+        self.packets + self.pending.is_some() as u64
+    }
+
+    /// End the stream and flush the file; returns the packet count
+    pub fn finish(mut self) -> Result<u64> {
+        if let Some(last) = self.pending.take() {
+            self.write(last, PacketWriteEndInfo::EndStream)?;
+        }
+        self.writer.into_inner().flush()?;
+        Ok(self.packets)
+    }
+
+    fn write(&mut self, packet: Vec<u8>, end: PacketWriteEndInfo) -> Result<()> {
+        self.packets += 1;
+        let granule = self.packets * self.packet_samples;
+        self.writer
+            .write_packet(packet.into(), self.serial, end, granule)?;
+        Ok(())
+    }
+}
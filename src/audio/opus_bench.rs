@@ -0,0 +1,170 @@
+//! Opus Encoder Benchmark
+//!
+//! Encodes one fixture at a series of encoder settings and reports what
+//! each costs on the wire and in CPU, for tuning `[audio.opus]` on metered
+//! connections. The fixture is 16kHz mono PCM: a decoded audio file, or a
+//! generated speech-like signal with pauses (so DTX has silence to skip).
+
+use anyhow::Result;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use super::decode::AudioFileReader;
+use super::encoder::{OpusApplication, OpusEncoder, OpusSettings};
+use super::pipeline::capture_pipeline;
+use super::{FRAME_DURATION_MS, OPUS_CHANNELS, OPUS_SAMPLE_RATE};
+
+/// Size and encoding cost of a fixture at one setting
+#[derive(Debug, Clone)]
+pub struct OpusBenchResult {
+    pub settings: OpusSettings,
+    pub packets: u64,
+    pub bytes: u64,
+    /// Length of the encoded audio
+    pub audio: Duration,
+    /// Time spent encoding, on one thread
+    pub encode_time: Duration,
+}
+
+impl OpusBenchResult {
+    /// Average bitrate on the wire, in kbps
+    pub fn kbps(&self) -> f64 {
+        self.bytes as f64 * 8.0 / self.audio.as_secs_f64().max(0.001) / 1000.0
+    }
+
+    /// Data used per hour of dictation, in MB
+    pub fn mb_per_hour(&self) -> f64 {
+        self.kbps() * 3600.0 / 8.0 / 1000.0
+    }
+
+    /// Share of one core needed to encode in real time
+    pub fn cpu_percent(&self) -> f64 {
+        self.encode_time.as_secs_f64() / self.audio.as_secs_f64().max(0.001) * 100.0
+    }
+}
+
+/// Settings worth comparing: `configured` first, then the libopus defaults
+/// and VoIP presets down to 12kbps, each without duplicates
+pub fn opus_bench_presets(configured: &OpusSettings) -> Vec<OpusSettings> {
+    let voip = |kbps: u32| OpusSettings {
+        application: OpusApplication::Voip,
+        bitrate: Some(kbps * 1000),
+        ..OpusSettings::default()
+    };
+    let candidates = [
+        configured.clone(),
+        OpusSettings::default(),
+        voip(32),
+        voip(24),
+        voip(16),
+        voip(12),
+        OpusSettings {
+            vbr: false,
+            ..voip(16)
+        },
+        OpusSettings {
+            complexity: Some(5),
+            ..voip(16)
+        },
+        OpusSettings {
+            complexity: Some(0),
+            ..voip(16)
+        },
+        OpusSettings {
+            fec: true,
+            packet_loss_percent: 10,
+            ..voip(16)
+        },
+        OpusSettings {
+            dtx: true,
+            ..voip(16)
+        },
+    ];
+
+    let mut presets: Vec<OpusSettings> = Vec::new();
+    for settings in candidates {
+        if !presets.contains(&settings) {
+            presets.push(settings);
+        }
+    }
+    presets
+}
+
+/// Encode `pcm` (16kHz mono) with `settings`; a trailing partial frame is
+/// left out
+pub fn bench_opus(pcm: &[i16], settings: &OpusSettings) -> Result<OpusBenchResult> {
+    let mut encoder = OpusEncoder::with_settings(OPUS_SAMPLE_RATE, OPUS_CHANNELS, settings)?;
+    let mut packets = 0u64;
+    let mut bytes = 0u64;
+
+    let started = Instant::now();
+    for frame in pcm.chunks_exact(encoder.frame_size()) {
+        bytes += encoder.encode_samples(frame)?.len() as u64;
+        packets += 1;
+    }
+    let encode_time = started.elapsed();
+
+    Ok(OpusBenchResult {
+        settings: settings.clone(),
+        packets,
+        bytes,
+        audio: Duration::from_millis(packets * FRAME_DURATION_MS as u64),
+        encode_time,
+    })
+}
+
+/// Decode an audio file to 16kHz mono, as captured audio would be
+pub fn read_fixture(path: impl AsRef<Path>) -> Result<Vec<i16>> {
+    let mut reader = AudioFileReader::open(path)?;
+    let (mut writer, mut processor) = capture_pipeline(reader.sample_rate(), reader.channels())?;
+    let mut chunk = vec![0i16; processor.native_frame_len()];
+
+    let mut pcm = Vec::new();
+    loop {
+        let n = reader.read(&mut chunk)?;
+        if n == 0 {
+            break;
+        }
+        // The last partial frame is padded with silence
+        chunk[n..].fill(0);
+        writer.write_i16(&chunk);
+        while let Some(frame) = processor.next_frame() {
+            pcm.extend_from_slice(frame);
+        }
+    }
+    Ok(pcm)
+}
+
+/// A voice-like test signal at 16kHz: harmonic "syllables" on a drifting
+/// pitch, with a pause every two seconds
+pub fn speech_like_fixture(duration: Duration) -> Vec<i16> {
+    let rate = OPUS_SAMPLE_RATE as f64;
+    let len = (duration.as_secs_f64() * rate) as usize;
+    // Deterministic noise for breath and the room
+    let mut seed = 0x2545_f491u32;
+    let mut noise = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        seed as f64 / u32::MAX as f64 * 2.0 - 1.0
+    };
+
+    let mut phase = 0.0f64;
+    (0..len)
+        .map(|i| {
+            let t = i as f64 / rate;
+            let room = noise() * 0.001;
+            // 1.5s of speech, then 0.5s of pause
+            if t % 2.0 >= 1.5 {
+                return (room * 32767.0) as i16;
+            }
+            let pitch = 140.0 + 30.0 * (t * 0.7 * std::f64::consts::TAU).sin();
+            phase += pitch / rate * std::f64::consts::TAU;
+            // Four syllables a second
+            let envelope = (t * 4.0 * std::f64::consts::PI).sin().abs();
+            let voiced: f64 = (1..=12).map(|h| (phase * h as f64).sin() / h as f64).sum();
+            let sample = envelope * (voiced * 0.2 + noise() * 0.02) + room;
+            (sample.clamp(-1.0, 1.0) * 32767.0) as i16
+        })
+        .collect()
+}
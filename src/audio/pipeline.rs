@@ -0,0 +1,173 @@
+//! Real-time Capture Pipeline
+//!
+//! The cpal callback only copies samples into a preallocated lock-free SPSC
+//! ring buffer (`CaptureWriter`), so the audio thread never allocates, locks
+//! or blocks. The processing thread takes whole 20ms frames out of the ring
+//! with `FrameProcessor`, which downmixes them and resamples them to 16kHz
+//! mono (`FrameResampler`) in buffers allocated once up front.
+
+use anyhow::{anyhow, Result};
+use rtrb::{Consumer, Producer, RingBuffer};
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use super::resampler::FrameResampler;
+use super::{FRAME_DURATION_MS, OPUS_SAMPLE_RATE};
+
+/// Ring buffer length in frames: how far processing may fall behind (×20ms)
+/// before the audio thread has to drop samples
+const RING_FRAMES: usize = 25;
+
+/// Native sample rates the pipeline takes: at least a whole sample per
+/// frame, and frame buffers of a sane size
+const SUPPORTED_SAMPLE_RATES: RangeInclusive<u32> = 8000..=384_000;
+/// Most interleaved channels the pipeline takes
+const MAX_CHANNELS: u16 = 8;
+
+/// Check that audio in this native format can go through the pipeline
+pub(crate) fn check_native_format(sample_rate: u32, channels: u16) -> Result<()> {
+    if !SUPPORTED_SAMPLE_RATES.contains(&sample_rate) {
+        return Err(anyhow!(
+            "Unsupported sample rate {}Hz (expected {}-{}Hz)",
+            sample_rate,
+            SUPPORTED_SAMPLE_RATES.start(),
+            SUPPORTED_SAMPLE_RATES.end()
+        ));
+    }
+    if !(1..=MAX_CHANNELS).contains(&channels) {
+        return Err(anyhow!(
+            "Unsupported channel count {} (expected 1-{})",
+            channels,
+            MAX_CHANNELS
+        ));
+    }
+    Ok(())
+}
+
+/// Create the two ends of the pipeline for a device's native format;
+/// fails for formats outside the supported range
+pub fn capture_pipeline(
+    native_sample_rate: u32,
+    native_channels: u16,
+) -> Result<(CaptureWriter, FrameProcessor)> {
+    check_native_format(native_sample_rate, native_channels)?;
+    let channels = native_channels as usize;
+    let native_frame_len =
+        (native_sample_rate as u64 * FRAME_DURATION_MS as u64 / 1000) as usize * channels;
+    let output_frame_len = (OPUS_SAMPLE_RATE * FRAME_DURATION_MS / 1000) as usize;
+
+    let (producer, consumer) = RingBuffer::new(native_frame_len * RING_FRAMES);
+    let overrun_samples = Arc::new(AtomicU64::new(0));
+
+    let writer = CaptureWriter {
+        producer,
+        overrun_samples: overrun_samples.clone(),
+    };
+    let processor = FrameProcessor {
+        consumer,
+        channels,
+        frame: vec![0; native_frame_len],
+        mono: vec![0.0; native_frame_len / channels],
+        resampler: FrameResampler::new(native_sample_rate, native_frame_len / channels),
+        resampled: vec![0; output_frame_len],
+        volume: 0,
+        overrun_samples,
+    };
+    Ok((writer, processor))
+}
+
+/// Audio-thread end of the pipeline
+pub struct CaptureWriter {
+    producer: Producer<i16>,
+    overrun_samples: Arc<AtomicU64>,
+}
+
+impl CaptureWriter {
+    /// Queue interleaved i16 samples; whatever does not fit is dropped
+    pub fn write_i16(&mut self, data: &[i16]) {
+        let (_, rest) = self.producer.push_partial_slice(data);
+        self.record_overrun(rest.len());
+    }
+
+    /// Queue interleaved f32 samples, converting them on the way in
+    pub fn write_f32(&mut self, data: &[f32]) {
+        let n = data.len().min(self.producer.slots());
+        if let Ok(chunk) = self.producer.write_chunk_uninit(n) {
+            chunk.fill_from_iter(data.iter().map(|s| (s * 32767.0) as i16));
+        }
+        self.record_overrun(data.len() - n);
+    }
+
+    fn record_overrun(&self, dropped: usize) {
+        if dropped > 0 {
+            self.overrun_samples
+                .fetch_add(dropped as u64, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Processing-thread end of the pipeline
+pub struct FrameProcessor {
+    consumer: Consumer<i16>,
+    channels: usize,
+    /// One native frame, interleaved
+    frame: Vec<i16>,
+    /// One native frame, mono in [-1.0, 1.0]
+    mono: Vec<f32>,
+    resampler: FrameResampler,
+    /// One 16kHz mono frame, ready for the encoder
+    resampled: Vec<i16>,
+    volume: u32,
+    overrun_samples: Arc<AtomicU64>,
+}
+
+impl FrameProcessor {
+    /// Take the next 20ms frame as 16kHz mono, `None` until enough audio
+    /// has been captured for one
+    pub fn next_frame(&mut self) -> Option<&[i16]> {
+        // With non-integer ratios a native frame yields one sample more or
+        // less than a 16kHz frame, so this may take zero or two of them
+        while !self.resampler.pop(&mut self.resampled) {
+            if self.consumer.pop_entire_slice(&mut self.frame).is_err() {
+                return None;
+            }
+
+            // Step 1: Convert stereo to mono (if needed)
+            let scale = 1.0 / (32768.0 * self.channels as f32);
+            for (out, chunk) in self
+                .mono
+                .iter_mut()
+                .zip(self.frame.chunks_exact(self.channels))
+            {
+                let sum: i32 = chunk.iter().map(|&s| s as i32).sum();
+                *out = sum as f32 * scale;
+            }
+
+            // Calculate RMS volume, normalized to 0-100 (assume 10000 is max speech volume)
+            let sum_sq: f64 = self.mono.iter().map(|&s| (s as f64) * (s as f64)).sum();
+            let rms = (sum_sq / self.mono.len().max(1) as f64).sqrt() * 32768.0;
+            self.volume = (rms / 100.0).min(100.0) as u32;
+
+            // Step 2: Resample to 16kHz
+            self.resampler.push(&self.mono);
+        }
+
+        Some(&self.resampled)
+    }
+
+    /// Volume (0-100) of the last frame
+    pub fn volume(&self) -> u32 {
+        self.volume
+    }
+
+    /// Samples the audio thread dropped because the ring was full
+    pub fn overrun_samples(&self) -> u64 {
+        self.overrun_samples.load(Ordering::Relaxed)
+    }
+
+    /// Length of one native frame in interleaved samples
+    pub fn native_frame_len(&self) -> usize {
+        self.frame.len()
+    }
+}
@@ -0,0 +1,91 @@
+//! Band-limited Resampling
+//!
+//! Converts the capture device's native rate to the 16kHz the encoder
+//! expects with a windowed-sinc resampler (rubato). The filter state and
+//! fractional read position carry over from one frame to the next, so
+//! frame edges are seamless and non-integer ratios such as 44100→16000
+//! produce exactly the right number of samples over time. Output is
+//! queued and handed out in fixed-size frames.
+
+use rubato::{
+    calculate_cutoff, Resampler as _, SincFixedIn, SincInterpolationParameters,
+    SincInterpolationType, WindowFunction,
+};
+
+use super::OPUS_SAMPLE_RATE;
+
+/// Filter length in input samples
+const SINC_LEN: usize = 128;
+const WINDOW: WindowFunction = WindowFunction::BlackmanHarris2;
+
+/// Streaming resampler from a native rate to 16kHz mono
+pub struct FrameResampler {
+    /// `None` when the input is already 16kHz
+    sinc: Option<SincFixedIn<f32>>,
+    /// Output of one input chunk
+    chunk_out: Vec<f32>,
+    /// Resampled samples not yet handed out
+    pending: Vec<f32>,
+    input_len: usize,
+}
+
+impl FrameResampler {
+    /// Resampler taking input in chunks of exactly `input_len` mono samples
+    pub fn new(input_rate: u32, input_len: usize) -> Self {
+        let input_len = input_len.max(1);
+        let sinc = (input_rate != OPUS_SAMPLE_RATE).then(|| {
+            let parameters = SincInterpolationParameters {
+                sinc_len: SINC_LEN,
+                // Highest cutoff whose transition band still ends below 8kHz
+                f_cutoff: calculate_cutoff(SINC_LEN, WINDOW),
+                oversampling_factor: 256,
+                interpolation: SincInterpolationType::Linear,
+                window: WINDOW,
+            };
+            let ratio = OPUS_SAMPLE_RATE as f64 / input_rate.max(1) as f64;
+            SincFixedIn::new(ratio, 1.0, parameters, input_len, 1)
+                .expect("fixed ratio and mono input are always valid")
+        });
+        let chunk_len = sinc.as_ref().map_or(input_len, |s| s.output_frames_max());
+
+        Self {
+            sinc,
+            chunk_out: vec![0.0; chunk_len],
+            // Room for a chunk on top of a partly consumed frame
+            pending: Vec::with_capacity(chunk_len * 2 + input_len),
+            input_len,
+        }
+    }
+
+    /// Input chunk length in samples
+    pub fn input_len(&self) -> usize {
+        self.input_len
+    }
+
+    /// Resample one chunk of `input_len` samples in [-1.0, 1.0]
+    pub fn push(&mut self, input: &[f32]) {
+        debug_assert_eq!(input.len(), self.input_len);
+        match self.sinc.as_mut() {
+            Some(sinc) => {
+                match sinc.process_into_buffer(&[input], &mut [&mut self.chunk_out[..]], None) {
+                    Ok((_, written)) => self.pending.extend_from_slice(&self.chunk_out[..written]),
+                    Err(e) => tracing::warn!("Resampling failed: {}", e),
+                }
+            }
+            None => self.pending.extend_from_slice(input),
+        }
+    }
+
+    /// Fill `out` with the next resampled samples, `false` until enough
+    /// have been produced
+    pub fn pop(&mut self, out: &mut [i16]) -> bool {
+        if self.pending.len() < out.len() {
+            return false;
+        }
+        for (o, s) in out.iter_mut().zip(&self.pending) {
+            *o = (s.clamp(-1.0, 1.0) * 32767.0).round() as i16;
+        }
+        self.pending.drain(..out.len());
+        true
+    }
+}
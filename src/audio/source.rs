@@ -0,0 +1,599 @@
+//! Audio Sources
+//!
+//! `AudioCapture` records from an `AudioSource`: an input device through
+//! cpal by default, or a WAV/Ogg Opus file, raw PCM on stdin, or a generated
+//! signal, so the pipeline also runs headless and in tests. A source only
+//! delivers interleaved samples in its own format into a `capture_pipeline`;
+//! downmixing, resampling to 16kHz, Opus and the frame channel are the same
+//! for every source.
+//!
+//! Sources other than cpal run on their own thread, paced to real time.
+
+use anyhow::{anyhow, Context, Result};
+use cpal::traits::{DeviceTrait, StreamTrait};
+use cpal::SampleFormat;
+use std::any::Any;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::decode::AudioFileReader;
+use super::device::{default_input_device, find_input_device};
+use super::pipeline::{capture_pipeline, check_native_format, FrameProcessor};
+use super::{FRAME_DURATION_MS, OPUS_SAMPLE_RATE};
+
+/// Where recorded audio comes from
+pub trait AudioSource: Send + Sync {
+    /// Name for logs and `DeviceFallback` reports
+    fn name(&self) -> String;
+
+    /// Start delivering audio; it stops when the stream is dropped
+    fn open(&self) -> Result<SourceStream>;
+
+    /// What to use instead when this source cannot be opened or is lost
+    fn fallback(&self) -> Option<Arc<dyn AudioSource>> {
+        None
+    }
+}
+
+/// How a running source is doing, set from wherever it produces audio
+#[derive(Debug, Default)]
+pub struct SourceState {
+    lost: AtomicBool,
+    ended: AtomicBool,
+}
+
+impl SourceState {
+    /// The source went away (e.g. the device was unplugged)
+    pub fn set_lost(&self) {
+        self.lost.store(true, Ordering::SeqCst);
+    }
+
+    /// The source has no more audio (e.g. the end of a file)
+    pub fn set_ended(&self) {
+        self.ended.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::SeqCst)
+    }
+
+    pub fn is_ended(&self) -> bool {
+        self.ended.load(Ordering::SeqCst)
+    }
+}
+
+type AvailabilityCheck = Box<dyn Fn() -> bool>;
+
+/// An opened source: the processing end of its pipeline, and whatever keeps
+/// it producing (dropping the stream stops the source)
+pub struct SourceStream {
+    name: String,
+    pub(crate) processor: FrameProcessor,
+    state: Arc<SourceState>,
+    available: Option<AvailabilityCheck>,
+    _running: Box<dyn Any>,
+}
+
+impl SourceStream {
+    pub fn new(
+        name: impl Into<String>,
+        processor: FrameProcessor,
+        state: Arc<SourceState>,
+        running: impl Any,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            processor,
+            state,
+            available: None,
+            _running: Box::new(running),
+        }
+    }
+
+    /// Check used when the source stalls, for backends that do not report a
+    /// disconnect themselves
+    pub fn with_availability_check(mut self, check: impl Fn() -> bool + 'static) -> Self {
+        self.available = Some(Box::new(check));
+        self
+    }
+
+    /// Name of what is actually being recorded, e.g. the device name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_lost(&self) -> bool {
+        self.state.is_lost()
+    }
+
+    pub fn is_ended(&self) -> bool {
+        self.state.is_ended()
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.available.as_ref().is_none_or(|check| check())
+    }
+}
+
+/// An input device, opened in its native config
+#[derive(Debug, Clone, Default)]
+pub struct CpalSource {
+    /// `None` for the system default
+    device: Option<String>,
+}
+
+impl CpalSource {
+    /// The named input device, or the system default for `None`
+    pub fn new(device: Option<String>) -> Self {
+        Self { device }
+    }
+}
+
+impl AudioSource for CpalSource {
+    fn name(&self) -> String {
+        self.device
+            .clone()
+            .unwrap_or_else(|| "(default)".to_string())
+    }
+
+    fn open(&self) -> Result<SourceStream> {
+        let host = cpal::default_host();
+        let device = match self.device.as_deref() {
+            Some(name) => find_input_device(&host, name)
+                .ok_or_else(|| anyhow!("Input device '{}' not found", name))?,
+            None => default_input_device(&host)?,
+        };
+        open_device(&device)
+    }
+
+    /// Another device, or a lost default device, falls back to the
+    /// current default
+    fn fallback(&self) -> Option<Arc<dyn AudioSource>> {
+        Some(Arc::new(CpalSource::default()))
+    }
+}
+
+/// Start a stream on `device` in its native config, feeding a new pipeline
+fn open_device(device: &cpal::Device) -> Result<SourceStream> {
+    let name = device.name().unwrap_or_default();
+    println!("[AudioCapture] Device: {}", name);
+
+    // Get the device's default config - USE THIS EXACTLY
+    let supported_config = device.default_input_config()?;
+    println!("[AudioCapture] Device config: {:?}", supported_config);
+
+    let native_sample_rate = supported_config.sample_rate().0;
+    let native_channels = supported_config.channels();
+    let sample_format = supported_config.sample_format();
+
+    println!(
+        "[AudioCapture] Native: {}Hz, {} channels, {:?}",
+        native_sample_rate, native_channels, sample_format
+    );
+
+    // Use the device's EXACT config (don't override channels!)
+    let config = supported_config.config();
+    println!("[AudioCapture] Using config: {:?}", config);
+
+    // Preallocated ring buffer and frame buffers; see `pipeline`
+    let (mut writer, processor) = capture_pipeline(native_sample_rate, native_channels)?;
+    println!(
+        "[AudioCapture] Samples/frame: native={} ({}ch), opus={} (mono)",
+        processor.native_frame_len(),
+        native_channels,
+        OPUS_SAMPLE_RATE * FRAME_DURATION_MS / 1000
+    );
+
+    let state = Arc::new(SourceState::default());
+    let err_state = state.clone();
+    let err_fn = move |err| match err {
+        cpal::StreamError::DeviceNotAvailable => err_state.set_lost(),
+        err => println!("[AudioCapture] Stream error: {}", err),
+    };
+
+    // The callbacks run on the audio thread: no allocation, locking or blocking
+    let stream = match sample_format {
+        SampleFormat::I16 => {
+            println!("[AudioCapture] Building I16 stream");
+            device.build_input_stream(
+                &config,
+                move |data: &[i16], _: &cpal::InputCallbackInfo| {
+                    writer.write_i16(data);
+                },
+                err_fn,
+                None,
+            )?
+        }
+        SampleFormat::F32 => {
+            println!("[AudioCapture] Building F32 stream");
+            device.build_input_stream(
+                &config,
+                move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                    writer.write_f32(data);
+                },
+                err_fn,
+                None,
+            )?
+        }
+        format => {
+            return Err(anyhow!("Unsupported format: {:?}", format));
+        }
+    };
+
+    stream.play()?;
+    println!("[AudioCapture] Stream playing!");
+
+    let check_name = name.clone();
+    Ok(
+        SourceStream::new(name, processor, state, stream).with_availability_check(move || {
+            find_input_device(&cpal::default_host(), &check_name).is_some()
+        }),
+    )
+}
+
+/// A WAV or Ogg Opus file, played once at real-time speed
+#[derive(Debug, Clone)]
+pub struct FileSource {
+    path: PathBuf,
+}
+
+impl FileSource {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl AudioSource for FileSource {
+    fn name(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn open(&self) -> Result<SourceStream> {
+        let mut reader = AudioFileReader::open(&self.path)?;
+        println!(
+            "[AudioCapture] File: {} ({}Hz, {} channels)",
+            self.path.display(),
+            reader.sample_rate(),
+            reader.channels()
+        );
+        let sample_rate = reader.sample_rate();
+        let channels = reader.channels();
+        spawn_paced(self.name(), sample_rate, channels, move |buf| {
+            reader.read(buf).unwrap_or_else(|e| {
+                println!("[AudioCapture] File read error: {}", e);
+                0
+            })
+        })
+    }
+}
+
+/// Raw signed 16-bit little-endian PCM on stdin, e.g. from `sox` or `ffmpeg`
+#[derive(Debug, Clone)]
+pub struct StdinSource {
+    sample_rate: u32,
+    channels: u16,
+}
+
+impl StdinSource {
+    /// Fails for sample rates and channel counts the pipeline cannot take
+    pub fn new(sample_rate: u32, channels: u16) -> Result<Self> {
+        check_native_format(sample_rate, channels)?;
+        Ok(Self {
+            sample_rate,
+            channels,
+        })
+    }
+}
+
+impl Default for StdinSource {
+    /// 16kHz mono
+    fn default() -> Self {
+        Self {
+            sample_rate: OPUS_SAMPLE_RATE,
+            channels: 1,
+        }
+    }
+}
+
+impl AudioSource for StdinSource {
+    fn name(&self) -> String {
+        format!("stdin ({}Hz, {} channels)", self.sample_rate, self.channels)
+    }
+
+    fn open(&self) -> Result<SourceStream> {
+        let mut stdin = std::io::stdin();
+        let mut bytes = Vec::new();
+        spawn_paced(self.name(), self.sample_rate, self.channels, move |buf| {
+            // Fill a whole chunk unless stdin ends first
+            bytes.resize(buf.len() * 2, 0);
+            let mut filled = 0;
+            while filled < bytes.len() {
+                match stdin.read(&mut bytes[filled..]) {
+                    Ok(0) => break,
+                    Ok(n) => filled += n,
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                    Err(e) => {
+                        println!("[AudioCapture] stdin read error: {}", e);
+                        break;
+                    }
+                }
+            }
+            for (out, pair) in buf.iter_mut().zip(bytes[..filled].chunks_exact(2)) {
+                *out = i16::from_le_bytes([pair[0], pair[1]]);
+            }
+            filled / 2
+        })
+    }
+}
+
+/// One part of a `SyntheticSource`
+#[derive(Debug, Clone)]
+pub enum Signal {
+    /// A sine wave; amplitude is relative to full scale
+    Tone {
+        frequency: f32,
+        amplitude: f32,
+        duration: Duration,
+    },
+    Silence(Duration),
+    /// Recorded mono samples at the source's sample rate
+    Samples(Arc<[i16]>),
+}
+
+/// A generated test signal: tones, silence and recorded fixtures in
+/// sequence, at real-time speed
+#[derive(Debug, Clone)]
+pub struct SyntheticSource {
+    sample_rate: u32,
+    channels: u16,
+    signals: Vec<Signal>,
+}
+
+impl SyntheticSource {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            channels: 1,
+            signals: Vec::new(),
+        }
+    }
+
+    /// Deliver every sample on this many channels
+    pub fn with_channels(mut self, channels: u16) -> Self {
+        self.channels = channels.max(1);
+        self
+    }
+
+    pub fn tone(mut self, frequency: f32, amplitude: f32, duration: Duration) -> Self {
+        self.signals.push(Signal::Tone {
+            frequency,
+            amplitude,
+            duration,
+        });
+        self
+    }
+
+    pub fn silence(mut self, duration: Duration) -> Self {
+        self.signals.push(Signal::Silence(duration));
+        self
+    }
+
+    pub fn samples(mut self, samples: impl Into<Arc<[i16]>>) -> Self {
+        self.signals.push(Signal::Samples(samples.into()));
+        self
+    }
+
+    /// Total length of the signal
+    pub fn duration(&self) -> Duration {
+        let samples: usize = self.signals.iter().map(|s| self.signal_len(s)).sum();
+        Duration::from_secs_f64(samples as f64 / self.sample_rate as f64)
+    }
+
+    /// Length of a part in samples per channel
+    fn signal_len(&self, signal: &Signal) -> usize {
+        let samples = |d: &Duration| (d.as_secs_f64() * self.sample_rate as f64).round() as usize;
+        match signal {
+            Signal::Tone { duration, .. } => samples(duration),
+            Signal::Silence(duration) => samples(duration),
+            Signal::Samples(samples) => samples.len(),
+        }
+    }
+
+    /// Mono sample `index` of a part
+    fn sample(&self, signal: &Signal, index: usize) -> i16 {
+        match signal {
+            Signal::Tone {
+                frequency,
+                amplitude,
+                ..
+            } => {
+                let t = index as f64 / self.sample_rate as f64;
+                let value = (std::f64::consts::TAU * *frequency as f64 * t).sin();
+                (value * *amplitude as f64 * 32767.0) as i16
+            }
+            Signal::Silence(_) => 0,
+            Signal::Samples(samples) => samples[index],
+        }
+    }
+}
+
+impl AudioSource for SyntheticSource {
+    fn name(&self) -> String {
+        format!("synthetic ({:.1}s)", self.duration().as_secs_f32())
+    }
+
+    fn open(&self) -> Result<SourceStream> {
+        let source = self.clone();
+        let channels = self.channels as usize;
+        let (mut part, mut index) = (0, 0);
+        spawn_paced(self.name(), self.sample_rate, self.channels, move |buf| {
+            let mut n = 0;
+            for frame in buf.chunks_exact_mut(channels) {
+                while part < source.signals.len()
+                    && index == source.signal_len(&source.signals[part])
+                {
+                    part += 1;
+                    index = 0;
+                }
+                let Some(signal) = source.signals.get(part) else {
+                    break;
+                };
+                frame.fill(source.sample(signal, index));
+                index += 1;
+                n += channels;
+            }
+            n
+        })
+    }
+}
+
+/// Stops a source thread when dropped
+struct StopOnDrop(Arc<AtomicBool>);
+
+impl Drop for StopOnDrop {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Run `read` on a thread, feeding a new pipeline one frame of audio per
+/// frame duration; `read` fills a buffer with interleaved samples and
+/// returns how many it wrote, 0 at the end
+fn spawn_paced<F>(
+    name: String,
+    sample_rate: u32,
+    channels: u16,
+    mut read: F,
+) -> Result<SourceStream>
+where
+    F: FnMut(&mut [i16]) -> usize + Send + 'static,
+{
+    let (mut writer, processor) = capture_pipeline(sample_rate, channels)?;
+    let chunk_len = processor.native_frame_len();
+    let state = Arc::new(SourceState::default());
+    let running = Arc::new(AtomicBool::new(true));
+
+    let thread_state = state.clone();
+    let thread_running = running.clone();
+    thread::Builder::new()
+        .name("audio-source".to_string())
+        .spawn(move || {
+            let mut chunk = vec![0i16; chunk_len];
+            let frame = Duration::from_millis(FRAME_DURATION_MS as u64);
+            let started = Instant::now();
+            let mut frames = 0u32;
+            while thread_running.load(Ordering::SeqCst) {
+                let n = read(&mut chunk);
+                // A partial frame (a truncated stream) would shift the channels
+                let n = n - n % channels.max(1) as usize;
+                if n == 0 {
+                    thread_state.set_ended();
+                    break;
+                }
+                writer.write_i16(&chunk[..n]);
+
+                frames += 1;
+                if let Some(wait) =
+                    (started + frame * frames).checked_duration_since(Instant::now())
+                {
+                    thread::sleep(wait);
+                }
+            }
+        })
+        .context("Failed to start audio source thread")?;
+
+    Ok(SourceStream::new(
+        name,
+        processor,
+        state,
+        StopOnDrop(running),
+    ))
+}
+
+/// Build a source from a command-line spec:
+///
+/// - `mic` or `mic:NAME`: an input device (the default without a name)
+/// - `file:PATH`: a WAV or Ogg Opus file
+/// - `stdin[:RATE[:CHANNELS]]`: raw s16le PCM, 16kHz mono by default
+/// - `tone[:FREQ[:SECS]]`: a sine at -10dBFS, 440Hz for 5s by default
+/// - `silence[:SECS]`
+pub fn source_from_spec(spec: &str) -> Result<Arc<dyn AudioSource>> {
+    let (kind, arg) = match spec.split_once(':') {
+        Some((kind, arg)) => (kind, Some(arg)),
+        None => (spec, None),
+    };
+    let invalid =
+        |value: &str, what: &str| anyhow!("Invalid {} '{}' in source '{}'", what, value, spec);
+    let number = |value: &str, what: &str| -> Result<f64> {
+        value
+            .parse::<f64>()
+            .ok()
+            .filter(|v| *v > 0.0)
+            .ok_or_else(|| invalid(value, what))
+    };
+    let count = |value: &str, what: &str| -> Result<u32> {
+        value
+            .parse::<u32>()
+            .ok()
+            .filter(|v| *v > 0)
+            .ok_or_else(|| invalid(value, what))
+    };
+
+    let source: Arc<dyn AudioSource> = match kind {
+        "mic" => Arc::new(CpalSource::new(arg.map(str::to_string))),
+        "file" => Arc::new(FileSource::new(
+            arg.filter(|p| !p.is_empty())
+                .ok_or_else(|| anyhow!("Source 'file' needs a path: file:PATH"))?,
+        )),
+        "stdin" => {
+            let mut parts = arg.unwrap_or_default().split(':').filter(|p| !p.is_empty());
+            let rate = match parts.next() {
+                Some(rate) => count(rate, "sample rate")?,
+                None => OPUS_SAMPLE_RATE,
+            };
+            let channels = match parts.next() {
+                Some(channels) => u16::try_from(count(channels, "channel count")?)
+                    .map_err(|_| invalid(channels, "channel count"))?,
+                None => 1,
+            };
+            Arc::new(
+                StdinSource::new(rate, channels)
+                    .map_err(|e| anyhow!("{} in source '{}'", e, spec))?,
+            )
+        }
+        "tone" => {
+            let mut parts = arg.unwrap_or_default().split(':').filter(|p| !p.is_empty());
+            let frequency = match parts.next() {
+                Some(f) => number(f, "frequency")?,
+                None => 440.0,
+            };
+            let secs = match parts.next() {
+                Some(s) => number(s, "duration")?,
+                None => 5.0,
+            };
+            Arc::new(SyntheticSource::new(OPUS_SAMPLE_RATE).tone(
+                frequency as f32,
+                0.316,
+                Duration::from_secs_f64(secs),
+            ))
+        }
+        "silence" => {
+            let secs = match arg {
+                Some(s) => number(s, "duration")?,
+                None => 5.0,
+            };
+            Arc::new(SyntheticSource::new(OPUS_SAMPLE_RATE).silence(Duration::from_secs_f64(secs)))
+        }
+        _ => {
+            return Err(anyhow!(
+                "Unknown audio source '{}' (expected mic, file, stdin, tone or silence)",
+                spec
+            ))
+        }
+    };
+    Ok(source)
+}
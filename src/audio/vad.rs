@@ -0,0 +1,148 @@
+//! Voice Activity Detection
+//!
+//! A frame-level energy detector for 16kHz mono frames. A frame is loud
+//! when its level reaches `speech_threshold_db` (dBFS); speech starts after
+//! `min_speech` of consecutive loud frames, so clicks are ignored, and ends
+//! only after `hangover` of quiet ones, so pauses between words do not cut
+//! an utterance. The time spent outside speech drives the silence
+//! auto-stop.
+
+use std::time::Duration;
+
+use super::OPUS_SAMPLE_RATE;
+
+/// VAD thresholds and what to do with the result
+#[derive(Debug, Clone)]
+pub struct VadSettings {
+    /// Frame level (dBFS) at or above which a frame counts as loud
+    pub speech_threshold_db: f32,
+    /// Loud audio needed before speech is detected
+    pub min_speech: Duration,
+    /// Quiet audio after which detected speech ends
+    pub hangover: Duration,
+    /// Stop recording after this much silence following speech
+    pub silence_timeout: Option<Duration>,
+    /// Stop recording if nothing is said for this long after starting
+    pub no_speech_timeout: Option<Duration>,
+    /// Send nothing until speech is detected, except `leading_padding`
+    pub hold_leading_silence: bool,
+    /// Audio sent from before the detected start of speech
+    pub leading_padding: Duration,
+    /// Also stop when the server reports the end of an utterance
+    pub use_server_vad: bool,
+}
+
+impl Default for VadSettings {
+    fn default() -> Self {
+        Self {
+            speech_threshold_db: -40.0,
+            min_speech: Duration::from_millis(60),
+            hangover: Duration::from_millis(300),
+            silence_timeout: None,
+            no_speech_timeout: None,
+            hold_leading_silence: false,
+            leading_padding: Duration::from_millis(300),
+            use_server_vad: false,
+        }
+    }
+}
+
+/// Energy-based speech detector with onset delay and hangover
+#[derive(Debug)]
+pub struct EnergyVad {
+    settings: VadSettings,
+    speaking: bool,
+    heard_speech: bool,
+    /// Consecutive loud audio while not speaking
+    loud: Duration,
+    /// Consecutive quiet audio while speaking
+    quiet: Duration,
+    /// Audio outside speech since the last speech (or the start)
+    silence: Duration,
+}
+
+impl EnergyVad {
+    pub fn new(settings: VadSettings) -> Self {
+        Self {
+            settings,
+            speaking: false,
+            heard_speech: false,
+            loud: Duration::ZERO,
+            quiet: Duration::ZERO,
+            silence: Duration::ZERO,
+        }
+    }
+
+    pub fn settings(&self) -> &VadSettings {
+        &self.settings
+    }
+
+    /// Classify the next 16kHz mono frame; returns whether speech is active
+    pub fn process(&mut self, frame: &[i16]) -> bool {
+        let duration =
+            Duration::from_micros(frame.len() as u64 * 1_000_000 / OPUS_SAMPLE_RATE as u64);
+        let loud = frame_level_db(frame) >= self.settings.speech_threshold_db;
+
+        if loud {
+            self.quiet = Duration::ZERO;
+            if !self.speaking {
+                self.loud += duration;
+                if self.loud >= self.settings.min_speech {
+                    self.speaking = true;
+                    self.heard_speech = true;
+                }
+            }
+        } else {
+            self.loud = Duration::ZERO;
+            if self.speaking {
+                self.quiet += duration;
+                if self.quiet > self.settings.hangover {
+                    self.speaking = false;
+                }
+            }
+        }
+
+        if self.speaking {
+            self.silence = Duration::ZERO;
+        } else {
+            self.silence += duration;
+        }
+        self.speaking
+    }
+
+    /// Speech is currently active (including the hangover)
+    pub fn is_speaking(&self) -> bool {
+        self.speaking
+    }
+
+    /// Speech has been detected at least once
+    pub fn heard_speech(&self) -> bool {
+        self.heard_speech
+    }
+
+    /// Audio outside speech since speech last ended, or since the start
+    pub fn trailing_silence(&self) -> Duration {
+        self.silence
+    }
+
+    /// The silence (or no-speech) timeout has been reached
+    pub fn timed_out(&self) -> bool {
+        let timeout = if self.heard_speech {
+            self.settings.silence_timeout
+        } else {
+            self.settings.no_speech_timeout
+        };
+        timeout.is_some_and(|t| self.silence >= t)
+    }
+}
+
+/// RMS level of a frame in dBFS (-100 for digital silence)
+pub fn frame_level_db(frame: &[i16]) -> f32 {
+    let sum_sq: f64 = frame.iter().map(|&s| (s as f64) * (s as f64)).sum();
+    let rms = (sum_sq / frame.len().max(1) as f64).sqrt() / 32768.0;
+    if rms <= 1e-5 {
+        -100.0
+    } else {
+        (20.0 * rms.log10()) as f32
+    }
+}
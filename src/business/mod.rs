@@ -3,9 +3,19 @@
 //! Contains the core business logic for voice input control.
 
 mod hotkey_manager;
+mod session_archive;
+mod text_formatter;
 mod text_inserter;
+mod transcriber;
+mod vocabulary_corrector;
 mod voice_controller;
 
 pub use hotkey_manager::HotkeyManager;
-pub use text_inserter::TextInserter;
+pub use session_archive::{ArchiveRetention, ArchivedSession, SessionArchive, SessionRecord};
+pub use text_formatter::TextFormatter;
+pub use text_inserter::{TextBuffer, TextInserter};
+pub use transcriber::{
+    audio_files_in, FileTranscriber, Transcript, TranscriptFormat, TranscriptSegment,
+};
+pub use vocabulary_corrector::VocabularyCorrector;
 pub use voice_controller::VoiceController;
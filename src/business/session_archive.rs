@@ -0,0 +1,240 @@
+//! Session Archive
+//!
+//! Opt-in record of what was said next to what was recognized. A session's
+//! Opus frames are teed off on their way to the ASR client into
+//! `session-<unix ms>.opus` (Ogg Opus) in the archive directory; when the
+//! session ends, a `.json` sidecar with the typed transcript, segment
+//! timings and the focused app is written next to it. Old sessions are
+//! pruned by age and by the archive's total size.
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use super::transcriber::TranscriptSegment;
+use crate::asr::{Language, CHANNELS, FRAME_DURATION_MS, SAMPLE_RATE};
+use crate::audio::OggOpusWriter;
+
+/// Frames queued between the archive and the ASR client
+const TEE_QUEUE_FRAMES: usize = 100;
+
+/// How long archived sessions are kept
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ArchiveRetention {
+    /// Sessions older than this are deleted; `None` keeps them
+    pub max_age: Option<Duration>,
+    /// The oldest sessions are deleted while the archive is larger than
+    /// this; `None` never limits it
+    pub max_total_bytes: Option<u64>,
+}
+
+/// Sidecar of an archived session
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionRecord {
+    /// Unix time the session started, in milliseconds
+    pub started_at_ms: u64,
+    pub duration_ms: u64,
+    /// Application the text was typed into
+    pub focused_app: Option<String>,
+    pub language: String,
+    /// Text typed during the session
+    pub transcript: String,
+    /// Finalized segments, timed from the start of the audio
+    pub segments: Vec<TranscriptSegment>,
+    /// Ogg Opus file next to the sidecar
+    pub audio_file: String,
+}
+
+/// Directory of archived sessions
+#[derive(Debug, Clone)]
+pub struct SessionArchive {
+    dir: PathBuf,
+    retention: ArchiveRetention,
+}
+
+impl SessionArchive {
+    pub fn new(dir: impl Into<PathBuf>, retention: ArchiveRetention) -> Self {
+        Self {
+            dir: dir.into(),
+            retention,
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Open the audio file of a new session
+    pub fn start(
+        &self,
+        focused_app: Option<String>,
+        language: Language,
+    ) -> Result<ArchivedSession> {
+        fs::create_dir_all(&self.dir)?;
+        // Sessions started within the same millisecond get distinct files
+        let mut started_at_ms = current_time_ms();
+        while self
+            .dir
+            .join(format!("session-{}.opus", started_at_ms))
+            .exists()
+        {
+            started_at_ms += 1;
+        }
+        let audio_file = format!("session-{}.opus", started_at_ms);
+        let writer = OggOpusWriter::create(
+            self.dir.join(&audio_file),
+            SAMPLE_RATE,
+            CHANNELS,
+            FRAME_DURATION_MS,
+        )?;
+
+        Ok(ArchivedSession {
+            archive: self.clone(),
+            started_at_ms,
+            audio_file,
+            focused_app,
+            language,
+            writer: Some(writer),
+            tee: None,
+        })
+    }
+
+    /// Delete sessions beyond the retention limits; returns how many
+    pub fn prune(&self) -> Result<usize> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+
+        // A session's audio and sidecar share the `session-<ms>` stem
+        let mut sessions: BTreeMap<u64, Vec<(PathBuf, u64)>> = BTreeMap::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let started_at_ms = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.strip_prefix("session-"))
+                .and_then(|ms| ms.parse().ok());
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            if let Some(started_at_ms) = started_at_ms {
+                sessions
+                    .entry(started_at_ms)
+                    .or_default()
+                    .push((path, size));
+            }
+        }
+
+        let now = current_time_ms();
+        let mut total: u64 = sessions.values().flatten().map(|(_, size)| size).sum();
+        let mut removed = 0;
+        // Oldest first
+        for (started_at_ms, files) in sessions {
+            let expired = self
+                .retention
+                .max_age
+                .is_some_and(|age| now.saturating_sub(started_at_ms) > age.as_millis() as u64);
+            let oversized = self
+                .retention
+                .max_total_bytes
+                .is_some_and(|max| total > max);
+            if !expired && !oversized {
+                break;
+            }
+            for (path, size) in files {
+                fs::remove_file(&path)?;
+                total -= size;
+            }
+            removed += 1;
+        }
+        if removed > 0 {
+            tracing::info!("Pruned {} archived sessions", removed);
+        }
+        Ok(removed)
+    }
+}
+
+/// A session being archived
+pub struct ArchivedSession {
+    archive: SessionArchive,
+    started_at_ms: u64,
+    audio_file: String,
+    focused_app: Option<String>,
+    language: Language,
+    /// Handed to the tee task once audio flows
+    writer: Option<OggOpusWriter>,
+    tee: Option<JoinHandle<Result<OggOpusWriter>>>,
+}
+
+impl ArchivedSession {
+    /// Archive the frames of `audio_rx` on their way to the returned receiver
+    pub fn tee(&mut self, mut audio_rx: mpsc::Receiver<Vec<u8>>) -> mpsc::Receiver<Vec<u8>> {
+        let (tx, rx) = mpsc::channel(TEE_QUEUE_FRAMES);
+        let Some(mut writer) = self.writer.take() else {
+            return audio_rx;
+        };
+        self.tee = Some(tokio::spawn(async move {
+            let mut result = Ok(());
+            while let Some(frame) = audio_rx.recv().await {
+                if result.is_ok() {
+                    result = writer.write_packet(&frame);
+                }
+                // The ASR session may end first; keep archiving what is captured
+                let _ = tx.send(frame).await;
+            }
+            result.map(|_| writer)
+        }));
+        rx
+    }
+
+    /// Close the audio file once capture has ended, write the sidecar and
+    /// apply retention; returns the sidecar, `None` if there was no audio
+    pub async fn finish(
+        mut self,
+        transcript: String,
+        segments: Vec<TranscriptSegment>,
+    ) -> Result<Option<PathBuf>> {
+        let writer = match self.tee.take() {
+            Some(tee) => tee
+                .await
+                .map_err(|e| anyhow!("Archive task failed: {}", e))??,
+            None => self
+                .writer
+                .take()
+                .ok_or_else(|| anyhow!("Archived session has no audio file"))?,
+        };
+        let audio_path = self.archive.dir.join(&self.audio_file);
+        let packets = writer.finish()?;
+        if packets == 0 {
+            fs::remove_file(&audio_path)?;
+            return Ok(None);
+        }
+
+        let record = SessionRecord {
+            started_at_ms: self.started_at_ms,
+            duration_ms: packets * FRAME_DURATION_MS as u64,
+            focused_app: self.focused_app,
+            language: self.language.code().to_string(),
+            transcript,
+            segments,
+            audio_file: self.audio_file,
+        };
+        let sidecar = audio_path.with_extension("json");
+        fs::write(&sidecar, serde_json::to_string_pretty(&record)?)?;
+
+        self.archive.prune()?;
+        Ok(Some(sidecar))
+    }
+}
+
+fn current_time_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
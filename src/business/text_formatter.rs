@@ -0,0 +1,127 @@
+//! Text Formatter
+//!
+//! Adjusts punctuation and digits of recognized text to the session's
+//! recognition language before it is typed:
+//!
+//! - zh-CN: ASCII punctuation after Chinese text becomes full-width
+//! - en-US: full-width punctuation becomes ASCII, followed by a space
+//! - mixed: punctuation follows the script before it, and Chinese is
+//!   separated from English words and numbers by a space
+//!
+//! Full-width digits and letters are always narrowed to ASCII.
+
+use crate::asr::Language;
+
+/// Language-specific formatting of recognized text
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TextFormatter {
+    language: Language,
+}
+
+impl TextFormatter {
+    pub fn new(language: Language) -> Self {
+        Self { language }
+    }
+
+    pub fn language(&self) -> Language {
+        self.language
+    }
+
+    pub fn format(&self, text: &str) -> String {
+        let chars: Vec<char> = text.chars().map(narrow).collect();
+        let mut formatted = String::with_capacity(text.len());
+        // Last letter, digit or Chinese character; punctuation takes its script
+        let mut context: Option<char> = None;
+        for (i, &c) in chars.iter().enumerate() {
+            let prev = formatted.chars().last();
+            let next = chars.get(i + 1).copied();
+            let wide = match (self.language, context) {
+                (Language::EnUs, _) => false,
+                (_, Some(p)) if is_cjk(p) => true,
+                (Language::ZhCn, _) => is_wide_punct(c),
+                (Language::Mixed, Some(_)) => false,
+                (Language::Mixed, None) => is_wide_punct(c),
+            };
+
+            if wide {
+                if let Some(w) = widen_punct(c, next) {
+                    formatted.push(w);
+                    continue;
+                }
+            } else if let Some(n) = narrow_punct(c) {
+                formatted.push_str(n);
+                if next.is_some_and(|n| !n.is_whitespace() && !is_closing(n)) {
+                    formatted.push(' ');
+                }
+                continue;
+            }
+
+            if self.language == Language::Mixed
+                && prev.is_some_and(|p| (is_cjk(p) && is_latin(c)) || (is_latin(p) && is_cjk(c)))
+            {
+                formatted.push(' ');
+            }
+            formatted.push(c);
+            if is_cjk(c) || is_latin(c) {
+                context = Some(c);
+            }
+        }
+        formatted
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c, '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' | '\u{F900}'..='\u{FAFF}')
+}
+
+fn is_latin(c: char) -> bool {
+    c.is_ascii_alphanumeric()
+}
+
+fn is_wide_punct(c: char) -> bool {
+    narrow_punct(c).is_some()
+}
+
+fn is_closing(c: char) -> bool {
+    matches!(
+        c,
+        ')' | ']' | '"' | '\'' | ',' | '.' | '?' | '!' | ':' | ';' | '）' | '”' | '’'
+    ) || is_wide_punct(c)
+}
+
+/// Full-width digits and letters to ASCII
+fn narrow(c: char) -> char {
+    match c {
+        '０'..='９' | 'Ａ'..='Ｚ' | 'ａ'..='ｚ' => {
+            char::from_u32(c as u32 - 0xFEE0).unwrap_or(c)
+        }
+        _ => c,
+    }
+}
+
+/// ASCII form of a full-width sentence punctuation mark
+fn narrow_punct(c: char) -> Option<&'static str> {
+    Some(match c {
+        '，' | '、' => ",",
+        '。' => ".",
+        '？' => "?",
+        '！' => "!",
+        '：' => ":",
+        '；' => ";",
+        _ => return None,
+    })
+}
+
+/// Full-width form of an ASCII punctuation mark; a '.' followed by a
+/// digit is a decimal point and stays
+fn widen_punct(c: char, next: Option<char>) -> Option<char> {
+    Some(match c {
+        ',' => '，',
+        '.' if !next.is_some_and(|n| n.is_ascii_digit()) => '。',
+        '?' => '？',
+        '!' => '！',
+        ':' => '：',
+        ';' => '；',
+        _ => return None,
+    })
+}
@@ -4,6 +4,7 @@
 
 use crate::platform::{PlatformFactory, TextAction};
 use anyhow::Result;
+use std::sync::{Arc, Mutex};
 
 /// Text inserter service using platform-specific implementation
 pub struct TextInserter {
@@ -18,6 +19,11 @@ impl TextInserter {
         }
     }
 
+    /// Create a text inserter backed by a custom action (e.g. `TextBuffer`)
+    pub fn with_action(inner: Box<dyn TextAction>) -> Self {
+        Self { inner }
+    }
+
     /// Insert text into the currently focused window
     pub fn insert(&self, text: &str) -> Result<()> {
         self.inner.insert(text)
@@ -34,3 +40,41 @@ impl Default for TextInserter {
         Self::new()
     }
 }
+
+/// In-memory text target, used to replay sessions offline
+///
+/// Applies inserts and deletions to a string instead of the focused window,
+/// so the text a session would have typed can be inspected.
+#[derive(Clone, Default)]
+pub struct TextBuffer {
+    text: Arc<Mutex<String>>,
+}
+
+impl TextBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Text typed so far
+    pub fn text(&self) -> String {
+        self.text.lock().map(|t| t.clone()).unwrap_or_default()
+    }
+}
+
+impl TextAction for TextBuffer {
+    fn insert(&self, text: &str) -> Result<()> {
+        if let Ok(mut buffer) = self.text.lock() {
+            buffer.push_str(text);
+        }
+        Ok(())
+    }
+
+    fn delete_chars(&self, count: usize) -> Result<()> {
+        if let Ok(mut buffer) = self.text.lock() {
+            for _ in 0..count {
+                buffer.pop();
+            }
+        }
+        Ok(())
+    }
+}
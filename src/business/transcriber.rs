@@ -0,0 +1,371 @@
+//! File Transcription
+//!
+//! Transcribes a recorded audio file (WAV, Ogg Opus or MP3) over the same
+//! realtime ASR session as dictation. A blocking thread decodes the file,
+//! resamples it to 16kHz mono and Opus-encodes it; frames go out as fast as
+//! the session takes them, or at most `max_speed` times real time, so a long
+//! recording takes a fraction of its length. Finalized segments are kept
+//! with their timings and rendered as plain text, JSON lines, SRT or VTT.
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+use super::voice_controller::SegmentTracker;
+use crate::asr::{
+    AsrClient, AsrError, RecognitionSegment, ResponseType, SessionStats, CHANNELS,
+    FRAME_DURATION_MS, SAMPLE_RATE,
+};
+use crate::audio::{capture_pipeline, AudioFileReader, OpusEncoder, OpusSettings};
+use crate::business::{TextFormatter, VocabularyCorrector};
+
+/// Extensions picked up when transcribing a directory
+const AUDIO_EXTENSIONS: &[&str] = &["wav", "ogg", "opus", "mp3"];
+
+/// Encoded frames queued ahead of the session (1s of audio)
+const SEND_QUEUE_FRAMES: usize = 50;
+
+/// Silence sent after the file, flushing the resampler and giving the last
+/// word room to finalize
+const TRAILING_SILENCE_FRAMES: usize = 10;
+
+/// Output format of a transcript
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TranscriptFormat {
+    /// One segment per line
+    #[default]
+    Text,
+    /// One JSON object per segment: `start_ms`, `end_ms`, `text`
+    JsonLines,
+    Srt,
+    Vtt,
+}
+
+impl TranscriptFormat {
+    /// Parse a format name or file extension (`txt`, `jsonl`, `srt`, `vtt`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "txt" | "text" => Some(Self::Text),
+            "jsonl" | "json" => Some(Self::JsonLines),
+            "srt" => Some(Self::Srt),
+            "vtt" => Some(Self::Vtt),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Text => "txt",
+            Self::JsonLines => "jsonl",
+            Self::Srt => "srt",
+            Self::Vtt => "vtt",
+        }
+    }
+}
+
+/// One finalized segment; times are milliseconds from the start of the audio
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TranscriptSegment {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub text: String,
+}
+
+impl TranscriptSegment {
+    /// Timing of a recognized segment, from its own times or its words';
+    /// untimed segments follow the previous one
+    pub(crate) fn from_recognized(
+        segment: &RecognitionSegment,
+        text: String,
+        previous_end: u64,
+    ) -> Self {
+        let start_ms = segment.start_ms().unwrap_or(previous_end);
+        let end_ms = segment.end_ms().unwrap_or(start_ms).max(start_ms);
+        Self {
+            start_ms,
+            end_ms,
+            text,
+        }
+    }
+}
+
+/// The result of transcribing one file
+#[derive(Debug, Clone, Default)]
+pub struct Transcript {
+    pub segments: Vec<TranscriptSegment>,
+    /// Length of the audio in the file
+    pub audio_duration: Duration,
+    /// Wall-clock time the transcription took
+    pub elapsed: Duration,
+    pub stats: Option<SessionStats>,
+}
+
+impl Transcript {
+    /// How many times faster than real time the file was transcribed
+    pub fn speed(&self) -> f64 {
+        self.audio_duration.as_secs_f64() / self.elapsed.as_secs_f64().max(0.001)
+    }
+
+    pub fn render(&self, format: TranscriptFormat) -> String {
+        let mut out = String::new();
+        match format {
+            TranscriptFormat::Text => {
+                for segment in &self.segments {
+                    let _ = writeln!(out, "{}", segment.text);
+                }
+            }
+            TranscriptFormat::JsonLines => {
+                for segment in &self.segments {
+                    let line = serde_json::to_string(segment)
+                        .expect("transcript segments always serialize");
+                    let _ = writeln!(out, "{}", line);
+                }
+            }
+            TranscriptFormat::Srt => {
+                for (i, segment) in self.segments.iter().enumerate() {
+                    let _ = writeln!(
+                        out,
+                        "{}\n{} --> {}\n{}\n",
+                        i + 1,
+                        timestamp(segment.start_ms, ','),
+                        timestamp(segment.end_ms, ','),
+                        segment.text
+                    );
+                }
+            }
+            TranscriptFormat::Vtt => {
+                out.push_str("WEBVTT\n\n");
+                for segment in &self.segments {
+                    let _ = writeln!(
+                        out,
+                        "{} --> {}\n{}\n",
+                        timestamp(segment.start_ms, '.'),
+                        timestamp(segment.end_ms, '.'),
+                        segment.text
+                    );
+                }
+            }
+        }
+        out
+    }
+}
+
+/// `HH:MM:SS,mmm` (SRT) or `HH:MM:SS.mmm` (VTT)
+fn timestamp(ms: u64, separator: char) -> String {
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        separator,
+        ms % 1000
+    )
+}
+
+/// Audio files directly inside `dir`, sorted by name
+pub fn audio_files_in(dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+    let dir = dir.as_ref();
+    let entries =
+        std::fs::read_dir(dir).map_err(|e| anyhow!("Failed to read {}: {}", dir.display(), e))?;
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Transcribes audio files through an `AsrClient`
+pub struct FileTranscriber {
+    client: Arc<AsrClient>,
+    max_speed: Option<f64>,
+    opus: OpusSettings,
+    formatter: TextFormatter,
+    corrector: Option<Arc<VocabularyCorrector>>,
+}
+
+impl FileTranscriber {
+    pub fn new(client: Arc<AsrClient>) -> Self {
+        Self {
+            client,
+            max_speed: None,
+            opus: OpusSettings::default(),
+            formatter: TextFormatter::default(),
+            corrector: None,
+        }
+    }
+
+    /// Cap streaming at `factor` times real time, for servers that reject
+    /// audio arriving too fast; `None` sends as fast as the session allows
+    pub fn with_max_speed(mut self, factor: Option<f64>) -> Self {
+        self.max_speed = factor.filter(|f| *f > 0.0);
+        self
+    }
+
+    /// Encode the audio with these settings (`[audio.opus]`)
+    pub fn with_opus(mut self, settings: OpusSettings) -> Self {
+        self.opus = settings;
+        self
+    }
+
+    pub fn with_formatter(mut self, formatter: TextFormatter) -> Self {
+        self.formatter = formatter;
+        self
+    }
+
+    pub fn with_corrector(mut self, corrector: Arc<VocabularyCorrector>) -> Self {
+        self.corrector = Some(corrector);
+        self
+    }
+
+    pub async fn transcribe(&self, path: impl AsRef<Path>) -> Result<Transcript> {
+        let started = Instant::now();
+        // Unreadable files fail before a session is opened
+        let reader = AudioFileReader::open(path)?;
+
+        let (audio_tx, audio_rx) = mpsc::channel(SEND_QUEUE_FRAMES);
+        let mut result_rx = self.client.start_realtime(audio_rx).await?;
+        let max_speed = self.max_speed;
+        let opus = self.opus.clone();
+        let streaming =
+            tokio::task::spawn_blocking(move || stream_file(reader, audio_tx, &opus, max_speed));
+
+        let mut tracker = SegmentTracker::default();
+        let mut segments = Vec::new();
+        let mut interim = Vec::new();
+        let mut stats = None;
+        while let Some(mut response) = result_rx.recv().await {
+            match response.response_type {
+                ResponseType::InterimResult | ResponseType::FinalResult => {
+                    if response.segments.is_empty() {
+                        // Responses built without segments carry only text
+                        if response.response_type == ResponseType::FinalResult {
+                            self.push_segment(
+                                &mut segments,
+                                &RecognitionSegment {
+                                    text: response.text.clone(),
+                                    ..Default::default()
+                                },
+                            );
+                        }
+                        continue;
+                    }
+                    let (committed, pending) = tracker.split_segments(&response.segments);
+                    for segment in committed {
+                        self.push_segment(&mut segments, segment);
+                    }
+                    interim = pending.into_iter().cloned().collect();
+                }
+                ResponseType::SessionFinished => {
+                    stats = response.stats.take();
+                    break;
+                }
+                ResponseType::Error => {
+                    // The session drops the audio channel, ending the stream
+                    let error = response.error.unwrap_or(AsrError::Rejected {
+                        status_code: response.status_code,
+                        message: response.error_msg,
+                    });
+                    return Err(error.into());
+                }
+                _ => {}
+            }
+        }
+        // Text the server never finalized is still its best guess
+        for segment in &interim {
+            self.push_segment(&mut segments, segment);
+        }
+
+        let audio_duration = streaming
+            .await
+            .map_err(|e| anyhow!("Audio streaming failed: {}", e))??;
+        Ok(Transcript {
+            segments,
+            audio_duration,
+            elapsed: started.elapsed(),
+            stats,
+        })
+    }
+
+    /// Correct and format a segment's text, and resolve its timing
+    fn push_segment(&self, segments: &mut Vec<TranscriptSegment>, segment: &RecognitionSegment) {
+        let mut text = segment.text.clone();
+        if let Some(ref corrector) = self.corrector {
+            text = corrector.correct(&text);
+        }
+        let text = self.formatter.format(text.trim());
+        if text.is_empty() {
+            return;
+        }
+
+        let previous_end = segments.last().map_or(0, |s| s.end_ms);
+        segments.push(TranscriptSegment::from_recognized(
+            segment,
+            text,
+            previous_end,
+        ));
+    }
+}
+
+/// Decode, resample and encode a file into `tx`; returns the audio length
+fn stream_file(
+    mut reader: AudioFileReader,
+    tx: mpsc::Sender<Vec<u8>>,
+    opus: &OpusSettings,
+    max_speed: Option<f64>,
+) -> Result<Duration> {
+    let (mut writer, mut processor) = capture_pipeline(reader.sample_rate(), reader.channels())?;
+    let mut encoder = OpusEncoder::with_settings(SAMPLE_RATE, CHANNELS, opus)?;
+    if processor.native_frame_len() == 0 {
+        // An empty chunk never reaches the end of the file
+        return Err(anyhow!(
+            "Cannot stream audio at {}Hz: a frame would hold no samples",
+            reader.sample_rate()
+        ));
+    }
+    let mut chunk = vec![0i16; processor.native_frame_len()];
+    let frame_duration = Duration::from_millis(FRAME_DURATION_MS as u64);
+
+    let started = Instant::now();
+    let mut samples_read = 0u64;
+    let mut frames_sent = 0u32;
+    let mut trailing = 0;
+    'stream: while trailing < TRAILING_SILENCE_FRAMES {
+        let n = reader.read(&mut chunk)?;
+        if n < chunk.len() {
+            chunk[n..].fill(0);
+            trailing += 1;
+        }
+        samples_read += n as u64;
+
+        writer.write_i16(&chunk);
+        while let Some(frame) = processor.next_frame() {
+            let packet = encoder.encode_samples(frame)?;
+            if let Some(speed) = max_speed {
+                let due = frame_duration.mul_f64(frames_sent as f64 / speed);
+                if let Some(wait) = due.checked_sub(started.elapsed()) {
+                    std::thread::sleep(wait);
+                }
+            }
+            if tx.blocking_send(packet).is_err() {
+                // The session ended early; its result says why
+                break 'stream;
+            }
+            frames_sent += 1;
+        }
+    }
+
+    let frames = samples_read / reader.channels().max(1) as u64;
+    Ok(Duration::from_secs_f64(
+        frames as f64 / reader.sample_rate().max(1) as f64,
+    ))
+}
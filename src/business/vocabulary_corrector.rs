@@ -0,0 +1,168 @@
+//! Vocabulary Corrector
+//!
+//! Fallback for servers that ignore hotwords: rewrites final text so that
+//! words sounding like a vocabulary term are spelled like it. Chinese
+//! characters are compared by pinyin, toneless and with the usual
+//! confusions folded together (z/zh, c/ch, s/sh, n/l, an/ang, en/eng,
+//! in/ing); heteronyms match on any of their readings. Other characters
+//! must match case-insensitively, so "tauri" becomes "Tauri".
+
+use pinyin::ToPinyinMulti;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+use crate::data::Vocabulary;
+
+/// One character of a term or of the text being corrected
+#[derive(Debug, Clone, PartialEq)]
+enum Sound {
+    /// Chinese character: its folded readings
+    Han(Vec<String>),
+    /// Anything else, lowercased
+    Other(char),
+}
+
+impl Sound {
+    fn of(c: char) -> Self {
+        match c.to_pinyin_multi() {
+            Some(readings) => {
+                let mut folded: Vec<String> =
+                    readings.into_iter().map(|p| fold(p.plain())).collect();
+                folded.dedup();
+                Sound::Han(folded)
+            }
+            None => Sound::Other(c.to_lowercase().next().unwrap_or(c)),
+        }
+    }
+
+    fn matches(&self, other: &Sound) -> bool {
+        match (self, other) {
+            (Sound::Han(a), Sound::Han(b)) => a.iter().any(|r| b.contains(r)),
+            (Sound::Other(a), Sound::Other(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+/// Fold a toneless syllable so commonly confused ones compare equal
+fn fold(syllable: &str) -> String {
+    let mut s = syllable.to_string();
+    for (from, to) in [("zh", "z"), ("ch", "c"), ("sh", "s")] {
+        if let Some(rest) = s.strip_prefix(from) {
+            s = format!("{}{}", to, rest);
+            break;
+        }
+    }
+    if let Some(rest) = s.strip_prefix('n') {
+        if !rest.is_empty() && rest != "g" {
+            s = format!("l{}", rest);
+        }
+    }
+    if s.len() > 2 && s.ends_with("ng") {
+        s.pop();
+    }
+    s
+}
+
+struct Entry {
+    term: String,
+    sounds: Vec<Sound>,
+    weight: f32,
+    /// Latin-only terms must not match inside a longer word
+    word_bounded: bool,
+}
+
+/// Pinyin-aware fuzzy correction of recognized text against the vocabulary
+pub struct VocabularyCorrector {
+    entries: RwLock<Vec<Entry>>,
+    enabled: AtomicBool,
+}
+
+impl VocabularyCorrector {
+    pub fn new(vocabulary: &Vocabulary) -> Self {
+        let corrector = Self {
+            entries: RwLock::new(Vec::new()),
+            enabled: AtomicBool::new(true),
+        };
+        corrector.set_vocabulary(vocabulary);
+        corrector
+    }
+
+    /// Turn correction on or off (`[vocabulary] correction`)
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::SeqCst);
+    }
+
+    /// Replace the terms being corrected towards
+    pub fn set_vocabulary(&self, vocabulary: &Vocabulary) {
+        let mut entries: Vec<Entry> = vocabulary
+            .terms()
+            .iter()
+            // A single character sounds like too many others
+            .filter(|t| t.term.chars().count() >= 2)
+            .map(|t| Entry {
+                term: t.term.clone(),
+                sounds: t.term.chars().map(Sound::of).collect(),
+                weight: t.effective_weight(),
+                word_bounded: t.term.is_ascii(),
+            })
+            .collect();
+        // Prefer heavier, then longer terms
+        entries.sort_by(|a, b| {
+            b.weight
+                .total_cmp(&a.weight)
+                .then(b.sounds.len().cmp(&a.sounds.len()))
+        });
+        if let Ok(mut current) = self.entries.write() {
+            *current = entries;
+        }
+    }
+
+    /// Rewrite every stretch of `text` that sounds like a vocabulary term
+    pub fn correct(&self, text: &str) -> String {
+        if !self.enabled.load(Ordering::SeqCst) {
+            return text.to_string();
+        }
+        let entries = match self.entries.read() {
+            Ok(entries) if !entries.is_empty() => entries,
+            _ => return text.to_string(),
+        };
+
+        let chars: Vec<char> = text.chars().collect();
+        let sounds: Vec<Sound> = chars.iter().map(|&c| Sound::of(c)).collect();
+        let mut corrected = String::with_capacity(text.len());
+        let mut i = 0;
+        while i < chars.len() {
+            let found = entries.iter().find(|entry| {
+                let end = i + entry.sounds.len();
+                end <= chars.len()
+                    && entry
+                        .sounds
+                        .iter()
+                        .zip(&sounds[i..end])
+                        .all(|(a, b)| a.matches(b))
+                    && (!entry.word_bounded || is_word_boundary(&chars, i, end))
+            });
+            match found {
+                Some(entry) => {
+                    corrected.push_str(&entry.term);
+                    i += entry.sounds.len();
+                }
+                None => {
+                    corrected.push(chars[i]);
+                    i += 1;
+                }
+            }
+        }
+
+        if corrected != text {
+            tracing::debug!("Vocabulary correction: '{}' -> '{}'", text, corrected);
+        }
+        corrected
+    }
+}
+
+fn is_word_boundary(chars: &[char], start: usize, end: usize) -> bool {
+    let is_word = |c: &char| c.is_ascii_alphanumeric();
+    (start == 0 || !is_word(&chars[start - 1])) && !chars.get(end).is_some_and(is_word)
+}
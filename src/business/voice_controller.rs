@@ -1,14 +1,40 @@
 //! Voice Controller
 //!
 //! Coordinates voice input between audio capture, ASR, and text insertion.
+//!
+//! When a session ends its `SessionStats` are completed with the capture
+//! side's dropped frames, logged and passed to the stats callback.
+//!
+//! Finalized text goes through the `VocabularyCorrector`, if one is set,
+//! before it is typed; all text is formatted for the session's language.
+//!
+//! With local VAD enabled on the `AudioCapture`, a recording also ends by
+//! itself after enough silence (or, optionally, when the server reports the
+//! end of an utterance): capture stops, the remaining text is finalized and
+//! typed, and the auto-stop callback fires.
+//!
+//! With a `SessionArchive` set, each session's audio is archived along with
+//! the text typed, its segment timings and the focused app.
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
 
-use crate::asr::{AsrClient, ResponseType};
+use crate::asr::{
+    AsrClient, AsrError, AsrResponse, Language, RecognitionSegment, ResponseType, SessionOptions,
+    SessionStats,
+};
 use crate::audio::AudioCapture;
-use crate::business::TextInserter;
+use crate::business::{
+    ArchivedSession, SessionArchive, TextFormatter, TextInserter, TranscriptSegment,
+    VocabularyCorrector,
+};
+use crate::platform::focused_app;
+
+/// How long a stopped session may take to deliver its final stats
+const STATS_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// Voice input controller
 pub struct VoiceController {
@@ -18,6 +44,11 @@ pub struct VoiceController {
     is_recording: Arc<AtomicBool>,
     stop_signal: Arc<AtomicBool>,
     on_result: Option<Arc<dyn Fn(String, bool) + Send + Sync + 'static>>,
+    on_error: Option<Arc<dyn Fn(AsrError) + Send + Sync + 'static>>,
+    on_stats: Option<Arc<dyn Fn(SessionStats) + Send + Sync + 'static>>,
+    on_auto_stop: Option<Arc<dyn Fn() + Send + Sync + 'static>>,
+    corrector: Option<Arc<VocabularyCorrector>>,
+    archive: Option<SessionArchive>,
 }
 
 impl VoiceController {
@@ -34,6 +65,11 @@ impl VoiceController {
             is_recording: Arc::new(AtomicBool::new(false)),
             stop_signal: Arc::new(AtomicBool::new(false)),
             on_result: None,
+            on_error: None,
+            on_stats: None,
+            on_auto_stop: None,
+            corrector: None,
+            archive: None,
         }
     }
 
@@ -45,6 +81,51 @@ impl VoiceController {
         self.on_result = Some(Arc::new(callback));
     }
 
+    /// Set callback for ASR errors that end a running session
+    pub fn set_on_error<F>(&mut self, callback: F)
+    where
+        F: Fn(AsrError) + Send + Sync + 'static,
+    {
+        self.on_error = Some(Arc::new(callback));
+    }
+
+    /// Set callback for the stats of each finished session
+    pub fn set_on_stats<F>(&mut self, callback: F)
+    where
+        F: Fn(SessionStats) + Send + Sync + 'static,
+    {
+        self.on_stats = Some(Arc::new(callback));
+    }
+
+    /// Set callback for recordings that ended by themselves (VAD)
+    pub fn set_on_auto_stop<F>(&mut self, callback: F)
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.on_auto_stop = Some(Arc::new(callback));
+    }
+
+    /// Correct finalized text against the user vocabulary
+    pub fn set_corrector(&mut self, corrector: Arc<VocabularyCorrector>) {
+        self.corrector = Some(corrector);
+    }
+
+    /// Archive the audio and transcript of sessions from the next one on;
+    /// `None` stops archiving
+    pub fn set_archive(&mut self, archive: Option<SessionArchive>) {
+        self.archive = archive;
+    }
+
+    /// Recognition language of the next session
+    pub fn language(&self) -> Language {
+        self.asr_client.session_options().language
+    }
+
+    /// Switch the recognition language from the next session on
+    pub fn set_language(&self, language: Language) {
+        self.asr_client.set_language(language);
+    }
+
     /// Check if currently recording
     pub fn is_recording(&self) -> bool {
         self.is_recording.load(Ordering::SeqCst)
@@ -61,6 +142,15 @@ impl VoiceController {
 
     /// Start voice input
     pub async fn start(&mut self) -> Result<()> {
+        self.start_session(None).await
+    }
+
+    /// Start voice input with session options overriding the client's
+    pub async fn start_with_options(&mut self, options: SessionOptions) -> Result<()> {
+        self.start_session(Some(options)).await
+    }
+
+    async fn start_session(&mut self, session_options: Option<SessionOptions>) -> Result<()> {
         if self.is_recording() {
             return Ok(());
         }
@@ -72,24 +162,108 @@ impl VoiceController {
         // Start audio capture
         tracing::debug!("Starting audio capture...");
         let audio_rx = self.audio_capture.start()?;
+        // Always-warm capture starts the stream with audio from before now
+        let preroll = self.audio_capture.preroll_sent();
         tracing::info!("Audio capture started, frames will be sent to ASR");
 
         // Start ASR
         tracing::debug!("Connecting to ASR server...");
-        let mut result_rx = self.asr_client.start_realtime(audio_rx).await?;
+        let session_options = session_options.unwrap_or_else(|| self.asr_client.session_options());
+        let formatter = TextFormatter::new(session_options.language);
+        let (audio_rx, archived) = self.archive_session(audio_rx, session_options.language);
+        let started = self
+            .asr_client
+            .start_realtime_with_preroll(audio_rx, session_options, preroll)
+            .await;
+        let result_rx = match started {
+            Ok(rx) => rx,
+            Err(e) => {
+                if e.is_auth() {
+                    tracing::error!("ASR rejected credentials: {}", e);
+                } else {
+                    tracing::error!("Failed to connect to ASR server: {}", e);
+                }
+                self.audio_capture.stop();
+                self.is_recording.store(false, Ordering::SeqCst);
+                // What was captured is still worth keeping
+                if let Some(archived) = archived {
+                    tokio::spawn(archived.finish(String::new(), Vec::new()));
+                }
+                return Err(e.into());
+            }
+        };
         tracing::info!("ASR connection established");
 
+        self.spawn_result_task(result_rx, formatter, archived);
+        Ok(())
+    }
+
+    /// Tee the session's audio into the archive, when one is set
+    fn archive_session(
+        &self,
+        audio_rx: mpsc::Receiver<Vec<u8>>,
+        language: Language,
+    ) -> (mpsc::Receiver<Vec<u8>>, Option<ArchivedSession>) {
+        let Some(ref archive) = self.archive else {
+            return (audio_rx, None);
+        };
+        match archive.start(focused_app(), language) {
+            Ok(mut archived) => (archived.tee(audio_rx), Some(archived)),
+            Err(e) => {
+                // Archiving is optional; never fail the session over it
+                tracing::warn!("Session archiving disabled: {}", e);
+                (audio_rx, None)
+            }
+        }
+    }
+
+    /// Run recorded ASR responses through the normal result handling
+    ///
+    /// Used to reproduce sessions offline (see `asr::replay_capture`); no
+    /// audio is captured and the session ends when the stream does.
+    pub fn start_replay(&mut self, result_rx: mpsc::Receiver<AsrResponse>) -> Result<()> {
+        if self.is_recording() {
+            return Err(anyhow!("Voice input is already running"));
+        }
+
+        tracing::info!("Replaying recorded ASR session...");
+        self.is_recording.store(true, Ordering::SeqCst);
+        self.stop_signal.store(false, Ordering::SeqCst);
+        self.spawn_result_task(result_rx, TextFormatter::new(self.language()), None);
+        Ok(())
+    }
+
+    /// Apply ASR results to the focused window until the session ends
+    fn spawn_result_task(
+        &self,
+        mut result_rx: mpsc::Receiver<AsrResponse>,
+        formatter: TextFormatter,
+        archived: Option<ArchivedSession>,
+    ) {
         // Clone for the task
         let text_inserter = self.text_inserter.clone();
         let is_recording = self.is_recording.clone();
         let stop_signal = self.stop_signal.clone();
         let audio_capture = self.audio_capture.clone();
         let on_result_cb = self.on_result.clone();
+        let on_error_cb = self.on_error.clone();
+        let on_stats_cb = self.on_stats.clone();
+        let on_auto_stop_cb = self.on_auto_stop.clone();
+        let corrector = self.corrector.clone();
+        let server_vad = audio_capture.vad().is_some_and(|v| v.use_server_vad);
 
         // Spawn result processing task
         tokio::spawn(async move {
+            // Interim text currently on screen; finalized text is never revised
             let mut last_text = String::new();
+            let mut segments = SegmentTracker::default();
             let mut response_count = 0u32;
+            let mut stats = None;
+            let mut stopped = false;
+            let mut auto_stopped = false;
+            // Typed text and its timings, for the archive
+            let mut transcript = String::new();
+            let mut timeline: Vec<TranscriptSegment> = Vec::new();
 
             tracing::info!("ASR result processing task started");
 
@@ -100,52 +274,89 @@ impl VoiceController {
                         "Voice input stopped by user (processed {} responses)",
                         response_count
                     );
+                    stopped = true;
                     break;
                 }
 
+                // Silence ended the recording: stop sending audio and let the
+                // session finalize what was said
+                if !auto_stopped && audio_capture.silence_timed_out() {
+                    tracing::info!("Voice input stopped after silence");
+                    println!("🔇 [静音] 自动停止");
+                    auto_stopped = true;
+                    audio_capture.stop();
+                }
+
                 // Use timeout to periodically check stop signal
                 match tokio::time::timeout(std::time::Duration::from_millis(100), result_rx.recv())
                     .await
                 {
-                    Ok(Some(response)) => {
+                    Ok(Some(mut response)) => {
                         response_count += 1;
                         match response.response_type {
-                            ResponseType::InterimResult => {
-                                tracing::debug!("[INTERIM #{}] {}", response_count, response.text);
-                                println!("📝 [识别中] {}", response.text);
+                            ResponseType::InterimResult | ResponseType::FinalResult => {
+                                let is_final = response.response_type == ResponseType::FinalResult;
+                                if is_final {
+                                    tracing::info!("[FINAL #{}] {}", response_count, response.text);
+                                } else {
+                                    tracing::debug!(
+                                        "[INTERIM #{}] {}",
+                                        response_count,
+                                        response.text
+                                    );
+                                }
 
                                 if let Some(ref cb) = on_result_cb {
-                                    cb(response.text.clone(), false);
+                                    cb(response.text.clone(), is_final);
                                 }
 
-                                if !response.text.is_empty() {
-                                    if let Err(e) =
-                                        update_text(&text_inserter, &last_text, &response.text)
-                                    {
-                                        tracing::error!("Failed to update text: {}", e);
+                                let (mut committed, interim, finalized) = segments.split(&response);
+                                if let Some(ref corrector) = corrector {
+                                    committed = corrector.correct(&committed);
+                                }
+                                let committed = formatter.format(&committed);
+                                let interim = formatter.format(&interim);
+                                if archived.is_some() {
+                                    for segment in finalized {
+                                        let mut text = segment.text.clone();
+                                        if let Some(ref corrector) = corrector {
+                                            text = corrector.correct(&text);
+                                        }
+                                        let previous_end = timeline.last().map_or(0, |s| s.end_ms);
+                                        timeline.push(TranscriptSegment::from_recognized(
+                                            segment,
+                                            formatter.format(&text),
+                                            previous_end,
+                                        ));
                                     }
-                                    last_text = response.text.clone();
+                                    transcript.push_str(&committed);
                                 }
-                            }
-                            ResponseType::FinalResult => {
-                                tracing::info!("[FINAL #{}] {}", response_count, response.text);
-                                println!("✅ [确认] {}", response.text);
-
-                                if let Some(ref cb) = on_result_cb {
-                                    cb(response.text.clone(), true);
+                                if !committed.is_empty() {
+                                    println!("✅ [确认] {}", committed);
+                                }
+                                if !interim.is_empty() {
+                                    println!("📝 [识别中] {}", interim);
                                 }
 
-                                if !response.text.is_empty() {
+                                // 已确认的分段直接上屏，之后只修改末尾的临时分段
+                                let new_text = format!("{}{}", committed, interim);
+                                if new_text != last_text {
                                     if let Err(e) =
-                                        update_text(&text_inserter, &last_text, &response.text)
+                                        update_text(&text_inserter, &last_text, &new_text)
                                     {
                                         tracing::error!("Failed to update text: {}", e);
                                     }
-                                    // 清空 last_text，这样新的语句不会删除已确认的文字
-                                    last_text = String::new();
+                                }
+                                last_text = interim;
+
+                                if server_vad && response.vad_finished && !auto_stopped {
+                                    tracing::info!("Voice input stopped at server VAD end");
+                                    auto_stopped = true;
+                                    audio_capture.stop();
                                 }
                             }
                             ResponseType::SessionFinished => {
+                                stats = response.stats.take();
                                 tracing::info!(
                                     "ASR session finished (total {} responses)",
                                     response_count
@@ -154,8 +365,17 @@ impl VoiceController {
                                 break;
                             }
                             ResponseType::Error => {
-                                tracing::error!("ASR error: {}", response.error_msg);
-                                println!("❌ [错误] {}", response.error_msg);
+                                stats = response.stats.take();
+                                let error = response.error.unwrap_or(AsrError::Rejected {
+                                    status_code: response.status_code,
+                                    message: response.error_msg,
+                                });
+                                tracing::error!("ASR error ({}): {}", error.kind(), error);
+                                println!("❌ [错误] {}", error);
+
+                                if let Some(ref cb) = on_error_cb {
+                                    cb(error);
+                                }
                                 break;
                             }
                             _ => {
@@ -180,10 +400,35 @@ impl VoiceController {
 
             // Cleanup
             audio_capture.stop();
+            let dropped_frames = audio_capture.dropped_frames();
             is_recording.store(false, Ordering::SeqCst);
-        });
 
-        Ok(())
+            // The session goes on until the server has finalized the remaining
+            // audio; wait for its stats without touching the text any more
+            if stopped {
+                stats = wait_for_stats(&mut result_rx).await;
+            }
+            if auto_stopped {
+                if let Some(ref cb) = on_auto_stop_cb {
+                    cb();
+                }
+            }
+            if let Some(mut stats) = stats {
+                stats.frames_dropped = dropped_frames;
+                tracing::info!("Session stats: {}", stats);
+                if let Some(ref cb) = on_stats_cb {
+                    cb(stats);
+                }
+            }
+
+            if let Some(archived) = archived {
+                match archived.finish(transcript, timeline).await {
+                    Ok(Some(sidecar)) => tracing::info!("Session archived to {:?}", sidecar),
+                    Ok(None) => {}
+                    Err(e) => tracing::warn!("Failed to archive session: {}", e),
+                }
+            }
+        });
     }
 
     /// Stop voice input
@@ -207,6 +452,100 @@ impl VoiceController {
     }
 }
 
+/// Drain a stopped session's responses until the one carrying its stats
+async fn wait_for_stats(result_rx: &mut mpsc::Receiver<AsrResponse>) -> Option<SessionStats> {
+    let drain = async {
+        while let Some(response) = result_rx.recv().await {
+            if response.stats.is_some() {
+                return response.stats;
+            }
+        }
+        None
+    };
+    tokio::time::timeout(STATS_TIMEOUT, drain)
+        .await
+        .ok()
+        .flatten()
+}
+
+/// Splits responses into newly finalized text and the trailing interim text
+///
+/// Segments up to the first non-final one are committed; everything after it
+/// is still revisable. Servers that resend finalized segments are
+/// de-duplicated so committed text is never typed twice: by `index` when
+/// every segment carries one, otherwise by position, as long as the resent
+/// segments still read the same.
+#[derive(Default)]
+pub(crate) struct SegmentTracker {
+    last_committed_index: Option<u32>,
+    /// Text of the segments committed from the server's current list, for
+    /// segments without an `index`
+    committed_texts: Vec<String>,
+}
+
+impl SegmentTracker {
+    /// Newly finalized text, the trailing interim text, and the segments
+    /// the finalized text came from
+    pub(crate) fn split<'a>(
+        &mut self,
+        response: &'a AsrResponse,
+    ) -> (String, String, Vec<&'a RecognitionSegment>) {
+        // Responses built without segments (e.g. synthetic ones)
+        if response.segments.is_empty() {
+            return match response.response_type {
+                ResponseType::FinalResult => (response.text.clone(), String::new(), Vec::new()),
+                _ => (String::new(), response.text.clone(), Vec::new()),
+            };
+        }
+
+        let (committed, interim) = self.split_segments(&response.segments);
+        let text = |segments: &[&RecognitionSegment]| -> String {
+            segments.iter().map(|s| s.text.as_str()).collect()
+        };
+        (text(&committed), text(&interim), committed)
+    }
+
+    /// Newly finalized segments, and the revisable ones after them
+    pub(crate) fn split_segments<'a>(
+        &mut self,
+        segments: &'a [RecognitionSegment],
+    ) -> (Vec<&'a RecognitionSegment>, Vec<&'a RecognitionSegment>) {
+        let finals = segments.iter().take_while(|s| s.is_final()).count();
+        let (finalized, revisable) = segments.split_at(finals);
+        let interim = revisable.iter().collect();
+
+        let indexes: Option<Vec<u32>> = finalized.iter().map(|s| s.index).collect();
+        if let Some(indexes) = indexes.filter(|i| !i.is_empty()) {
+            let mut committed = Vec::new();
+            for (segment, index) in finalized.iter().zip(indexes) {
+                if self.last_committed_index.is_some_and(|last| index <= last) {
+                    continue;
+                }
+                self.last_committed_index = Some(index);
+                committed.push(segment);
+            }
+            return (committed, interim);
+        }
+
+        // Without an index, a response that starts with the segments already
+        // committed is a resend of the same list; anything else (an interim
+        // response, a different sentence) starts a new one
+        let resent = self.committed_texts.len() <= finalized.len()
+            && self
+                .committed_texts
+                .iter()
+                .zip(finalized)
+                .all(|(text, segment)| *text == segment.text);
+        if !resent {
+            self.committed_texts.clear();
+        }
+        let committed: Vec<_> = finalized[self.committed_texts.len()..].iter().collect();
+        self.committed_texts
+            .extend(committed.iter().map(|s| s.text.clone()));
+        (committed, interim)
+    }
+}
+
 /// Update text in the focused window using incremental updates
 ///
 /// Uses prefix matching to minimize deletions and insertions:
@@ -6,6 +6,17 @@ use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::asr::{
+    split_no_proxy, AsrResult, DeviceProfile, Endpoints, Hotword, Language, Proxy, ProxySettings,
+    SessionOptions, DEFAULT_PROFILE_NAME,
+};
+
+use crate::audio::{OpusApplication, OpusSettings, VadSettings};
+use crate::business::{ArchiveRetention, SessionArchive};
+
+use super::Vocabulary;
 
 /// Application configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,6 +27,18 @@ pub struct AppConfig {
     pub hotkey: HotkeyConfig,
     #[serde(default)]
     pub asr: AsrConfig,
+    #[serde(default)]
+    pub endpoints: EndpointsConfig,
+    #[serde(default)]
+    pub network: NetworkConfig,
+    #[serde(default)]
+    pub device: DeviceConfig,
+    #[serde(default)]
+    pub vocabulary: VocabularyConfig,
+    #[serde(default)]
+    pub audio: AudioConfig,
+    #[serde(default)]
+    pub archive: ArchiveConfig,
 }
 
 impl Default for AppConfig {
@@ -24,6 +47,12 @@ impl Default for AppConfig {
             general: GeneralConfig::default(),
             hotkey: HotkeyConfig::default(),
             asr: AsrConfig::default(),
+            endpoints: EndpointsConfig::default(),
+            network: NetworkConfig::default(),
+            device: DeviceConfig::default(),
+            vocabulary: VocabularyConfig::default(),
+            audio: AudioConfig::default(),
+            archive: ArchiveConfig::default(),
         }
     }
 }
@@ -51,6 +80,21 @@ impl AppConfig {
         Self::app_data_dir().join("credentials.json")
     }
 
+    /// Get the user vocabulary file path
+    pub fn vocabulary_path() -> PathBuf {
+        Self::app_data_dir().join("vocabulary.txt")
+    }
+
+    /// Get the directory for recorded ASR sessions
+    pub fn captures_dir() -> PathBuf {
+        Self::app_data_dir().join("captures")
+    }
+
+    /// Get the directory for archived session audio and transcripts
+    pub fn archive_dir() -> PathBuf {
+        Self::app_data_dir().join("archive")
+    }
+
     /// Load configuration from file or create default
     pub fn load_or_default() -> Result<Self> {
         let path = Self::config_path();
@@ -66,6 +110,38 @@ impl AppConfig {
         }
     }
 
+    /// Options sent with every StartSession: recognition settings, language
+    /// and the vocabulary as hotwords when enabled
+    pub fn session_options(&self, vocabulary: &Vocabulary) -> SessionOptions {
+        SessionOptions {
+            language: self.general.language(),
+            hotwords: self.vocabulary.hotwords(vocabulary),
+            ..self.asr.session_options()
+        }
+    }
+
+    /// Local VAD settings for `AudioCapture`, `None` unless
+    /// `[asr] vad_enabled`
+    ///
+    /// Auto-stop is opt-in through the `[audio.vad]` timeouts; pauses
+    /// between sentences never end a continuous dictation.
+    pub fn vad_settings(&self) -> Option<VadSettings> {
+        self.asr.vad_enabled.then(|| {
+            let mut settings = self.audio.vad.settings();
+            if self.asr.continuous {
+                settings.silence_timeout = None;
+            }
+            settings
+        })
+    }
+
+    /// The session archive, `None` unless `[archive] enabled`
+    pub fn session_archive(&self) -> Option<SessionArchive> {
+        self.archive
+            .enabled
+            .then(|| SessionArchive::new(Self::archive_dir(), self.archive.retention()))
+    }
+
     /// Save configuration to file
     pub fn save(&self) -> Result<()> {
         let path = Self::config_path();
@@ -80,6 +156,7 @@ impl AppConfig {
 pub struct GeneralConfig {
     #[serde(default)]
     pub auto_start: bool,
+    /// Recognition language: "zh-CN", "en-US" or "mixed"
     #[serde(default = "default_language")]
     pub language: String,
     #[serde(default)]
@@ -87,7 +164,14 @@ pub struct GeneralConfig {
 }
 
 fn default_language() -> String {
-    "zh-CN".to_string()
+    Language::default().code().to_string()
+}
+
+impl GeneralConfig {
+    /// The configured recognition language, Chinese if unrecognized
+    pub fn language(&self) -> Language {
+        Language::from_code(&self.language).unwrap_or_default()
+    }
 }
 
 impl Default for GeneralConfig {
@@ -144,15 +228,509 @@ fn default_true() -> bool {
     true
 }
 
+fn default_input_mode() -> String {
+    SessionOptions::default().input_mode
+}
+
+fn default_app_name() -> String {
+    SessionOptions::default().app_name
+}
+
+fn default_warm_pool_idle_secs() -> u64 {
+    60
+}
+
+fn default_rollover_secs() -> u64 {
+    240
+}
+
 /// ASR configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AsrConfig {
+    /// Local voice activity detection, tuned in `[audio.vad]`
     #[serde(default = "default_true")]
     pub vad_enabled: bool,
+    #[serde(default = "default_true")]
+    pub enable_punctuation: bool,
+    #[serde(default)]
+    pub enable_speech_rejection: bool,
+    #[serde(default = "default_true")]
+    pub enable_asr_twopass: bool,
+    #[serde(default = "default_true")]
+    pub enable_asr_threepass: bool,
+    #[serde(default = "default_input_mode")]
+    pub input_mode: String,
+    #[serde(default = "default_app_name")]
+    pub app_name: String,
+    /// Write every session's wire frames to `captures_dir()` (debugging aid)
+    #[serde(default)]
+    pub record_sessions: bool,
+    /// Keep a handshaken session ready so recording starts without delay
+    #[serde(default)]
+    pub warm_pool: bool,
+    /// Seconds an unused warm session is kept before it is replaced
+    #[serde(default = "default_warm_pool_idle_secs")]
+    pub warm_pool_idle_secs: u64,
+    /// Long-form dictation: spread a recording over several server sessions
+    #[serde(default)]
+    pub continuous: bool,
+    /// Server session age (seconds) after which continuous mode rolls over
+    /// at the next sentence boundary
+    #[serde(default = "default_rollover_secs")]
+    pub rollover_secs: u64,
 }
 
 impl Default for AsrConfig {
     fn default() -> Self {
-        Self { vad_enabled: true }
+        Self {
+            vad_enabled: true,
+            enable_punctuation: true,
+            enable_speech_rejection: false,
+            enable_asr_twopass: true,
+            enable_asr_threepass: true,
+            input_mode: default_input_mode(),
+            app_name: default_app_name(),
+            record_sessions: false,
+            warm_pool: false,
+            warm_pool_idle_secs: default_warm_pool_idle_secs(),
+            continuous: false,
+            rollover_secs: default_rollover_secs(),
+        }
+    }
+}
+
+impl AsrConfig {
+    /// Options sent with every StartSession
+    pub fn session_options(&self) -> SessionOptions {
+        SessionOptions {
+            enable_punctuation: self.enable_punctuation,
+            enable_speech_rejection: self.enable_speech_rejection,
+            enable_asr_twopass: self.enable_asr_twopass,
+            enable_asr_threepass: self.enable_asr_threepass,
+            input_mode: self.input_mode.clone(),
+            app_name: self.app_name.clone(),
+            hotwords: Vec::new(),
+            language: Language::default(),
+        }
+    }
+
+    /// Idle timeout for `AsrClient::set_warm_pool`, `None` when disabled
+    pub fn warm_pool_timeout(&self) -> Option<Duration> {
+        self.warm_pool
+            .then(|| Duration::from_secs(self.warm_pool_idle_secs.max(1)))
+    }
+
+    /// Session age for `AsrClient::set_rollover`, `None` when not continuous
+    pub fn rollover_after(&self) -> Option<Duration> {
+        self.continuous
+            .then(|| Duration::from_secs(self.rollover_secs.max(1)))
+    }
+}
+
+/// How the user vocabulary is applied
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VocabularyConfig {
+    /// Send the terms as StartSession hotwords
+    #[serde(default)]
+    pub send_hotwords: bool,
+    /// Pinyin-based fuzzy correction of final text
+    #[serde(default = "default_true")]
+    pub correction: bool,
+}
+
+impl Default for VocabularyConfig {
+    fn default() -> Self {
+        Self {
+            send_hotwords: false,
+            correction: true,
+        }
+    }
+}
+
+impl VocabularyConfig {
+    /// Hotwords for `SessionOptions`, empty unless `send_hotwords` is set
+    pub fn hotwords(&self, vocabulary: &Vocabulary) -> Vec<Hotword> {
+        if self.send_hotwords {
+            vocabulary.hotwords()
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// Audio input settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioConfig {
+    /// Input device name; unset (or no longer connected) uses the system
+    /// default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    /// Keep the microphone open between recordings, buffering the last
+    /// `preroll_ms` so speech started with the hotkey is not clipped
+    #[serde(default)]
+    pub always_warm: bool,
+    #[serde(default = "default_preroll_ms")]
+    pub preroll_ms: u64,
+    #[serde(default)]
+    pub vad: VadConfig,
+    #[serde(default)]
+    pub opus: OpusConfig,
+}
+
+fn default_preroll_ms() -> u64 {
+    400
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            device: None,
+            always_warm: false,
+            preroll_ms: default_preroll_ms(),
+            vad: VadConfig::default(),
+            opus: OpusConfig::default(),
+        }
+    }
+}
+
+impl AudioConfig {
+    /// Pre-roll for `AudioCapture::set_preroll`, `None` unless always warm
+    pub fn preroll(&self) -> Option<Duration> {
+        self.always_warm
+            .then(|| Duration::from_millis(self.preroll_ms.clamp(100, 2000)))
+    }
+}
+
+fn default_speech_threshold_db() -> f32 {
+    VadSettings::default().speech_threshold_db
+}
+
+fn default_min_speech_ms() -> u64 {
+    VadSettings::default().min_speech.as_millis() as u64
+}
+
+fn default_hangover_ms() -> u64 {
+    VadSettings::default().hangover.as_millis() as u64
+}
+
+fn default_silence_timeout_ms() -> u64 {
+    0
+}
+
+fn default_no_speech_timeout_ms() -> u64 {
+    0
+}
+
+fn default_leading_padding_ms() -> u64 {
+    VadSettings::default().leading_padding.as_millis() as u64
+}
+
+/// Local voice activity detection (enabled by `[asr] vad_enabled`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VadConfig {
+    /// Frame level (dBFS) at or above which audio counts as speech
+    #[serde(default = "default_speech_threshold_db")]
+    pub speech_threshold_db: f32,
+    /// Speech must last this long to be detected (ignores clicks)
+    #[serde(default = "default_min_speech_ms")]
+    pub min_speech_ms: u64,
+    /// Speech ends after this much quiet (bridges pauses between words)
+    #[serde(default = "default_hangover_ms")]
+    pub hangover_ms: u64,
+    /// Stop after this much silence following speech; 0 (the default)
+    /// never stops, nor does continuous dictation
+    #[serde(default = "default_silence_timeout_ms")]
+    pub silence_timeout_ms: u64,
+    /// Stop if nothing is said this long after starting; 0 (the default)
+    /// never stops
+    #[serde(default = "default_no_speech_timeout_ms")]
+    pub no_speech_timeout_ms: u64,
+    /// Send no audio until speech starts
+    #[serde(default)]
+    pub hold_leading_silence: bool,
+    /// Audio from before the start of speech still sent when holding back
+    #[serde(default = "default_leading_padding_ms")]
+    pub leading_padding_ms: u64,
+    /// Also stop when the server reports the end of an utterance
+    #[serde(default)]
+    pub use_server_vad: bool,
+}
+
+impl Default for VadConfig {
+    fn default() -> Self {
+        Self {
+            speech_threshold_db: default_speech_threshold_db(),
+            min_speech_ms: default_min_speech_ms(),
+            hangover_ms: default_hangover_ms(),
+            silence_timeout_ms: default_silence_timeout_ms(),
+            no_speech_timeout_ms: default_no_speech_timeout_ms(),
+            hold_leading_silence: false,
+            leading_padding_ms: default_leading_padding_ms(),
+            use_server_vad: false,
+        }
+    }
+}
+
+impl VadConfig {
+    pub fn settings(&self) -> VadSettings {
+        let timeout = |ms: u64| (ms > 0).then(|| Duration::from_millis(ms));
+        VadSettings {
+            speech_threshold_db: self.speech_threshold_db,
+            min_speech: Duration::from_millis(self.min_speech_ms),
+            hangover: Duration::from_millis(self.hangover_ms),
+            silence_timeout: timeout(self.silence_timeout_ms),
+            no_speech_timeout: timeout(self.no_speech_timeout_ms),
+            hold_leading_silence: self.hold_leading_silence,
+            leading_padding: Duration::from_millis(self.leading_padding_ms),
+            use_server_vad: self.use_server_vad,
+        }
+    }
+}
+
+fn default_opus_application() -> String {
+    OpusApplication::default().name().to_string()
+}
+
+/// Opus encoder tuning; lower bitrates save data on metered connections
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpusConfig {
+    /// "audio" or "voip" (better speech at low bitrates)
+    #[serde(default = "default_opus_application")]
+    pub application: String,
+    /// Target bitrate in kbps; 0 lets the encoder choose
+    #[serde(default)]
+    pub bitrate_kbps: u32,
+    /// Variable bitrate; false encodes at a constant bitrate
+    #[serde(default = "default_true")]
+    pub vbr: bool,
+    /// 0 (least CPU) to 10 (best quality); unset keeps the encoder default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub complexity: Option<u8>,
+    /// In-band forward error correction against lost packets
+    #[serde(default)]
+    pub fec: bool,
+    /// Expected packet loss (0-100) that FEC protects against
+    #[serde(default)]
+    pub packet_loss_percent: u8,
+    /// Send near-empty packets while silent
+    #[serde(default)]
+    pub dtx: bool,
+}
+
+impl Default for OpusConfig {
+    fn default() -> Self {
+        Self {
+            application: default_opus_application(),
+            bitrate_kbps: 0,
+            vbr: true,
+            complexity: None,
+            fec: false,
+            packet_loss_percent: 0,
+            dtx: false,
+        }
+    }
+}
+
+impl OpusConfig {
+    /// Settings for `OpusEncoder`; an unknown application falls back to
+    /// "audio"
+    pub fn settings(&self) -> OpusSettings {
+        OpusSettings {
+            application: OpusApplication::from_name(&self.application).unwrap_or_default(),
+            bitrate: (self.bitrate_kbps > 0).then(|| self.bitrate_kbps * 1000),
+            vbr: self.vbr,
+            complexity: self.complexity.map(|c| c.min(10)),
+            fec: self.fec,
+            packet_loss_percent: self.packet_loss_percent.min(100),
+            dtx: self.dtx,
+        }
+    }
+}
+
+fn default_archive_max_age_days() -> u64 {
+    30
+}
+
+fn default_archive_max_total_mb() -> u64 {
+    500
+}
+
+/// Opt-in archive of each session's audio and transcript in
+/// `archive_dir()`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Delete sessions older than this; 0 keeps them
+    #[serde(default = "default_archive_max_age_days")]
+    pub max_age_days: u64,
+    /// Delete the oldest sessions while the archive is larger; 0 never limits it
+    #[serde(default = "default_archive_max_total_mb")]
+    pub max_total_mb: u64,
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_age_days: default_archive_max_age_days(),
+            max_total_mb: default_archive_max_total_mb(),
+        }
+    }
+}
+
+impl ArchiveConfig {
+    pub fn retention(&self) -> ArchiveRetention {
+        ArchiveRetention {
+            max_age: (self.max_age_days > 0)
+                .then(|| Duration::from_secs(self.max_age_days * 24 * 60 * 60)),
+            max_total_bytes: (self.max_total_mb > 0).then(|| self.max_total_mb * 1024 * 1024),
+        }
+    }
+}
+
+/// Endpoint overrides (unset fields fall back to the built-in defaults)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EndpointsConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub register_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settings_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub websocket_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub websocket_host: Option<String>,
+}
+
+impl EndpointsConfig {
+    /// Resolve the endpoint set: defaults, then config values, then
+    /// `DOUBAO_*` environment variables
+    pub fn resolve(&self) -> Endpoints {
+        let mut endpoints = Endpoints::default();
+        if let Some(ref url) = self.register_url {
+            endpoints.register_url = url.clone();
+        }
+        if let Some(ref url) = self.settings_url {
+            endpoints.settings_url = url.clone();
+        }
+        if let Some(ref url) = self.websocket_url {
+            endpoints.websocket_url = url.clone();
+        }
+        if let Some(ref host) = self.websocket_host {
+            endpoints.websocket_host = Some(host.clone());
+        }
+        endpoints.apply_env();
+        endpoints
+    }
+}
+
+/// Outbound proxy settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkConfig {
+    /// `http://host:port`, `socks5://host:port` or `socks5h://host:port`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_password: Option<String>,
+    /// Hosts reached directly, in addition to `NO_PROXY`
+    #[serde(default)]
+    pub no_proxy: Vec<String>,
+    /// Fall back to `HTTPS_PROXY` / `ALL_PROXY` / `HTTP_PROXY` when `proxy`
+    /// is not set
+    #[serde(default = "default_true")]
+    pub use_env_proxy: bool,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            proxy_username: None,
+            proxy_password: None,
+            no_proxy: Vec::new(),
+            use_env_proxy: true,
+        }
+    }
+}
+
+impl NetworkConfig {
+    /// Resolve the proxy: config values first, then the environment
+    pub fn resolve(&self) -> AsrResult<ProxySettings> {
+        let mut settings = if self.use_env_proxy {
+            ProxySettings::from_env()?
+        } else {
+            ProxySettings::default()
+        };
+
+        if let Some(url) = self.proxy.as_deref().filter(|u| !u.trim().is_empty()) {
+            settings.proxy = Some(Proxy::parse(url)?);
+        }
+        if let Some(ref username) = self.proxy_username {
+            let password = self.proxy_password.clone().unwrap_or_default();
+            settings.proxy = settings
+                .proxy
+                .map(|proxy| proxy.with_auth(username.clone(), password));
+        }
+        settings
+            .no_proxy
+            .extend(self.no_proxy.iter().flat_map(|h| split_no_proxy(h)));
+
+        if let Some(ref proxy) = settings.proxy {
+            tracing::info!("Using proxy {}", proxy.display_url());
+        }
+        Ok(settings)
+    }
+}
+
+fn default_profile_name() -> String {
+    DEFAULT_PROFILE_NAME.to_string()
+}
+
+/// Device identity used for registration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceConfig {
+    /// Name of the profile used when registering and requesting tokens
+    #[serde(default = "default_profile_name")]
+    pub profile: String,
+    /// Profiles in addition to (or, with the same name, replacing) the
+    /// built-in one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<DeviceProfile>,
+}
+
+impl Default for DeviceConfig {
+    fn default() -> Self {
+        Self {
+            profile: default_profile_name(),
+            profiles: Vec::new(),
+        }
+    }
+}
+
+impl DeviceConfig {
+    /// Every selectable profile, the built-in one first
+    pub fn available_profiles(&self) -> Vec<DeviceProfile> {
+        let mut profiles = Vec::with_capacity(self.profiles.len() + 1);
+        if !self.profiles.iter().any(|p| p.name == DEFAULT_PROFILE_NAME) {
+            profiles.push(DeviceProfile::default());
+        }
+        profiles.extend(self.profiles.iter().cloned());
+        profiles
+    }
+
+    /// The selected profile, or the built-in one if it is not defined
+    pub fn selected_profile(&self) -> DeviceProfile {
+        self.available_profiles()
+            .into_iter()
+            .find(|p| p.name == self.profile)
+            .unwrap_or_else(|| {
+                tracing::warn!(
+                    "Device profile '{}' not found, using the built-in profile",
+                    self.profile
+                );
+                DeviceProfile::default()
+            })
     }
 }
@@ -1,22 +1,54 @@
 //! Credential Store
 //!
 //! Manages device credentials with optional encryption.
+//!
+//! Tokens can be revoked or expire server-side. When that happens the store
+//! first asks the settings API for a fresh token and, if the device itself
+//! is no longer accepted, registers a new one; the result is written back to
+//! `credentials.json`.
+//!
+//! Registration and token requests go through the configured proxy, if any.
+//!
+//! New devices are registered with the selected `DeviceProfile`. When a
+//! different profile is selected later, the stored credentials switch to it
+//! and fetch a token under the new identity.
 
 use anyhow::Result;
+use serde::Serialize;
 use std::path::PathBuf;
+use std::sync::RwLock;
+use tokio::sync::Mutex;
 
-use crate::asr::{get_asr_token, register_device, DeviceCredentials};
+use crate::asr::{
+    get_asr_token, register_device, AsrError, DeviceCredentials, DeviceProfile, Endpoints,
+    ProxySettings,
+};
 use crate::data::AppConfig;
 
+/// Outcome of validating the stored credentials
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialStatus {
+    /// The cached token is still the one the server hands out
+    Valid,
+    /// The server issued a new token for the same device
+    Refreshed,
+    /// The device was registered again
+    Reregistered,
+}
+
 /// Credential store for managing device credentials
 pub struct CredentialStore {
     credentials_path: PathBuf,
-    credentials: Option<DeviceCredentials>,
+    credentials: Mutex<Option<DeviceCredentials>>,
+    endpoints: Endpoints,
+    proxy: ProxySettings,
+    profile: RwLock<DeviceProfile>,
 }
 
 impl CredentialStore {
     /// Create a new credential store
-    pub fn new(_config: &AppConfig) -> Result<Self> {
+    pub fn new(config: &AppConfig) -> Result<Self> {
         let credentials_path = AppConfig::credentials_path();
 
         // Try to load existing credentials
@@ -28,29 +60,205 @@ impl CredentialStore {
 
         Ok(Self {
             credentials_path,
-            credentials,
+            credentials: Mutex::new(credentials),
+            endpoints: config.endpoints.resolve(),
+            proxy: config.network.resolve()?,
+            profile: RwLock::new(config.device.selected_profile()),
         })
     }
 
+    /// Create a store backed by an explicit credentials file
+    pub fn with_path(credentials_path: PathBuf, credentials: Option<DeviceCredentials>) -> Self {
+        Self {
+            credentials_path,
+            credentials: Mutex::new(credentials),
+            endpoints: Endpoints::default(),
+            proxy: ProxySettings::default(),
+            profile: RwLock::new(DeviceProfile::default()),
+        }
+    }
+
+    /// Override the endpoints used for registration and token requests
+    pub fn with_endpoints(mut self, endpoints: Endpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    /// Send registration and token requests through `proxy`
+    pub fn with_proxy(mut self, proxy: ProxySettings) -> Self {
+        self.proxy = proxy;
+        self
+    }
+
+    /// Register (or switch existing credentials) with `profile`
+    pub fn with_profile(self, profile: DeviceProfile) -> Self {
+        self.set_profile(profile);
+        self
+    }
+
+    /// Select the device profile; returns true if it changed
+    ///
+    /// Takes effect on the next `ensure_credentials` / `validate_credentials`.
+    pub fn set_profile(&self, profile: DeviceProfile) -> bool {
+        match self.profile.write() {
+            Ok(mut current) if *current != profile => {
+                *current = profile;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The selected device profile
+    pub fn profile(&self) -> DeviceProfile {
+        match self.profile.read() {
+            Ok(profile) => profile.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
     /// Ensure we have valid credentials
     pub async fn ensure_credentials(&self) -> Result<DeviceCredentials> {
+        let mut cached = self.credentials.lock().await;
+
         // Check if we have existing complete credentials
-        if let Some(ref creds) = self.credentials {
+        if let Some(ref creds) = *cached {
             if creds.is_complete() {
-                tracing::info!("Using cached credentials");
+                let mut creds = creds.clone();
+                if !self.apply_profile(&mut creds) {
+                    tracing::info!("Using cached credentials");
+                    return Ok(creds);
+                }
+
+                // The token belongs to the identity it was requested with
+                let (creds, _) = self.renew(&creds, false).await?;
+                creds.save(&self.credentials_path)?;
+                *cached = Some(creds.clone());
+                return Ok(creds);
+            }
+        }
+
+        let creds = self.register_new().await?;
+        *cached = Some(creds.clone());
+        Ok(creds)
+    }
+
+    /// Credentials currently held by the store
+    pub async fn current(&self) -> Option<DeviceCredentials> {
+        self.credentials.lock().await.clone()
+    }
+
+    /// Get new credentials after the server rejected `rejected`
+    ///
+    /// Requests a new token for the same device, and re-registers the device
+    /// if that fails or the server hands back the token it just rejected.
+    /// Concurrent callers that hit the same rejection share one refresh.
+    pub async fn refresh_credentials(
+        &self,
+        rejected: &DeviceCredentials,
+    ) -> Result<DeviceCredentials> {
+        let mut cached = self.credentials.lock().await;
+
+        // Someone else already replaced the rejected credentials
+        if let Some(ref creds) = *cached {
+            if creds.is_complete()
+                && (creds.token != rejected.token || creds.device_id != rejected.device_id)
+            {
+                tracing::info!("Credentials were already refreshed");
                 return Ok(creds.clone());
             }
         }
 
-        // Need to register device
+        let (creds, status) = self.renew(rejected, true).await?;
+        tracing::info!("Credentials renewed after auth failure: {:?}", status);
+        *cached = Some(creds.clone());
+        Ok(creds)
+    }
+
+    /// Check the stored credentials against the server, renewing them if needed
+    ///
+    /// Meant to be run once at startup so an expired token is replaced before
+    /// the first recording rather than during it.
+    pub async fn validate_credentials(&self) -> Result<CredentialStatus> {
+        let mut cached = self.credentials.lock().await;
+
+        let mut current = match *cached {
+            Some(ref creds) if creds.is_complete() => creds.clone(),
+            _ => {
+                let creds = self.register_new().await?;
+                *cached = Some(creds);
+                return Ok(CredentialStatus::Reregistered);
+            }
+        };
+        let switched = self.apply_profile(&mut current);
+
+        let (creds, status) = self.renew(&current, false).await?;
+        if status != CredentialStatus::Valid {
+            tracing::info!("Credentials validated: {:?}", status);
+        } else if switched {
+            creds.save(&self.credentials_path)?;
+        }
+        *cached = Some(creds);
+        Ok(status)
+    }
+
+    /// Fetch a fresh token for `current`, falling back to re-registration
+    ///
+    /// With `reject_unchanged`, getting the same token back counts as a failure.
+    async fn renew(
+        &self,
+        current: &DeviceCredentials,
+        reject_unchanged: bool,
+    ) -> Result<(DeviceCredentials, CredentialStatus)> {
+        if !current.device_id.is_empty() {
+            let mut creds = current.clone();
+            match get_asr_token(&mut creds, &self.endpoints, &self.proxy).await {
+                Ok(()) if creds.token != current.token => {
+                    creds.save(&self.credentials_path)?;
+                    tracing::info!("ASR token refreshed");
+                    return Ok((creds, CredentialStatus::Refreshed));
+                }
+                Ok(()) if !reject_unchanged => return Ok((creds, CredentialStatus::Valid)),
+                Ok(()) => {
+                    tracing::warn!("Server returned the rejected token again, re-registering");
+                }
+                // A dead network or proxy won't be fixed by registering a new device
+                Err(e) if e.is_retryable() || matches!(e, AsrError::Proxy(_)) => {
+                    return Err(e.into())
+                }
+                Err(e) => tracing::warn!("Token refresh failed ({}), re-registering", e),
+            }
+        }
+
+        let creds = self.register_new().await?;
+        Ok((creds, CredentialStatus::Reregistered))
+    }
+
+    /// Move `creds` to the selected profile; returns true if it changed
+    fn apply_profile(&self, creds: &mut DeviceCredentials) -> bool {
+        let profile = self.profile();
+        if creds.profile == profile {
+            return false;
+        }
+        tracing::info!(
+            "Switching device profile '{}' -> '{}'",
+            creds.profile.name,
+            profile.name
+        );
+        creds.profile = profile;
+        true
+    }
+
+    /// Register a new device, fetch its token and persist both
+    async fn register_new(&self) -> Result<DeviceCredentials> {
         tracing::info!("Registering new device...");
-        let mut creds = DeviceCredentials::new_generated();
+        let mut creds = DeviceCredentials::new_generated().with_profile(self.profile());
 
         // Register device to get device_id
-        register_device(&mut creds).await?;
+        register_device(&mut creds, &self.endpoints, &self.proxy).await?;
 
         // Get ASR token
-        get_asr_token(&mut creds).await?;
+        get_asr_token(&mut creds, &self.endpoints, &self.proxy).await?;
 
         // Save credentials
         creds.save(&self.credentials_path)?;
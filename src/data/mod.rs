@@ -2,6 +2,11 @@
 
 mod config;
 mod credential;
+mod vocabulary;
 
-pub use config::{AppConfig, AsrConfig, GeneralConfig, HotkeyConfig};
-pub use credential::CredentialStore;
+pub use config::{
+    AppConfig, ArchiveConfig, AsrConfig, AudioConfig, DeviceConfig, EndpointsConfig, GeneralConfig,
+    HotkeyConfig, NetworkConfig, OpusConfig, VadConfig, VocabularyConfig,
+};
+pub use credential::{CredentialStatus, CredentialStore};
+pub use vocabulary::{Vocabulary, VocabularyTerm};
@@ -0,0 +1,139 @@
+//! User Vocabulary
+//!
+//! Product names, people's names and other terms the recognizer tends to
+//! get wrong, kept in `vocabulary.txt` in the app data dir. One term per
+//! line, optionally followed by `|` and a weight; `#` starts a comment:
+//!
+//! ```text
+//! # 产品
+//! 豆包输入法|5
+//! 张伟
+//! ```
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use super::AppConfig;
+use crate::asr::Hotword;
+
+/// One vocabulary entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VocabularyTerm {
+    pub term: String,
+    /// Relative priority; higher wins when terms compete for the same text
+    #[serde(default)]
+    pub weight: Option<f32>,
+}
+
+impl VocabularyTerm {
+    /// Weight used for ranking, 1.0 when unset
+    pub fn effective_weight(&self) -> f32 {
+        self.weight.unwrap_or(1.0)
+    }
+}
+
+/// The user's vocabulary file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Vocabulary {
+    terms: Vec<VocabularyTerm>,
+}
+
+impl Vocabulary {
+    /// Parse the vocabulary file format; malformed weights are ignored
+    pub fn parse(content: &str) -> Self {
+        let mut vocabulary = Self::default();
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (term, weight) = match line.rsplit_once('|') {
+                Some((term, weight)) => (term.trim(), weight.trim().parse().ok()),
+                None => (line, None),
+            };
+            vocabulary.insert(term, weight);
+        }
+        vocabulary
+    }
+
+    /// Load the vocabulary from `AppConfig::vocabulary_path()`, empty if
+    /// the file does not exist yet
+    pub fn load_or_default() -> Result<Self> {
+        Self::load_from(&AppConfig::vocabulary_path())
+    }
+
+    /// Load a vocabulary file, empty if it does not exist
+    pub fn load_from(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    /// Save the vocabulary to `AppConfig::vocabulary_path()`
+    pub fn save(&self) -> Result<()> {
+        self.save_to(&AppConfig::vocabulary_path())
+    }
+
+    /// Save the vocabulary to a file
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_text())?;
+        Ok(())
+    }
+
+    /// Render in the file format
+    pub fn to_text(&self) -> String {
+        self.terms
+            .iter()
+            .map(|t| match t.weight {
+                Some(weight) => format!("{}|{}\n", t.term, weight),
+                None => format!("{}\n", t.term),
+            })
+            .collect()
+    }
+
+    pub fn terms(&self) -> &[VocabularyTerm] {
+        &self.terms
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Add a term, or update its weight if it is already present.
+    /// Returns false for blank terms.
+    pub fn insert(&mut self, term: &str, weight: Option<f32>) -> bool {
+        let term = term.trim();
+        if term.is_empty() {
+            return false;
+        }
+        match self.terms.iter_mut().find(|t| t.term == term) {
+            Some(existing) => existing.weight = weight,
+            None => self.terms.push(VocabularyTerm {
+                term: term.to_string(),
+                weight,
+            }),
+        }
+        true
+    }
+
+    /// Remove a term; returns whether it was present
+    pub fn remove(&mut self, term: &str) -> bool {
+        let len = self.terms.len();
+        self.terms.retain(|t| t.term != term.trim());
+        self.terms.len() != len
+    }
+
+    /// The terms as StartSession hotwords
+    pub fn hotwords(&self) -> Vec<Hotword> {
+        self.terms
+            .iter()
+            .map(|t| Hotword {
+                word: t.term.clone(),
+                weight: t.weight,
+            })
+            .collect()
+    }
+}
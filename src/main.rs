@@ -3,15 +3,51 @@
 //! Supports two modes:
 //! - CLI mode: For quick testing (run with --cli flag)
 //! - UI mode: Full application with system tray and hotkeys (default)
+//!
+//! `--endpoint KEY=URL` (repeatable) overrides the register, settings or
+//! websocket endpoint, e.g. `--endpoint ws=ws://127.0.0.1:9000/ocean/api/v1/ws`.
+//!
+//! `--source SPEC` (CLI mode) records from something other than the
+//! microphone: `file:PATH`, `stdin[:RATE[:CHANNELS]]`, `tone[:FREQ[:SECS]]`,
+//! `silence[:SECS]` or `mic:NAME`; see `audio::source_from_spec`.
+//!
+//! `transcribe PATH [--output PATH] [--format txt|jsonl|srt|vtt] [--speed N]`
+//! transcribes a WAV, Ogg Opus or MP3 file faster than real time (at most N
+//! times with `--speed`) and prints the transcript or writes it to `--output`;
+//! the format defaults to the output's extension, else plain text. Given a
+//! directory, every audio file in it gets a transcript next to it (or in the
+//! `--output` directory); files that already have one are skipped.
+//!
+//! `opus-bench [FILE]` encodes a file (or a generated speech-like signal)
+//! with the `[audio.opus]` settings and a set of presets, and compares the
+//! data each uses and the CPU it takes.
+//!
+//! `--record` writes each session's protocol frames to the captures directory;
+//! `--replay FILE [--paced]` plays a capture back offline and prints the text
+//! it would have typed.
 
 use anyhow::Result;
 use std::env;
 use std::io::{self, Write};
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tracing::{error, info, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+use doubao_voice_input::asr::{
+    load_capture, replay_capture, DeviceCredentials, Direction, Endpoints, LatencyStat,
+    ProxySettings, SessionStats,
+};
+use doubao_voice_input::audio::{
+    bench_opus, list_input_devices, opus_bench_presets, read_fixture, source_from_spec,
+    speech_like_fixture,
+};
+use doubao_voice_input::business::{
+    audio_files_in, FileTranscriber, SessionArchive, TextBuffer, TextFormatter, TranscriptFormat,
+    VocabularyCorrector,
+};
+use doubao_voice_input::data::{CredentialStatus, Vocabulary};
 use doubao_voice_input::{
     AppConfig, AsrClient, AudioCapture, CredentialStore, HotkeyManager, TextInserter,
     VoiceController,
@@ -22,16 +58,114 @@ async fn main() -> Result<()> {
     // Check for CLI mode
     let args: Vec<String> = env::args().collect();
     let cli_mode = args.iter().any(|a| a == "--cli" || a == "-c");
+    let record = args.iter().any(|a| a == "--record");
+    let endpoint_overrides = parse_endpoint_args(&args);
+
+    if args.get(1).is_some_and(|a| a == "transcribe") {
+        return run_transcribe_mode(&args, &endpoint_overrides, record).await;
+    }
+
+    if args.get(1).is_some_and(|a| a == "opus-bench") {
+        return run_opus_bench_mode(&args);
+    }
+
+    if let Some(path) = parse_flag_value(&args, "--replay") {
+        let paced = args.iter().any(|a| a == "--paced");
+        return run_replay_mode(&path, paced).await;
+    }
 
     if cli_mode {
-        run_cli_mode().await
+        let source = parse_flag_value(&args, "--source");
+        run_cli_mode(&endpoint_overrides, record, source.as_deref()).await
+    } else {
+        run_ui_mode(&endpoint_overrides, record).await
+    }
+}
+
+/// Value of `--flag VALUE` / `--flag=VALUE`, e.g. the capture file given
+/// with `--replay`
+fn parse_flag_value(args: &[String], flag: &str) -> Option<String> {
+    let prefix = format!("{}=", flag);
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == flag {
+            return iter.next().cloned();
+        } else if let Some(value) = arg.strip_prefix(&prefix) {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+/// Collect `--endpoint KEY=URL` / `--endpoint=KEY=URL` arguments
+fn parse_endpoint_args(args: &[String]) -> Vec<String> {
+    let mut overrides = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--endpoint" {
+            if let Some(spec) = iter.next() {
+                overrides.push(spec.clone());
+            }
+        } else if let Some(spec) = arg.strip_prefix("--endpoint=") {
+            overrides.push(spec.to_string());
+        }
+    }
+    overrides
+}
+
+/// Resolve endpoints: defaults < config file < environment < CLI flags
+fn resolve_endpoints(config: &AppConfig, overrides: &[String]) -> Result<Endpoints> {
+    let mut endpoints = config.endpoints.resolve();
+    for spec in overrides {
+        endpoints.apply_override(spec)?;
+    }
+    if endpoints != Endpoints::default() {
+        info!("Using custom endpoints: {:?}", endpoints);
+    }
+    Ok(endpoints)
+}
+
+/// Build the ASR client, recording sessions if asked to
+fn build_asr_client(
+    config: &AppConfig,
+    credentials: DeviceCredentials,
+    endpoints: Endpoints,
+    proxy: ProxySettings,
+    credential_store: Arc<CredentialStore>,
+    vocabulary: &Vocabulary,
+    record: bool,
+) -> AsrClient {
+    let client = AsrClient::new(credentials)
+        .with_endpoints(endpoints)
+        .with_proxy(proxy)
+        .with_session_options(config.session_options(vocabulary))
+        .with_credential_store(credential_store);
+    client.set_warm_pool(config.asr.warm_pool_timeout());
+    client.set_rollover(config.asr.rollover_after());
+
+    if record || config.asr.record_sessions {
+        client.with_recording_dir(AppConfig::captures_dir())
     } else {
-        run_ui_mode().await
+        client
     }
 }
 
+/// Load the user vocabulary, falling back to an empty one
+fn load_vocabulary() -> Vocabulary {
+    Vocabulary::load_or_default().unwrap_or_else(|e| {
+        warn!("Failed to load vocabulary: {}", e);
+        Vocabulary::default()
+    })
+}
+
+fn build_corrector(config: &AppConfig, vocabulary: &Vocabulary) -> Arc<VocabularyCorrector> {
+    let corrector = VocabularyCorrector::new(vocabulary);
+    corrector.set_enabled(config.vocabulary.correction);
+    Arc::new(corrector)
+}
+
 /// Run in full UI mode with system tray and hotkeys
-async fn run_ui_mode() -> Result<()> {
+async fn run_ui_mode(endpoint_overrides: &[String], record: bool) -> Result<()> {
     init_logging(false);
 
     info!(
@@ -41,10 +175,12 @@ async fn run_ui_mode() -> Result<()> {
 
     // Load configuration
     let config = AppConfig::load_or_default()?;
+    let endpoints = resolve_endpoints(&config, endpoint_overrides)?;
     info!("Configuration loaded");
 
     // Initialize credentials
-    let credential_store = CredentialStore::new(&config)?;
+    let credential_store =
+        Arc::new(CredentialStore::new(&config)?.with_endpoints(endpoints.clone()));
     let credentials = credential_store.ensure_credentials().await?;
     info!(
         "Device registered: {}",
@@ -52,15 +188,29 @@ async fn run_ui_mode() -> Result<()> {
     );
 
     // Initialize components
-    let audio_capture = Arc::new(AudioCapture::new()?);
+    let audio_capture = Arc::new(
+        AudioCapture::new()?
+            .with_input_device(config.audio.device.clone())
+            .with_vad(config.vad_settings())
+            .with_opus(config.audio.opus.settings())
+            .with_preroll(config.audio.preroll()),
+    );
     let text_inserter = Arc::new(TextInserter::new());
-    let asr_client = Arc::new(AsrClient::new(credentials));
-
-    let voice_controller = Arc::new(Mutex::new(VoiceController::new(
-        asr_client,
-        audio_capture,
-        text_inserter,
-    )));
+    let vocabulary = load_vocabulary();
+    let asr_client = Arc::new(build_asr_client(
+        &config,
+        credentials,
+        endpoints,
+        config.network.resolve()?,
+        credential_store,
+        &vocabulary,
+        record,
+    ));
+
+    let mut controller = VoiceController::new(asr_client, audio_capture, text_inserter);
+    controller.set_corrector(build_corrector(&config, &vocabulary));
+    controller.set_archive(build_archive(&config));
+    let voice_controller = Arc::new(Mutex::new(controller));
 
     // Initialize hotkey manager
     let hotkey_manager = HotkeyManager::new(&config.hotkey)?;
@@ -74,8 +224,21 @@ async fn run_ui_mode() -> Result<()> {
     Ok(())
 }
 
+/// The session archive if enabled, pruned to its retention limits
+fn build_archive(config: &AppConfig) -> Option<SessionArchive> {
+    let archive = config.session_archive()?;
+    if let Err(e) = archive.prune() {
+        warn!("Failed to prune the session archive: {}", e);
+    }
+    Some(archive)
+}
+
 /// Run in CLI mode for testing
-async fn run_cli_mode() -> Result<()> {
+async fn run_cli_mode(
+    endpoint_overrides: &[String],
+    record: bool,
+    source: Option<&str>,
+) -> Result<()> {
     init_logging(true);
 
     println!("╔═══════════════════════════════════════════════════════════╗");
@@ -94,12 +257,14 @@ async fn run_cli_mode() -> Result<()> {
     // Step 1: Load configuration
     println!("[1/5] 加载配置...");
     let config = AppConfig::load_or_default()?;
+    let endpoints = resolve_endpoints(&config, endpoint_overrides)?;
     info!("Configuration loaded");
     println!("      ✅ 配置加载成功");
 
     // Step 2: Initialize credential store and register device
     println!("[2/5] 初始化设备凭据...");
-    let credential_store = CredentialStore::new(&config)?;
+    let credential_store =
+        Arc::new(CredentialStore::new(&config)?.with_endpoints(endpoints.clone()));
 
     println!("      正在注册设备或加载缓存凭据...");
     let credentials = credential_store.ensure_credentials().await?;
@@ -116,6 +281,32 @@ async fn run_cli_mode() -> Result<()> {
     let audio_capture = match AudioCapture::new() {
         Ok(capture) => {
             println!("      ✅ 音频设备初始化成功");
+            if let Some(ref device) = config.audio.device {
+                println!("      🎙️  输入设备: {}", device);
+            }
+            let capture = capture
+                .with_input_device(config.audio.device.clone())
+                .with_vad(config.vad_settings())
+                .with_opus(config.audio.opus.settings());
+            capture.set_on_device_fallback(|fallback| {
+                println!(
+                    "⚠️  输入设备 '{}' 不可用，已切换到默认设备 '{}'",
+                    fallback.lost, fallback.fallback
+                );
+            });
+            if let Some(spec) = source {
+                let source = source_from_spec(spec)?;
+                println!("      🎧 音频来源: {}", source.name());
+                capture.set_source(Some(source));
+            }
+            // Opened only once fallbacks are reported
+            capture.set_preroll(config.audio.preroll());
+            if let Some(preroll) = capture.preroll() {
+                println!(
+                    "      🔴 麦克风常开: 保留最近 {}ms 音频 (仅在内存中)",
+                    preroll.as_millis()
+                );
+            }
             Arc::new(capture)
         }
         Err(e) => {
@@ -129,14 +320,34 @@ async fn run_cli_mode() -> Result<()> {
     // Step 4: Initialize components
     println!("[4/5] 初始化组件...");
     let text_inserter = Arc::new(TextInserter::new());
-    let asr_client = Arc::new(AsrClient::new(credentials.clone()));
-
-    let voice_controller = Arc::new(Mutex::new(VoiceController::new(
+    let vocabulary = load_vocabulary();
+    if !vocabulary.is_empty() {
+        println!("      📖 已加载 {} 个自定义词条", vocabulary.terms().len());
+    }
+    let asr_client = Arc::new(build_asr_client(
+        &config,
+        credentials,
+        endpoints,
+        config.network.resolve()?,
+        credential_store.clone(),
+        &vocabulary,
+        record,
+    ));
+
+    let mut controller = VoiceController::new(
         asr_client.clone(),
         audio_capture.clone(),
         text_inserter.clone(),
-    )));
+    );
+    controller.set_on_stats(|stats| print_session_stats(&stats));
+    controller.set_on_auto_stop(|| println!("⏹️  检测到静音，语音输入已自动停止"));
+    controller.set_corrector(build_corrector(&config, &vocabulary));
+    controller.set_archive(build_archive(&config));
+    let voice_controller = Arc::new(Mutex::new(controller));
     println!("      ✅ ASR 客户端、文本插入器已就绪");
+    if config.archive.enabled {
+        println!("      🗄️  会话存档: {}", AppConfig::archive_dir().display());
+    }
 
     // Step 5: Ready for testing
     println!("[5/5] 初始化完成！");
@@ -147,6 +358,12 @@ async fn run_cli_mode() -> Result<()> {
     println!("  [e] 停止语音输入 (End)");
     println!("  [t] 测试文本插入");
     println!("  [a] 测试 ASR 连接");
+    println!("  [l] 查看连接耗时 (Latency)");
+    println!("  [d] 列出输入设备 (Devices)");
+    println!(
+        "  [g] 切换识别语言 (当前: {})",
+        voice_controller.lock().await.language().label()
+    );
     println!("  [q] 退出程序 (Quit)");
     println!("════════════════════════════════════════════════════════════");
     println!();
@@ -224,13 +441,71 @@ async fn run_cli_mode() -> Result<()> {
                 println!("🔗 测试 ASR 连接...");
                 info!("Testing ASR connection...");
 
-                println!("   设备 ID: {}", credentials.device_id);
-                println!(
-                    "   Token: {}...",
-                    &credentials.token[..20.min(credentials.token.len())]
-                );
-                println!("✅ ASR 凭据有效");
-                println!("   完整 ASR 测试需要开始录音 (命令: s)");
+                match credential_store.validate_credentials().await {
+                    Ok(status) => {
+                        if let Some(renewed) = credential_store.current().await {
+                            asr_client.set_credentials(renewed);
+                        }
+                        let credentials = asr_client.credentials();
+                        println!("   设备 ID: {}", credentials.device_id);
+                        println!(
+                            "   Token: {}...",
+                            &credentials.token[..20.min(credentials.token.len())]
+                        );
+                        match status {
+                            CredentialStatus::Valid => println!("✅ ASR 凭据有效"),
+                            CredentialStatus::Refreshed => println!("✅ Token 已刷新"),
+                            CredentialStatus::Reregistered => println!("✅ 设备已重新注册"),
+                        }
+                        println!("   完整 ASR 测试需要开始录音 (命令: s)");
+                    }
+                    Err(e) => {
+                        error!("Credential validation failed: {}", e);
+                        println!("❌ 凭据验证失败: {}", e);
+                    }
+                }
+            }
+            "l" | "latency" => {
+                let metrics = asr_client.latency_metrics();
+                let average = |stat: &LatencyStat| match stat.average_ms() {
+                    Some(avg) => {
+                        format!("{} 次, 平均 {}ms, 最大 {}ms", stat.count, avg, stat.max_ms)
+                    }
+                    None => "暂无".to_string(),
+                };
+                println!("⏱️  会话就绪耗时:");
+                println!("   预热连接: {}", average(&metrics.warm));
+                println!("   即时连接: {}", average(&metrics.cold));
+                println!("   后台握手: {}", average(&metrics.background_handshake));
+            }
+            "d" | "devices" => match list_input_devices() {
+                Ok(devices) => {
+                    let selected = audio_capture.input_device();
+                    println!("🎙️  输入设备:");
+                    for device in devices {
+                        let mark = if selected.as_deref() == Some(device.name.as_str()) {
+                            "*"
+                        } else {
+                            " "
+                        };
+                        let default = if device.is_default { " (默认)" } else { "" };
+                        let format = device
+                            .default_config
+                            .map(|c| {
+                                format!("{}Hz {}ch {}", c.sample_rate, c.channels, c.sample_format)
+                            })
+                            .unwrap_or_default();
+                        println!("   {} {}{}  {}", mark, device.name, default, format);
+                    }
+                }
+                Err(e) => println!("❌ 无法列出输入设备: {}", e),
+            },
+            "g" | "lang" => {
+                let vc = voice_controller.lock().await;
+                let language = vc.language().next();
+                vc.set_language(language);
+                info!("Recognition language switched to {}", language.code());
+                println!("🌐 识别语言: {} (下次录音生效)", language.label());
             }
             "q" | "quit" | "exit" => {
                 println!("👋 退出程序...");
@@ -242,7 +517,7 @@ async fn run_cli_mode() -> Result<()> {
             }
             _ => {
                 println!("❓ 未知命令: {}", cmd);
-                println!("   输入 s/e/t/a/q");
+                println!("   输入 s/e/t/a/l/d/g/q");
             }
         }
     }
@@ -257,7 +532,234 @@ async fn run_cli_mode() -> Result<()> {
     Ok(())
 }
 
+/// Print the figures of a finished session
+fn print_session_stats(stats: &SessionStats) {
+    let ms = |value: Option<u64>| match value {
+        Some(ms) => format!("{}ms", ms),
+        None => "-".to_string(),
+    };
+    println!("📊 [会话统计]");
+    println!(
+        "   就绪: {}ms ({})",
+        stats.setup_ms,
+        if stats.warm { "预热" } else { "即时连接" }
+    );
+    println!(
+        "   连接: {}, 握手: {}",
+        ms(stats.connect_ms),
+        ms(stats.handshake_ms)
+    );
+    println!(
+        "   首个结果: {}, 停止到定稿: {}",
+        ms(stats.first_interim_ms),
+        ms(stats.stop_to_final_ms)
+    );
+    println!(
+        "   发送 {} 帧, 丢弃 {} 帧, {:.1} KB/s",
+        stats.frames_sent,
+        stats.frames_dropped,
+        stats.bytes_per_second as f64 / 1024.0
+    );
+    if stats.reconnects > 0 {
+        println!("   重连 {} 次", stats.reconnects);
+    }
+    if stats.rollovers > 0 {
+        println!("   会话切换 {} 次", stats.rollovers);
+    }
+}
+
+/// Replay a recorded session offline through the voice controller
+async fn run_replay_mode(path: &str, paced: bool) -> Result<()> {
+    init_logging(true);
+
+    println!("🔁 回放会话: {}", path);
+    let frames = load_capture(path)?;
+    let sent = frames.iter().filter(|f| f.dir == Direction::Out).count();
+    println!(
+        "   共 {} 帧 (发送 {}, 接收 {})",
+        frames.len(),
+        sent,
+        frames.len() - sent
+    );
+
+    // Type into a buffer instead of the focused window
+    let buffer = TextBuffer::new();
+    let text_inserter = Arc::new(TextInserter::with_action(Box::new(buffer.clone())));
+    let asr_client = Arc::new(AsrClient::new(DeviceCredentials::new_generated()));
+    let audio_capture = Arc::new(AudioCapture::new()?);
+
+    let mut voice_controller = VoiceController::new(asr_client, audio_capture, text_inserter);
+    voice_controller.start_replay(replay_capture(frames, paced)?)?;
+    while voice_controller.is_recording() {
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+
+    println!();
+    println!("📄 最终文本: {}", buffer.text());
+    Ok(())
+}
+
+/// Transcribe a file, or every audio file in a directory
+async fn run_transcribe_mode(
+    args: &[String],
+    endpoint_overrides: &[String],
+    record: bool,
+) -> Result<()> {
+    // stdout is reserved for the transcript
+    init_logging_to(false, io::stderr);
+
+    let input = match args.get(2) {
+        Some(input) if !input.starts_with("--") => PathBuf::from(input),
+        _ => anyhow::bail!(
+            "Usage: doubao-voice-input transcribe <FILE|DIR> [--output PATH] \
+             [--format txt|jsonl|srt|vtt] [--speed N]"
+        ),
+    };
+    let output = parse_flag_value(args, "--output").map(PathBuf::from);
+    let format = match parse_flag_value(args, "--format") {
+        Some(name) => TranscriptFormat::from_name(&name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown transcript format: {}", name))?,
+        None => output
+            .as_ref()
+            .filter(|_| !input.is_dir())
+            .and_then(|path| path.extension())
+            .and_then(|ext| TranscriptFormat::from_name(&ext.to_string_lossy()))
+            .unwrap_or_default(),
+    };
+    let max_speed = match parse_flag_value(args, "--speed") {
+        Some(speed) => Some(
+            speed
+                .parse::<f64>()
+                .map_err(|_| anyhow::anyhow!("Invalid --speed: {}", speed))?,
+        ),
+        None => None,
+    };
+
+    let config = AppConfig::load_or_default()?;
+    let endpoints = resolve_endpoints(&config, endpoint_overrides)?;
+    let credential_store =
+        Arc::new(CredentialStore::new(&config)?.with_endpoints(endpoints.clone()));
+    let credentials = credential_store.ensure_credentials().await?;
+    let vocabulary = load_vocabulary();
+    let session_options = config.session_options(&vocabulary);
+    let asr_client = Arc::new(build_asr_client(
+        &config,
+        credentials,
+        endpoints,
+        config.network.resolve()?,
+        credential_store,
+        &vocabulary,
+        record,
+    ));
+    let transcriber = FileTranscriber::new(asr_client)
+        .with_max_speed(max_speed)
+        .with_opus(config.audio.opus.settings())
+        .with_formatter(TextFormatter::new(session_options.language))
+        .with_corrector(build_corrector(&config, &vocabulary));
+
+    if !input.is_dir() {
+        eprintln!("🎧 转写: {}", input.display());
+        let transcript = transcriber.transcribe(&input).await?;
+        let text = transcript.render(format);
+        match output {
+            Some(path) => std::fs::write(&path, text)?,
+            None => print!("{}", text),
+        }
+        eprintln!(
+            "✅ 完成: {:.1}s 音频, 耗时 {:.1}s ({:.1}x 实时)",
+            transcript.audio_duration.as_secs_f64(),
+            transcript.elapsed.as_secs_f64(),
+            transcript.speed()
+        );
+        return Ok(());
+    }
+
+    let output_dir = output.unwrap_or_else(|| input.clone());
+    std::fs::create_dir_all(&output_dir)?;
+    let files = audio_files_in(&input)?;
+    eprintln!("📂 批量转写: {} 个音频文件", files.len());
+
+    let mut failed = 0;
+    for (i, file) in files.iter().enumerate() {
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        // Keep dots in the stem: "meeting.v2.wav" → "meeting.v2.srt"
+        let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+        let target = output_dir.join(format!("{}.{}", stem, format.extension()));
+        if target.exists() {
+            eprintln!("[{}/{}] ⏭️  {} (已有转写，跳过)", i + 1, files.len(), name);
+            continue;
+        }
+
+        match transcriber.transcribe(file).await {
+            Ok(transcript) => {
+                std::fs::write(&target, transcript.render(format))?;
+                eprintln!(
+                    "[{}/{}] ✅ {} → {} ({:.1}s 音频, {:.1}x 实时)",
+                    i + 1,
+                    files.len(),
+                    name,
+                    target.display(),
+                    transcript.audio_duration.as_secs_f64(),
+                    transcript.speed()
+                );
+            }
+            Err(e) => {
+                failed += 1;
+                eprintln!("[{}/{}] ❌ {}: {}", i + 1, files.len(), name, e);
+            }
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{} of {} files failed to transcribe", failed, files.len());
+    }
+    Ok(())
+}
+
+/// Compare Opus encoder settings on a file or a generated fixture
+fn run_opus_bench_mode(args: &[String]) -> Result<()> {
+    init_logging_to(false, io::stderr);
+
+    let (name, pcm) = match args.get(2) {
+        Some(path) if !path.starts_with("--") => (path.clone(), read_fixture(path)?),
+        _ => (
+            "合成语音 (60s)".to_string(),
+            speech_like_fixture(std::time::Duration::from_secs(60)),
+        ),
+    };
+    let config = AppConfig::load_or_default()?;
+    let configured = config.audio.opus.settings();
+
+    println!("🎛️  Opus 编码对比: {}", name);
+    // CJK headers are two columns wide per character
+    println!(
+        "   {:<34} {:>8} {:>8} {:>8}",
+        "设置", "kbps", "MB/小时", "CPU"
+    );
+    for settings in opus_bench_presets(&configured) {
+        let result = bench_opus(&pcm, &settings)?;
+        let marker = if settings == configured { "*" } else { " " };
+        println!(
+            " {} {:<36} {:>8.1} {:>10.1} {:>7.2}%",
+            marker,
+            settings.describe(),
+            result.kbps(),
+            result.mb_per_hour(),
+            result.cpu_percent()
+        );
+    }
+    println!("   * 当前配置 ([audio.opus]); CPU 为实时编码占用的单核比例");
+    Ok(())
+}
+
 fn init_logging(debug: bool) {
+    init_logging_to(debug, io::stdout);
+}
+
+fn init_logging_to<W>(debug: bool, writer: W)
+where
+    W: for<'w> tracing_subscriber::fmt::MakeWriter<'w> + Send + Sync + 'static,
+{
     let level = if debug {
         "doubao_voice_input=debug"
     } else {
@@ -268,6 +770,6 @@ fn init_logging(debug: bool) {
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| level.into()),
         )
-        .with(tracing_subscriber::fmt::layer())
+        .with(tracing_subscriber::fmt::layer().with_writer(writer))
         .init();
 }
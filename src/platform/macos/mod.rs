@@ -57,6 +57,41 @@ mod native {
 
         Ok(())
     }
+
+    pub fn focused_app() -> Option<String> {
+        use cocoa::base::{id, nil};
+        use objc::{msg_send, sel, sel_impl};
+
+        unsafe {
+            let class = objc::runtime::Class::get("NSWorkspace")?;
+            let workspace: id = msg_send![class, sharedWorkspace];
+            let app: id = msg_send![workspace, frontmostApplication];
+            if app == nil {
+                return None;
+            }
+            let name: id = msg_send![app, localizedName];
+            if name == nil {
+                return None;
+            }
+            let utf8: *const libc::c_char = msg_send![name, UTF8String];
+            if utf8.is_null() {
+                return None;
+            }
+            Some(
+                std::ffi::CStr::from_ptr(utf8)
+                    .to_string_lossy()
+                    .into_owned(),
+            )
+        }
+    }
+}
+
+/// Frontmost application's name
+pub fn focused_app() -> Option<String> {
+    #[cfg(target_os = "macos")]
+    return native::focused_app();
+    #[cfg(not(target_os = "macos"))]
+    None
 }
 
 pub struct MacosTextInserter;
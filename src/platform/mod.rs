@@ -19,6 +19,11 @@ pub trait HotkeyProvider: Send + Sync {
 pub mod macos;
 pub use macos::PlatformImpl;
 
+/// Name of the application that has keyboard focus, when it can be told
+pub fn focused_app() -> Option<String> {
+    macos::focused_app()
+}
+
 /// Factory for creating platform-specific implementations
 pub struct PlatformFactory;
 
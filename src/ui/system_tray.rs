@@ -11,12 +11,13 @@ use tray_icon::{
     TrayIconBuilder,
 };
 
+use crate::asr::Language;
 use crate::business::{HotkeyManager, VoiceController};
 use crate::data::AppConfig;
 
 /// Run the application with system tray
 pub async fn run_app(
-    _config: AppConfig,
+    config: AppConfig,
     voice_controller: Arc<Mutex<VoiceController>>,
     _hotkey_manager: HotkeyManager,
 ) -> Result<()> {
@@ -27,18 +28,21 @@ pub async fn run_app(
     let start_item = MenuItem::new("开始语音输入", true, None);
     let stop_item = MenuItem::new("停止语音输入", true, None);
     let separator1 = PredefinedMenuItem::separator();
+    let language_item = MenuItem::new(language_menu_text(config.general.language()), true, None);
     let settings_item = MenuItem::new("设置...", true, None);
     let separator2 = PredefinedMenuItem::separator();
     let quit_item = MenuItem::new("退出", true, None);
 
     let start_id = start_item.id().clone();
     let stop_id = stop_item.id().clone();
+    let language_id = language_item.id().clone();
     let settings_id = settings_item.id().clone();
     let quit_id = quit_item.id().clone();
 
     menu.append(&start_item)?;
     menu.append(&stop_item)?;
     menu.append(&separator1)?;
+    menu.append(&language_item)?;
     menu.append(&settings_item)?;
     menu.append(&separator2)?;
     menu.append(&quit_item)?;
@@ -54,6 +58,9 @@ pub async fn run_app(
     // Running flag
     let running = Arc::new(AtomicBool::new(true));
 
+    // Language switched from the menu; the item is relabeled on this thread
+    let switched_language = Arc::new(std::sync::Mutex::new(None::<Language>));
+
     // Get menu receiver
     let menu_rx = MenuEvent::receiver();
 
@@ -63,6 +70,7 @@ pub async fn run_app(
     // Spawn event handler thread for menu events
     let running_clone = running.clone();
     let vc_clone = voice_controller.clone();
+    let switched_clone = switched_language.clone();
 
     std::thread::spawn(move || {
         while running_clone.load(Ordering::SeqCst) {
@@ -90,6 +98,21 @@ pub async fn run_app(
                             }
                         }
                     });
+                } else if event.id == language_id {
+                    let vc = vc_clone.clone();
+                    let switched = switched_clone.clone();
+                    runtime_handle.spawn(async move {
+                        let controller = vc.lock().await;
+                        let language = controller.language().next();
+                        controller.set_language(language);
+                        tracing::info!("Recognition language switched to {}", language.code());
+                        if let Err(e) = save_language(language) {
+                            tracing::warn!("Failed to save language: {}", e);
+                        }
+                        if let Ok(mut switched) = switched.lock() {
+                            *switched = Some(language);
+                        }
+                    });
                 } else if event.id == settings_id {
                     tracing::info!("Settings from menu");
                     // Platform-specific settings message
@@ -110,12 +133,26 @@ pub async fn run_app(
     // Event loop for macOS
     while running.load(Ordering::SeqCst) {
         tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        if let Some(language) = switched_language.lock().ok().and_then(|mut l| l.take()) {
+            language_item.set_text(language_menu_text(language));
+        }
     }
 
     tracing::info!("Application exiting");
     Ok(())
 }
 
+fn language_menu_text(language: Language) -> String {
+    format!("识别语言: {}", language.label())
+}
+
+/// Persist a language switched from the menu
+fn save_language(language: Language) -> Result<()> {
+    let mut config = AppConfig::load_or_default()?;
+    config.general.language = language.code().to_string();
+    config.save()
+}
+
 /// Load the tray icon with modern appearance
 fn load_icon() -> Result<tray_icon::Icon> {
     let width = 32u32;
@@ -0,0 +1,139 @@
+//! `AudioCapture` on sources other than a microphone: generated signals and
+//! files go through the same resample → Opus → channel pipeline.
+
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use doubao_voice_input::audio::{
+    source_from_spec, AudioFileReader, FileSource, SyntheticSource, VadSettings,
+};
+use doubao_voice_input::AudioCapture;
+
+/// Receive Opus frames until the capture closes the channel
+async fn collect_frames(mut rx: mpsc::Receiver<Vec<u8>>) -> usize {
+    let mut frames = 0;
+    while let Ok(Some(_)) = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await {
+        frames += 1;
+    }
+    frames
+}
+
+#[tokio::test]
+async fn synthetic_source_streams_until_it_ends() {
+    // 400ms of stereo 48kHz: 20 frames, less the resampler's latency
+    let source = SyntheticSource::new(48000)
+        .with_channels(2)
+        .tone(440.0, 0.3, Duration::from_millis(200))
+        .silence(Duration::from_millis(200));
+    assert_eq!(source.duration(), Duration::from_millis(400));
+
+    let capture = AudioCapture::new().unwrap().with_source(Arc::new(source));
+    let frames = collect_frames(capture.start().unwrap()).await;
+    assert!((18..=20).contains(&frames), "{} frames", frames);
+    assert!(!capture.is_recording());
+}
+
+#[tokio::test]
+async fn vad_auto_stop_works_on_generated_speech() {
+    let source = SyntheticSource::new(16000)
+        .tone(300.0, 0.3, Duration::from_millis(300))
+        .silence(Duration::from_secs(1));
+    let capture = AudioCapture::new()
+        .unwrap()
+        .with_source(Arc::new(source))
+        .with_vad(Some(VadSettings {
+            silence_timeout: Some(Duration::from_millis(400)),
+            ..VadSettings::default()
+        }));
+
+    let rx = capture.start().unwrap();
+    assert!(!capture.silence_timed_out());
+    collect_frames(rx).await;
+    assert!(capture.silence_timed_out());
+}
+
+#[tokio::test]
+async fn wav_files_decode_and_stream() {
+    let path = std::env::temp_dir().join(format!("audio-source-{}.wav", std::process::id()));
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+    for i in 0..13230 {
+        writer.write_sample((i % 100) as i16).unwrap();
+    }
+    writer.finalize().unwrap();
+
+    let mut reader = AudioFileReader::open(&path).unwrap();
+    assert_eq!((reader.sample_rate(), reader.channels()), (44100, 1));
+    let mut samples = vec![0i16; 20000];
+    assert_eq!(reader.read(&mut samples).unwrap(), 13230);
+    assert_eq!(&samples[98..102], &[98, 99, 0, 1]);
+    assert_eq!(reader.read(&mut samples).unwrap(), 0);
+
+    // 300ms
+    let capture = AudioCapture::new()
+        .unwrap()
+        .with_source(Arc::new(FileSource::new(&path)));
+    let frames = collect_frames(capture.start().unwrap()).await;
+    assert!((13..=15).contains(&frames), "{} frames", frames);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn unsupported_files_are_rejected() {
+    let path = std::env::temp_dir().join(format!("audio-source-{}.txt", std::process::id()));
+    std::fs::write(&path, "not audio").unwrap();
+    assert!(AudioFileReader::open(&path).is_err());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn sources_parse_from_specs() {
+    assert_eq!(source_from_spec("mic").unwrap().name(), "(default)");
+    assert_eq!(
+        source_from_spec("mic:USB Mic: Left").unwrap().name(),
+        "USB Mic: Left"
+    );
+    assert_eq!(
+        source_from_spec("file:/tmp/a.wav").unwrap().name(),
+        "/tmp/a.wav"
+    );
+    assert_eq!(
+        source_from_spec("stdin:48000:2").unwrap().name(),
+        "stdin (48000Hz, 2 channels)"
+    );
+    assert_eq!(
+        source_from_spec("tone:1000:2").unwrap().name(),
+        "synthetic (2.0s)"
+    );
+    assert_eq!(
+        source_from_spec("silence:0.5").unwrap().name(),
+        "synthetic (0.5s)"
+    );
+
+    assert!(source_from_spec("file").is_err());
+    assert!(source_from_spec("stdin:fast").is_err());
+    // Rates and channel counts are whole and non-zero
+    for spec in [
+        "stdin:0.5",
+        "stdin:0",
+        "stdin:16000.5",
+        "stdin:16000:0",
+        "stdin:16000:1.5",
+        "stdin:16000:70000",
+        "stdin:-16000",
+        // Less than a sample per frame, or more than the pipeline takes
+        "stdin:10",
+        "stdin:300000000",
+        "stdin:16000:9",
+    ] {
+        assert!(source_from_spec(spec).is_err(), "{}", spec);
+    }
+    assert!(source_from_spec("speaker").is_err());
+}
@@ -0,0 +1,111 @@
+//! The capture pipeline's real-time guarantees: the device callback side
+//! never allocates, and frames come out downmixed and resampled.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use doubao_voice_input::audio::capture_pipeline;
+
+/// Counts allocations made on the current thread
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(|n| n.get())
+}
+
+#[test]
+fn callbacks_and_frame_processing_do_not_allocate() {
+    // 48kHz stereo, 10ms callbacks
+    let (mut writer, mut processor) = capture_pipeline(48000, 2).unwrap();
+    let f32_block = vec![0.25f32; 960];
+    let i16_block = vec![1000i16; 960];
+
+    let before = allocations();
+    let mut frames = 0;
+    for i in 0..200 {
+        if i % 2 == 0 {
+            writer.write_f32(&f32_block);
+        } else {
+            writer.write_i16(&i16_block);
+        }
+        while let Some(frame) = processor.next_frame() {
+            assert_eq!(frame.len(), 320);
+            frames += 1;
+        }
+    }
+    assert_eq!(allocations(), before);
+    // The resampler's filter holds back a few samples
+    assert!((99..=100).contains(&frames), "{} frames", frames);
+    assert_eq!(processor.overrun_samples(), 0);
+}
+
+#[test]
+fn frames_are_downmixed_and_resampled_to_16k_mono() {
+    let (mut writer, mut processor) = capture_pipeline(48000, 2).unwrap();
+    assert_eq!(processor.native_frame_len(), 1920);
+
+    // Left and right channels average out to 0.5
+    let block: Vec<f32> = (0..1920)
+        .map(|i| if i % 2 == 0 { 0.25 } else { 0.75 })
+        .collect();
+    writer.write_f32(&block[..1000]);
+    assert!(processor.next_frame().is_none());
+    writer.write_f32(&block[1000..]);
+    for _ in 0..4 {
+        writer.write_f32(&block);
+    }
+
+    // Past the resampler's start-up transient the level is unchanged
+    let mut frames = 0;
+    let mut last = Vec::new();
+    while let Some(frame) = processor.next_frame() {
+        assert_eq!(frame.len(), 320);
+        last = frame.to_vec();
+        frames += 1;
+    }
+    assert!((4..=5).contains(&frames), "{} frames", frames);
+    assert!(last.iter().all(|&s| (s - 16383).abs() <= 2), "{:?}", last);
+    assert!(processor.volume() > 0);
+
+    // A full ring drops the excess instead of blocking the audio thread
+    let silence = vec![0.0f32; 1920 * 30];
+    writer.write_f32(&silence);
+    assert_eq!(processor.overrun_samples(), 1920 * 5);
+}
+
+#[test]
+fn unsupported_native_formats_are_rejected() {
+    // Less than one sample per 20ms frame
+    assert!(capture_pipeline(10, 1).is_err());
+    assert!(capture_pipeline(0, 1).is_err());
+    // Would overflow the frame length in u32, and allocate gigabytes
+    assert!(capture_pipeline(300_000_000, 1).is_err());
+    assert!(capture_pipeline(48000, 0).is_err());
+    assert!(capture_pipeline(48000, 9).is_err());
+
+    let (_, processor) = capture_pipeline(8000, 8).unwrap();
+    assert_eq!(processor.native_frame_len(), 1280);
+}
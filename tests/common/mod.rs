@@ -0,0 +1,35 @@
+//! Fixtures shared by the integration tests.
+
+// Each test binary uses its own subset
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use doubao_voice_input::asr::{DeviceCredentials, DeviceProfile};
+
+pub fn test_credentials() -> DeviceCredentials {
+    DeviceCredentials {
+        device_id: "1234567890".to_string(),
+        install_id: "987654321".to_string(),
+        cdid: "cdid".to_string(),
+        openudid: "openudid".to_string(),
+        clientudid: "clientudid".to_string(),
+        token: "test-token".to_string(),
+        profile: DeviceProfile::default(),
+    }
+}
+
+/// A new empty directory, unique to the calling test
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("doubao-{}-{}", name, uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
@@ -0,0 +1,1416 @@
+//! End-to-end tests of `AsrClient` against the local mock ASR server.
+
+mod common;
+
+use prost::Message as _;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+
+use common::{now_ms, test_credentials};
+use doubao_voice_input::asr::mock_server::{MockAsrServer, MockReply, MockScript, ScriptedResult};
+use doubao_voice_input::asr::proto::AsrRequest;
+use doubao_voice_input::asr::{
+    load_capture, replay_capture, AsrError, AsrResponse, DeviceCredentials, DeviceProfile,
+    Direction, Endpoints, Hotword, Language, Proxy, ProxySettings, ResponseType, SessionOptions,
+};
+use doubao_voice_input::business::TextBuffer;
+use doubao_voice_input::data::CredentialStatus;
+use doubao_voice_input::{AsrClient, AudioCapture, CredentialStore, TextInserter, VoiceController};
+
+/// Feed `frames` dummy Opus frames, then close the audio channel
+fn audio_frames(frames: usize) -> mpsc::Receiver<Vec<u8>> {
+    let (tx, rx) = mpsc::channel(frames + 1);
+    for i in 0..frames {
+        tx.try_send(vec![i as u8; 40]).unwrap();
+    }
+    rx
+}
+
+async fn collect(mut rx: mpsc::Receiver<AsrResponse>) -> Vec<AsrResponse> {
+    let mut responses = Vec::new();
+    while let Ok(Some(response)) = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await {
+        let done = matches!(
+            response.response_type,
+            ResponseType::SessionFinished | ResponseType::Error
+        );
+        responses.push(response);
+        if done {
+            break;
+        }
+    }
+    responses
+}
+
+#[tokio::test]
+async fn streams_scripted_results() {
+    let server = MockAsrServer::start(MockScript::recognize("你好世界"))
+        .await
+        .unwrap();
+    let client = AsrClient::new(test_credentials()).with_ws_url(server.url());
+
+    let rx = client.start_realtime(audio_frames(20)).await.unwrap();
+    let responses = collect(rx).await;
+
+    let types: Vec<_> = responses.iter().map(|r| r.response_type.clone()).collect();
+    assert_eq!(
+        types,
+        vec![
+            ResponseType::InterimResult,
+            ResponseType::FinalResult,
+            ResponseType::SessionFinished
+        ]
+    );
+    assert_eq!(responses[0].text, "你好");
+    assert_eq!(responses[1].text, "你好世界");
+    assert!(responses[1].is_final);
+
+    let received = server.received();
+    let methods: Vec<_> = received.iter().map(|r| r.method_name.as_str()).collect();
+    assert_eq!(&methods[..2], &["StartTask", "StartSession"]);
+    assert_eq!(methods.last(), Some(&"FinishSession"));
+    assert_eq!(received[0].token, "test-token");
+    assert!(received[1].payload.contains("\"did\":\"1234567890\""));
+
+    let audio: Vec<_> = received
+        .iter()
+        .filter(|r| r.method_name == "TaskRequest")
+        .collect();
+    assert_eq!(audio.len(), 21);
+    assert_eq!(audio[0].frame_state, 1);
+    assert_eq!(audio[1].frame_state, 3);
+    assert_eq!(audio[20].frame_state, 9);
+}
+
+#[tokio::test]
+async fn preroll_frames_lead_the_session_with_earlier_timestamps() {
+    let server = MockAsrServer::start(MockScript::recognize("你好世界"))
+        .await
+        .unwrap();
+    let client = AsrClient::new(test_credentials()).with_ws_url(server.url());
+
+    // The first 20 frames (400ms) were captured before the hotkey
+    let before = now_ms();
+    let rx = client
+        .start_realtime_with_preroll(
+            audio_frames(30),
+            client.session_options(),
+            Duration::from_millis(400),
+        )
+        .await
+        .unwrap();
+    collect(rx).await;
+    let after = now_ms();
+
+    let audio: Vec<_> = server
+        .received()
+        .into_iter()
+        .filter(|r| r.method_name == "TaskRequest")
+        .collect();
+    let timestamps: Vec<u64> = audio
+        .iter()
+        .map(|r| {
+            let payload: serde_json::Value = serde_json::from_str(&r.payload).unwrap();
+            payload["timestamp_ms"].as_u64().unwrap()
+        })
+        .collect();
+    assert_eq!(audio[0].frame_state, 1);
+    assert!(timestamps[0] + 400 >= before && timestamps[0] + 400 <= after);
+    assert!(timestamps.windows(2).all(|pair| pair[1] - pair[0] == 20));
+}
+
+#[tokio::test]
+async fn session_stats_end_the_session() {
+    let server = MockAsrServer::start(MockScript {
+        latency: Duration::from_millis(30),
+        ..MockScript::recognize("你好世界")
+    })
+    .await
+    .unwrap();
+    let client = AsrClient::new(test_credentials()).with_ws_url(server.url());
+
+    let responses = collect(client.start_realtime(audio_frames(12)).await.unwrap()).await;
+    let last = responses.last().unwrap();
+    assert_eq!(last.response_type, ResponseType::SessionFinished);
+    assert!(responses[..responses.len() - 1]
+        .iter()
+        .all(|r| r.stats.is_none()));
+
+    let stats = last.stats.clone().unwrap();
+    assert!(!stats.warm);
+    assert!(stats.connect_ms.is_some());
+    // Two handshake replies, each delayed by the script
+    assert!(stats.handshake_ms.unwrap() >= 60);
+    assert!(stats.setup_ms >= stats.handshake_ms.unwrap());
+    assert!(stats.first_interim_ms.is_some());
+    assert!(stats.stop_to_final_ms.is_some());
+    assert_eq!(stats.frames_sent, 12);
+    assert_eq!(stats.frames_dropped, 0);
+    assert_eq!(stats.bytes_sent, 12 * 40);
+    assert!(stats.duration_ms >= stats.setup_ms);
+}
+
+#[tokio::test]
+async fn session_options_reach_start_session() {
+    let server = MockAsrServer::start(MockScript::recognize("你好"))
+        .await
+        .unwrap();
+    let client = AsrClient::new(test_credentials())
+        .with_ws_url(server.url())
+        .with_session_options(SessionOptions {
+            enable_punctuation: false,
+            ..Default::default()
+        });
+
+    // Client-wide options
+    collect(client.start_realtime(audio_frames(12)).await.unwrap()).await;
+
+    // Per-session override
+    let options = SessionOptions {
+        enable_speech_rejection: true,
+        app_name: "com.example.notes".to_string(),
+        ..client.session_options()
+    };
+    collect(
+        client
+            .start_realtime_with_options(audio_frames(12), options)
+            .await
+            .unwrap(),
+    )
+    .await;
+
+    let payloads: Vec<serde_json::Value> = server
+        .received()
+        .iter()
+        .filter(|r| r.method_name == "StartSession")
+        .map(|r| serde_json::from_str(&r.payload).unwrap())
+        .collect();
+    assert_eq!(payloads.len(), 2);
+    assert_eq!(payloads[0]["enable_punctuation"], false);
+    assert_eq!(payloads[0]["enable_speech_rejection"], false);
+    assert_eq!(payloads[0]["extra"]["app_name"], "com.android.chrome");
+    assert_eq!(payloads[1]["enable_punctuation"], false);
+    assert_eq!(payloads[1]["enable_speech_rejection"], true);
+    assert_eq!(payloads[1]["extra"]["app_name"], "com.example.notes");
+    assert_eq!(payloads[1]["extra"]["input_mode"], "tool");
+}
+
+#[tokio::test]
+async fn language_switch_applies_to_next_session() {
+    let server = MockAsrServer::start(MockScript::recognize("你好"))
+        .await
+        .unwrap();
+    let client = AsrClient::new(test_credentials()).with_ws_url(server.url());
+
+    for language in [Language::ZhCn, Language::EnUs, Language::Mixed] {
+        client.set_language(language);
+        collect(client.start_realtime(audio_frames(12)).await.unwrap()).await;
+    }
+
+    let payloads: Vec<serde_json::Value> = server
+        .received()
+        .iter()
+        .filter(|r| r.method_name == "StartSession")
+        .map(|r| serde_json::from_str(&r.payload).unwrap())
+        .collect();
+    assert_eq!(payloads[0]["language"], "zh-CN");
+    assert_eq!(payloads[1]["language"], "en-US");
+    // Mixed leaves the server default in place
+    assert!(payloads[2].get("language").is_none());
+}
+
+#[tokio::test]
+async fn hotwords_reach_start_session_context() {
+    let server = MockAsrServer::start(MockScript::recognize("你好"))
+        .await
+        .unwrap();
+    let client = AsrClient::new(test_credentials()).with_ws_url(server.url());
+
+    // No corpus without hotwords
+    collect(client.start_realtime(audio_frames(12)).await.unwrap()).await;
+
+    let options = SessionOptions {
+        hotwords: vec![
+            Hotword {
+                word: "豆包输入法".to_string(),
+                weight: Some(5.0),
+            },
+            Hotword {
+                word: "张伟".to_string(),
+                weight: None,
+            },
+        ],
+        ..Default::default()
+    };
+    collect(
+        client
+            .start_realtime_with_options(audio_frames(12), options)
+            .await
+            .unwrap(),
+    )
+    .await;
+
+    let payloads: Vec<serde_json::Value> = server
+        .received()
+        .iter()
+        .filter(|r| r.method_name == "StartSession")
+        .map(|r| serde_json::from_str(&r.payload).unwrap())
+        .collect();
+    assert!(payloads[0].get("corpus").is_none());
+    let context: serde_json::Value =
+        serde_json::from_str(payloads[1]["corpus"]["context"].as_str().unwrap()).unwrap();
+    assert_eq!(
+        context,
+        serde_json::json!({
+            "hotwords": [{ "word": "豆包输入法", "weight": 5.0 }, { "word": "张伟" }]
+        })
+    );
+}
+
+#[tokio::test]
+async fn results_expose_segments_and_word_timings() {
+    let result_json = serde_json::json!({
+        "results": [{
+            "text": "你好",
+            "index": 0,
+            "start_time": 120,
+            "end_time": 860.4,
+            "is_interim": false,
+            "is_vad_finished": true,
+            "alternatives": [
+                {
+                    "text": "你好",
+                    "confidence": 0.93,
+                    "words": [
+                        { "word": "你", "start_time": 120, "end_time": 480 },
+                        { "word": "好", "start_time": 480, "end_time": 860 }
+                    ]
+                },
+                { "text": "拟好", "confidence": 0.41 }
+            ],
+            "extra": { "nonstream_result": true, "model": "unknown-field" }
+        }],
+        "extra": { "packet_number": 7 }
+    });
+    let server = MockAsrServer::start(MockScript {
+        results: vec![ScriptedResult::raw(3, result_json.to_string())],
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    let client = AsrClient::new(test_credentials()).with_ws_url(server.url());
+
+    let responses = collect(client.start_realtime(audio_frames(5)).await.unwrap()).await;
+    let result = &responses[0];
+    assert_eq!(result.response_type, ResponseType::FinalResult);
+    assert_eq!(result.segments.len(), 1);
+
+    let segment = &result.segments[0];
+    assert!(segment.is_final());
+    assert_eq!(segment.index, Some(0));
+    assert_eq!(segment.start_time, Some(120));
+    assert_eq!(segment.end_time, Some(860));
+    assert_eq!(segment.alternatives.len(), 2);
+    assert_eq!(segment.alternatives[1].text, "拟好");
+    assert_eq!(segment.alternatives[0].confidence, Some(0.93));
+
+    let words: Vec<_> = segment
+        .words()
+        .iter()
+        .map(|w| (w.word.as_str(), w.start_time, w.end_time))
+        .collect();
+    assert_eq!(
+        words,
+        vec![("你", Some(120), Some(480)), ("好", Some(480), Some(860))]
+    );
+}
+
+#[tokio::test]
+async fn loosely_typed_fields_do_not_hide_results() {
+    let result_json = serde_json::json!({
+        "results": [
+            {
+                "text": "你好",
+                "index": -1,
+                "start_time": "120",
+                "end_time": "860.4",
+                "is_interim": "false",
+                "is_vad_finished": 1,
+                "words": null,
+                "alternatives": null,
+                "extra": null
+            },
+            {
+                "text": "世界",
+                "index": "1",
+                "words": [{ "word": "世", "start_time": -5, "end_time": "bad" }],
+                "is_interim": false,
+                "is_vad_finished": true
+            },
+            {
+                "text": "。",
+                "words": "not a list",
+                "is_interim": false,
+                "is_vad_finished": true
+            }
+        ],
+        "extra": { "packet_number": "7", "vad_start": null }
+    });
+    let server = MockAsrServer::start(MockScript {
+        results: vec![ScriptedResult::raw(3, result_json.to_string())],
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    let client = AsrClient::new(test_credentials()).with_ws_url(server.url());
+
+    let responses = collect(client.start_realtime(audio_frames(5)).await.unwrap()).await;
+    let result = &responses[0];
+    assert_eq!(result.response_type, ResponseType::FinalResult);
+    assert_eq!(result.text, "你好世界。");
+
+    let first = &result.segments[0];
+    assert_eq!(first.index, None);
+    assert_eq!((first.start_time, first.end_time), (Some(120), Some(860)));
+    assert!(first.is_final());
+    assert!(first.words().is_empty());
+
+    let second = &result.segments[1];
+    assert_eq!(second.index, Some(1));
+    assert_eq!(
+        (second.words[0].start_time, second.words[0].end_time),
+        (None, None)
+    );
+
+    // A segment that cannot be read at all keeps its text and finality
+    let third = &result.segments[2];
+    assert_eq!(third.text, "。");
+    assert!(third.is_final());
+}
+
+#[tokio::test]
+async fn multi_segment_results_commit_finalized_segments() {
+    let segment = |index: u32, text: &str, is_final: bool| {
+        serde_json::json!({
+            "text": text,
+            "index": index,
+            "is_interim": !is_final,
+            "is_vad_finished": is_final,
+            "extra": { "nonstream_result": is_final }
+        })
+    };
+    let payload = |segments: Vec<serde_json::Value>| {
+        serde_json::json!({ "results": segments, "extra": {} }).to_string()
+    };
+    let server = MockAsrServer::start(MockScript {
+        results: vec![
+            ScriptedResult::raw(2, payload(vec![segment(0, "今天", false)])),
+            ScriptedResult::raw(
+                4,
+                payload(vec![
+                    segment(0, "今天天气。", true),
+                    segment(1, "不", false),
+                ]),
+            ),
+            // The server may resend segments it already finalized
+            ScriptedResult::raw(
+                6,
+                payload(vec![
+                    segment(0, "今天天气。", true),
+                    segment(1, "不错。", true),
+                ]),
+            ),
+        ],
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    let client = AsrClient::new(test_credentials()).with_ws_url(server.url());
+
+    let responses = collect(client.start_realtime(audio_frames(10)).await.unwrap()).await;
+    let summary: Vec<_> = responses
+        .iter()
+        .map(|r| (r.response_type.clone(), r.text.as_str(), r.segments.len()))
+        .collect();
+    assert_eq!(
+        summary[..3],
+        [
+            (ResponseType::InterimResult, "今天", 1),
+            (ResponseType::InterimResult, "今天天气。不", 2),
+            (ResponseType::FinalResult, "今天天气。不错。", 2),
+        ]
+    );
+    assert!(responses[1].segments[0].is_final());
+    assert!(!responses[1].segments[1].is_final());
+
+    // Finalized segments are typed once; only the trailing one is revised
+    let (result_tx, result_rx) = mpsc::channel(16);
+    for response in responses {
+        result_tx.send(response).await.unwrap();
+    }
+    drop(result_tx);
+
+    let buffer = TextBuffer::new();
+    let mut controller = VoiceController::new(
+        Arc::new(AsrClient::new(test_credentials())),
+        Arc::new(AudioCapture::new().unwrap()),
+        Arc::new(TextInserter::with_action(Box::new(buffer.clone()))),
+    );
+    controller.start_replay(result_rx).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while controller.is_recording() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(buffer.text(), "今天天气。不错。");
+}
+
+#[tokio::test]
+async fn resent_segments_without_an_index_are_typed_once() {
+    let segment = |text: &str, is_final: bool| {
+        serde_json::json!({
+            "text": text,
+            "is_interim": !is_final,
+            "is_vad_finished": is_final
+        })
+    };
+    let payload = |segments: Vec<serde_json::Value>| {
+        serde_json::json!({ "results": segments, "extra": {} }).to_string()
+    };
+    let server = MockAsrServer::start(MockScript {
+        results: vec![
+            ScriptedResult::raw(2, payload(vec![segment("今天", false)])),
+            ScriptedResult::raw(
+                4,
+                payload(vec![segment("今天天气。", true), segment("不", false)]),
+            ),
+            // Resent with the next segment finalized
+            ScriptedResult::raw(
+                6,
+                payload(vec![segment("今天天气。", true), segment("不错。", true)]),
+            ),
+            // A new list: the same sentence said again is typed again
+            ScriptedResult::raw(8, payload(vec![segment("好", false)])),
+            ScriptedResult::raw(10, payload(vec![segment("好的。", true)])),
+            ScriptedResult::raw(12, payload(vec![segment("好", false)])),
+            ScriptedResult::raw(14, payload(vec![segment("好的。", true)])),
+        ],
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    let client = AsrClient::new(test_credentials()).with_ws_url(server.url());
+
+    let responses = collect(client.start_realtime(audio_frames(16)).await.unwrap()).await;
+    assert!(responses
+        .iter()
+        .all(|r| r.segments.iter().all(|s| s.index.is_none())));
+    let (result_tx, result_rx) = mpsc::channel(16);
+    for response in responses {
+        result_tx.send(response).await.unwrap();
+    }
+    drop(result_tx);
+
+    let buffer = TextBuffer::new();
+    let mut controller = VoiceController::new(
+        Arc::new(AsrClient::new(test_credentials())),
+        Arc::new(AudioCapture::new().unwrap()),
+        Arc::new(TextInserter::with_action(Box::new(buffer.clone()))),
+    );
+    controller.start_replay(result_rx).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while controller.is_recording() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(buffer.text(), "今天天气。不错。好的。好的。");
+}
+
+/// Feed `frames` dummy Opus frames at `interval`, like a live microphone
+fn paced_audio_frames(frames: usize, interval: Duration) -> mpsc::Receiver<Vec<u8>> {
+    let (tx, rx) = mpsc::channel(frames + 1);
+    tokio::spawn(async move {
+        for i in 0..frames {
+            tokio::time::sleep(interval).await;
+            if tx.send(vec![i as u8; 40]).await.is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+#[tokio::test]
+async fn continuous_mode_rolls_over_at_sentence_boundaries() {
+    let sentence = |after_frames: u32, text: &str| {
+        ScriptedResult::raw(
+            after_frames,
+            serde_json::json!({
+                "results": [{
+                    "text": text,
+                    "index": 0,
+                    "is_interim": false,
+                    "is_vad_finished": true,
+                    "extra": { "nonstream_result": true }
+                }],
+                "extra": {}
+            })
+            .to_string(),
+        )
+    };
+    let server = MockAsrServer::start_with_scripts(vec![
+        MockScript {
+            results: vec![sentence(10, "第一句。")],
+            ..Default::default()
+        },
+        MockScript {
+            results: vec![sentence(5, "第二句。")],
+            ..Default::default()
+        },
+        MockScript::default(),
+    ])
+    .await
+    .unwrap();
+    // Roll over at every sentence boundary
+    let client = AsrClient::new(test_credentials())
+        .with_ws_url(server.url())
+        .with_rollover(Duration::ZERO);
+
+    let rx = client
+        .start_realtime(paced_audio_frames(40, Duration::from_millis(5)))
+        .await
+        .unwrap();
+    let responses = collect(rx).await;
+    let summary: Vec<_> = responses
+        .iter()
+        .map(|r| (r.response_type.clone(), r.text.as_str()))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (ResponseType::FinalResult, "第一句。"),
+            (ResponseType::FinalResult, "第二句。"),
+            (ResponseType::SessionFinished, ""),
+        ]
+    );
+    // Segment numbering continues across server sessions
+    assert_eq!(responses[1].segments[0].index, Some(1));
+    let stats = responses[2].stats.clone().unwrap();
+    assert_eq!(stats.rollovers, 2);
+    assert_eq!(stats.frames_sent, 40);
+
+    // Every frame reached a session, and each retired session was finished
+    wait_for(|| {
+        server
+            .received()
+            .iter()
+            .filter(|r| r.method_name == "FinishSession")
+            .count()
+            == 3
+    })
+    .await;
+    let received = server.received();
+    let mut timestamps: Vec<u64> = received
+        .iter()
+        .filter(|r| r.method_name == "TaskRequest" && r.frame_state != 9)
+        .map(|r| {
+            let payload: serde_json::Value = serde_json::from_str(&r.payload).unwrap();
+            payload["timestamp_ms"].as_u64().unwrap()
+        })
+        .collect();
+    timestamps.sort_unstable();
+    timestamps.dedup();
+    assert_eq!(timestamps.len(), 40);
+    for connection in 1..3 {
+        let first = received
+            .iter()
+            .find(|r| r.connection == connection && r.method_name == "TaskRequest")
+            .unwrap();
+        assert_eq!(first.frame_state, 1);
+    }
+
+    // The consumer sees one uninterrupted transcript
+    let (result_tx, result_rx) = mpsc::channel(16);
+    for response in responses {
+        result_tx.send(response).await.unwrap();
+    }
+    drop(result_tx);
+
+    let buffer = TextBuffer::new();
+    let mut controller = VoiceController::new(
+        Arc::new(AsrClient::new(test_credentials())),
+        Arc::new(AudioCapture::new().unwrap()),
+        Arc::new(TextInserter::with_action(Box::new(buffer.clone()))),
+    );
+    controller.start_replay(result_rx).unwrap();
+    wait_for(|| !controller.is_recording()).await;
+    assert_eq!(buffer.text(), "第一句。第二句。");
+}
+
+/// Poll `condition` until it holds (or fail after 5s)
+async fn wait_for(mut condition: impl FnMut() -> bool) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !condition() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("condition not reached in time");
+}
+
+/// Final result for one segment timed `start..end` ms (of its server
+/// session), with a word at each end
+fn timed_sentence(after_frames: u32, text: &str, start: u64, end: u64) -> ScriptedResult {
+    ScriptedResult::raw(
+        after_frames,
+        serde_json::json!({
+            "results": [{
+                "text": text,
+                "start_time": start,
+                "end_time": end,
+                "is_interim": false,
+                "is_vad_finished": true,
+                "words": [
+                    { "word": "第", "start_time": start, "end_time": start + 20 },
+                    { "word": "。", "start_time": end - 20, "end_time": end }
+                ]
+            }],
+            "extra": {}
+        })
+        .to_string(),
+    )
+}
+
+/// (start, end) of each final segment and of its words
+fn segment_times(responses: &[AsrResponse]) -> Vec<(Option<u64>, Option<u64>)> {
+    responses
+        .iter()
+        .filter(|r| r.response_type == ResponseType::FinalResult)
+        .flat_map(|r| &r.segments)
+        .flat_map(|s| {
+            std::iter::once((s.start_time, s.end_time))
+                .chain(s.words.iter().map(|w| (w.start_time, w.end_time)))
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn segment_times_continue_across_rollovers() {
+    let server = MockAsrServer::start_with_scripts(vec![
+        MockScript {
+            results: vec![timed_sentence(10, "第一句。", 0, 200)],
+            ..Default::default()
+        },
+        // The rollover replays from the end of the first sentence
+        MockScript {
+            results: vec![timed_sentence(5, "第二句。", 20, 100)],
+            ..Default::default()
+        },
+        MockScript::default(),
+    ])
+    .await
+    .unwrap();
+    let client = AsrClient::new(test_credentials())
+        .with_ws_url(server.url())
+        .with_rollover(Duration::ZERO);
+
+    let rx = client
+        .start_realtime(paced_audio_frames(30, Duration::from_millis(5)))
+        .await
+        .unwrap();
+    let responses = collect(rx).await;
+    assert_eq!(
+        segment_times(&responses),
+        [
+            (Some(0), Some(200)),
+            (Some(0), Some(20)),
+            (Some(180), Some(200)),
+            (Some(220), Some(300)),
+            (Some(220), Some(240)),
+            (Some(280), Some(300)),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn segment_times_continue_across_reconnects() {
+    let server = MockAsrServer::start_with_scripts(vec![
+        MockScript {
+            results: vec![timed_sentence(10, "第一句。", 0, 200)],
+            drop_after_frames: Some(14),
+            ..Default::default()
+        },
+        MockScript {
+            results: vec![timed_sentence(8, "第二句。", 40, 120)],
+            ..Default::default()
+        },
+    ])
+    .await
+    .unwrap();
+    let client = AsrClient::new(test_credentials()).with_ws_url(server.url());
+
+    let rx = client
+        .start_realtime(paced_audio_frames(30, Duration::from_millis(5)))
+        .await
+        .unwrap();
+    let responses = collect(rx).await;
+    assert_eq!(
+        segment_times(&responses),
+        [
+            (Some(0), Some(200)),
+            (Some(0), Some(20)),
+            (Some(180), Some(200)),
+            (Some(240), Some(320)),
+            (Some(240), Some(260)),
+            (Some(300), Some(320)),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn warm_pool_hands_out_handshaken_session() {
+    let server = MockAsrServer::start(MockScript {
+        latency: Duration::from_millis(50),
+        ..MockScript::recognize("你好世界")
+    })
+    .await
+    .unwrap();
+    let client = AsrClient::new(test_credentials())
+        .with_ws_url(server.url())
+        .with_warm_pool(Duration::from_secs(30));
+    client.prewarm();
+    wait_for(|| client.latency_metrics().background_handshake.count == 1).await;
+
+    let responses = collect(client.start_realtime(audio_frames(20)).await.unwrap()).await;
+    let final_text = |responses: &[AsrResponse]| {
+        responses
+            .iter()
+            .find(|r| r.response_type == ResponseType::FinalResult)
+            .map(|r| r.text.clone())
+    };
+    assert_eq!(final_text(&responses).as_deref(), Some("你好世界"));
+
+    // Audio went out on the pre-warmed connection without a new handshake
+    let received = server.received();
+    assert!(received
+        .iter()
+        .filter(|r| r.method_name == "TaskRequest")
+        .all(|r| r.connection == 0));
+    let metrics = client.latency_metrics();
+    assert_eq!((metrics.warm.count, metrics.cold.count), (1, 0));
+    assert!(metrics.warm.last_ms < metrics.background_handshake.last_ms);
+
+    // A replacement is warmed in the background
+    wait_for(|| client.latency_metrics().background_handshake.count == 2).await;
+    assert_eq!(server.connection_count(), 2);
+
+    // A session needing other options does not use the warm one
+    let options = SessionOptions {
+        enable_punctuation: false,
+        ..Default::default()
+    };
+    let responses = collect(
+        client
+            .start_realtime_with_options(audio_frames(20), options)
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(final_text(&responses).as_deref(), Some("你好世界"));
+    assert_eq!(client.latency_metrics().cold.count, 1);
+}
+
+#[tokio::test]
+async fn warm_pool_recycles_idle_sessions() {
+    let server = MockAsrServer::start(MockScript::default()).await.unwrap();
+    let client = AsrClient::new(test_credentials())
+        .with_ws_url(server.url())
+        .with_warm_pool(Duration::from_millis(100));
+    client.prewarm();
+
+    wait_for(|| client.latency_metrics().background_handshake.count >= 3).await;
+    assert!(server.connection_count() >= 3);
+
+    // Disabling the pool closes the idle session and stops warming
+    client.set_warm_pool(None);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let connections = server.connection_count();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(server.connection_count(), connections);
+}
+
+#[tokio::test]
+async fn start_task_failure_is_reported() {
+    let server = MockAsrServer::start(MockScript {
+        start_task: MockReply::Fail {
+            status_code: 40100001,
+            message: "invalid token".to_string(),
+        },
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    let client = AsrClient::new(test_credentials()).with_ws_url(server.url());
+
+    let err = client.start_realtime(audio_frames(1)).await.unwrap_err();
+    assert!(err.is_auth());
+    assert!(err.to_string().contains("invalid token"));
+}
+
+#[tokio::test]
+async fn session_rejection_is_not_an_auth_error() {
+    let server = MockAsrServer::start(MockScript {
+        start_session: MockReply::Fail {
+            status_code: 45000001,
+            message: "unsupported audio format".to_string(),
+        },
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    let client = AsrClient::new(test_credentials()).with_ws_url(server.url());
+
+    let err = client.start_realtime(audio_frames(1)).await.unwrap_err();
+    assert!(matches!(
+        err,
+        AsrError::Rejected {
+            status_code: 45000001,
+            ..
+        }
+    ));
+}
+
+#[tokio::test]
+async fn heartbeats_and_latency_are_transparent() {
+    let server = MockAsrServer::start(MockScript {
+        latency: Duration::from_millis(5),
+        heartbeat_every: Some(3),
+        ..MockScript::recognize("测试")
+    })
+    .await
+    .unwrap();
+    let client = AsrClient::new(test_credentials()).with_ws_url(server.url());
+
+    let rx = client.start_realtime(audio_frames(12)).await.unwrap();
+    let responses = collect(rx).await;
+
+    assert!(responses
+        .iter()
+        .all(|r| r.response_type != ResponseType::Heartbeat));
+    assert_eq!(
+        responses.last().map(|r| r.response_type.clone()),
+        Some(ResponseType::SessionFinished)
+    );
+}
+
+#[tokio::test]
+async fn reconnects_and_replays_after_drop() {
+    let server = MockAsrServer::start_with_scripts(vec![
+        MockScript {
+            drop_after_frames: Some(8),
+            ..MockScript::recognize("你好世界")
+        },
+        MockScript::recognize("你好世界"),
+    ])
+    .await
+    .unwrap();
+    let client = AsrClient::new(test_credentials()).with_ws_url(server.url());
+
+    let rx = client.start_realtime(audio_frames(20)).await.unwrap();
+    let responses = collect(rx).await;
+
+    assert_eq!(server.connection_count(), 2);
+    assert!(responses
+        .iter()
+        .all(|r| r.response_type != ResponseType::Error));
+    let finals: Vec<_> = responses.iter().filter(|r| r.is_final).collect();
+    assert_eq!(finals.len(), 1);
+    assert_eq!(finals[0].text, "你好世界");
+    assert_eq!(
+        responses.last().map(|r| r.response_type.clone()),
+        Some(ResponseType::SessionFinished)
+    );
+
+    // Nothing was finalized before the drop, so every frame is replayed
+    let replayed: Vec<_> = server
+        .received()
+        .into_iter()
+        .filter(|r| r.connection == 1 && r.method_name == "TaskRequest")
+        .collect();
+    assert_eq!(replayed.len(), 21);
+    assert_eq!(replayed[0].frame_state, 1);
+    assert_eq!(replayed[20].frame_state, 9);
+}
+
+#[tokio::test]
+async fn audio_after_a_final_result_is_replayed_after_drop() {
+    // The first sentence ends at 200ms (frame 10); the connection drops
+    // once the next sentence has started
+    let first = ScriptedResult::raw(
+        12,
+        serde_json::json!({
+            "results": [{
+                "text": "第一句。",
+                "index": 0,
+                "start_time": 0,
+                "end_time": 200,
+                "is_interim": false,
+                "is_vad_finished": true
+            }],
+            "extra": {}
+        })
+        .to_string(),
+    );
+    let server = MockAsrServer::start_with_scripts(vec![
+        MockScript {
+            results: vec![first],
+            drop_after_frames: Some(16),
+            ..Default::default()
+        },
+        MockScript {
+            results: vec![ScriptedResult::final_result(3, "第二句。")],
+            ..Default::default()
+        },
+    ])
+    .await
+    .unwrap();
+    let client = AsrClient::new(test_credentials()).with_ws_url(server.url());
+
+    let rx = client
+        .start_realtime(paced_audio_frames(30, Duration::from_millis(5)))
+        .await
+        .unwrap();
+    let responses = collect(rx).await;
+    let finals: Vec<_> = responses
+        .iter()
+        .filter(|r| r.is_final)
+        .map(|r| r.text.as_str())
+        .collect();
+    assert_eq!(finals, ["第一句。", "第二句。"]);
+
+    // Everything after the end of the first sentence is replayed, not just
+    // what was sent after its result arrived
+    let received = server.received();
+    let replayed: Vec<_> = received
+        .iter()
+        .filter(|r| r.connection == 1 && r.method_name == "TaskRequest" && r.frame_state != 9)
+        .collect();
+    assert_eq!(replayed.len(), 20);
+    let first_timestamp = |connection: usize| {
+        let request = received
+            .iter()
+            .find(|r| r.connection == connection && r.method_name == "TaskRequest")
+            .unwrap();
+        let payload: serde_json::Value = serde_json::from_str(&request.payload).unwrap();
+        payload["timestamp_ms"].as_u64().unwrap()
+    };
+    assert_eq!(first_timestamp(1) - first_timestamp(0), 10 * 20);
+}
+
+/// Minimal stand-in for the settings API that always hands out `token`
+async fn settings_server(token: &str) -> String {
+    logged_settings_server(token).await.0
+}
+
+/// Like `settings_server`, also keeping the head of every request
+async fn logged_settings_server(token: &str) -> (String, RequestLog) {
+    let log = RequestLog::default();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let body = serde_json::json!({
+        "data": { "settings": { "asr_config": { "app_key": token } } },
+        "message": "success"
+    })
+    .to_string();
+
+    let requests = log.clone();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut buf = [0u8; 4096];
+            let n = stream.read(&mut buf).await.unwrap_or(0);
+            requests
+                .lock()
+                .unwrap()
+                .push(String::from_utf8_lossy(&buf[..n]).into_owned());
+            let reply = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let _ = stream.write_all(reply.as_bytes()).await;
+        }
+    });
+
+    (format!("http://{}/service/settings/v3/", addr), log)
+}
+
+type RequestLog = Arc<std::sync::Mutex<Vec<String>>>;
+
+#[tokio::test]
+async fn auth_failure_refreshes_token_and_retries() {
+    let server = MockAsrServer::start_with_scripts(vec![
+        MockScript {
+            start_task: MockReply::Fail {
+                status_code: 40100001,
+                message: "token expired".to_string(),
+            },
+            ..Default::default()
+        },
+        MockScript::recognize("你好"),
+    ])
+    .await
+    .unwrap();
+
+    let credentials_path =
+        std::env::temp_dir().join(format!("doubao-credentials-{}.json", uuid::Uuid::new_v4()));
+    let endpoints = Endpoints {
+        settings_url: settings_server("fresh-token").await,
+        websocket_url: server.url(),
+        ..Default::default()
+    };
+    let store = Arc::new(
+        CredentialStore::with_path(credentials_path.clone(), Some(test_credentials()))
+            .with_endpoints(endpoints.clone()),
+    );
+    let client = AsrClient::new(test_credentials())
+        .with_endpoints(endpoints)
+        .with_credential_store(store);
+
+    let rx = client.start_realtime(audio_frames(12)).await.unwrap();
+    let responses = collect(rx).await;
+    assert_eq!(
+        responses.last().map(|r| r.response_type.clone()),
+        Some(ResponseType::SessionFinished)
+    );
+
+    let starts: Vec<_> = server
+        .received()
+        .into_iter()
+        .filter(|r| r.method_name == "StartTask")
+        .collect();
+    assert_eq!(starts.len(), 2);
+    assert_eq!(starts[0].token, "test-token");
+    assert_eq!(starts[1].token, "fresh-token");
+
+    // The refreshed token is kept for later sessions and persisted
+    assert_eq!(client.credentials().token, "fresh-token");
+    let saved = DeviceCredentials::load(&credentials_path).unwrap();
+    assert_eq!(saved.token, "fresh-token");
+    assert_eq!(saved.device_id, "1234567890");
+    let _ = std::fs::remove_file(credentials_path);
+}
+
+#[tokio::test]
+async fn switching_device_profile_renews_token_under_new_identity() {
+    let (settings_url, requests) = logged_settings_server("profile-token").await;
+    let credentials_path =
+        std::env::temp_dir().join(format!("doubao-credentials-{}.json", uuid::Uuid::new_v4()));
+    let profile = DeviceProfile {
+        name: "custom".to_string(),
+        version_code: 42,
+        user_agent: "custom-agent/42".to_string(),
+        ..Default::default()
+    };
+    let store = CredentialStore::with_path(credentials_path.clone(), Some(test_credentials()))
+        .with_endpoints(Endpoints {
+            settings_url,
+            ..Default::default()
+        })
+        .with_profile(profile.clone());
+
+    assert_eq!(
+        store.validate_credentials().await.unwrap(),
+        CredentialStatus::Refreshed
+    );
+    let head = requests.lock().unwrap()[0].to_lowercase();
+    assert!(head.contains("version_code=42"));
+    assert!(head.contains("user-agent: custom-agent/42"));
+
+    // The profile is stored with the credentials it was used for
+    let saved = DeviceCredentials::load(&credentials_path).unwrap();
+    assert_eq!(saved.profile, profile);
+    assert_eq!(saved.token, "profile-token");
+    assert!(!store.set_profile(profile));
+    let _ = std::fs::remove_file(credentials_path);
+
+    // Credentials saved before profiles existed use the built-in one
+    let mut legacy = serde_json::to_value(test_credentials()).unwrap();
+    legacy.as_object_mut().unwrap().remove("profile");
+    let legacy: DeviceCredentials = serde_json::from_value(legacy).unwrap();
+    assert_eq!(legacy.profile, DeviceProfile::default());
+}
+
+#[tokio::test]
+async fn recorded_session_replays_offline() {
+    let server = MockAsrServer::start(MockScript::recognize("你好世界"))
+        .await
+        .unwrap();
+    let captures_dir =
+        std::env::temp_dir().join(format!("doubao-captures-{}", uuid::Uuid::new_v4()));
+    let client = AsrClient::new(test_credentials())
+        .with_ws_url(server.url())
+        .with_recording_dir(&captures_dir);
+
+    let live = collect(client.start_realtime(audio_frames(20)).await.unwrap()).await;
+
+    let capture_file = std::fs::read_dir(&captures_dir)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let frames = load_capture(&capture_file).unwrap();
+    let kinds: Vec<_> = frames
+        .iter()
+        .map(|f| (f.dir, f.kind.as_str()))
+        .take(4)
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            (Direction::Out, "StartTask"),
+            (Direction::In, "TaskStarted"),
+            (Direction::Out, "StartSession"),
+            (Direction::In, "SessionStarted"),
+        ]
+    );
+    assert_eq!(
+        frames.iter().filter(|f| f.dir == Direction::Out).count(),
+        server.received().len()
+    );
+    assert!(frames.windows(2).all(|w| w[0].t_ms <= w[1].t_ms));
+
+    // Tokens are not written to disk
+    for frame in frames.iter().filter(|f| f.dir == Direction::Out) {
+        let request = AsrRequest::decode(frame.bytes().unwrap().as_slice()).unwrap();
+        assert!(request.token.is_empty());
+    }
+
+    // The replayed stream matches what the live consumer saw
+    let replayed = collect(replay_capture(frames.clone(), false).unwrap()).await;
+    let summary = |responses: &[AsrResponse]| -> Vec<_> {
+        responses
+            .iter()
+            .map(|r| (r.response_type.clone(), r.text.clone()))
+            .collect()
+    };
+    assert_eq!(summary(&replayed), summary(&live));
+
+    // And drives the voice controller to the same text
+    let buffer = TextBuffer::new();
+    let mut controller = VoiceController::new(
+        Arc::new(AsrClient::new(test_credentials())),
+        Arc::new(AudioCapture::new().unwrap()),
+        Arc::new(TextInserter::with_action(Box::new(buffer.clone()))),
+    );
+    controller
+        .start_replay(replay_capture(frames, false).unwrap())
+        .unwrap();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while controller.is_recording() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(buffer.text(), "你好世界");
+
+    let _ = std::fs::remove_dir_all(captures_dir);
+}
+
+type ProxyLog = Arc<std::sync::Mutex<Vec<String>>>;
+
+/// Minimal HTTP proxy: tunnels `CONNECT` and forwards absolute-form requests,
+/// logging each request head
+async fn http_proxy() -> (String, ProxyLog) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let log = ProxyLog::default();
+
+    let log_clone = log.clone();
+    tokio::spawn(async move {
+        while let Ok((mut client, _)) = listener.accept().await {
+            let log = log_clone.clone();
+            tokio::spawn(async move {
+                let mut head = Vec::new();
+                while !head.ends_with(b"\r\n\r\n") {
+                    let mut byte = [0u8; 1];
+                    if client.read(&mut byte).await.unwrap_or(0) == 0 {
+                        return;
+                    }
+                    head.push(byte[0]);
+                }
+                let head_text = String::from_utf8_lossy(&head).to_string();
+                log.lock().unwrap().push(head_text.clone());
+
+                let mut parts = head_text.split_whitespace();
+                let method = parts.next().unwrap_or_default().to_string();
+                let target = parts.next().unwrap_or_default().to_string();
+                let authority = target
+                    .trim_start_matches("http://")
+                    .split('/')
+                    .next()
+                    .unwrap_or_default()
+                    .to_string();
+                let Ok(mut upstream) = TcpStream::connect(&authority).await else {
+                    return;
+                };
+                if method == "CONNECT" {
+                    let _ = client
+                        .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+                        .await;
+                } else {
+                    let _ = upstream.write_all(&head).await;
+                }
+                let _ = tokio::io::copy_bidirectional(&mut client, &mut upstream).await;
+            });
+        }
+    });
+
+    (format!("http://{}", addr), log)
+}
+
+/// Minimal SOCKS5 proxy (no auth), logging each `host:port` target
+async fn socks5_proxy() -> (String, ProxyLog) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let log = ProxyLog::default();
+
+    let log_clone = log.clone();
+    tokio::spawn(async move {
+        while let Ok((mut client, _)) = listener.accept().await {
+            let log = log_clone.clone();
+            tokio::spawn(async move {
+                let mut greeting = [0u8; 2];
+                client.read_exact(&mut greeting).await.unwrap();
+                let mut methods = vec![0u8; greeting[1] as usize];
+                client.read_exact(&mut methods).await.unwrap();
+                client.write_all(&[5, 0]).await.unwrap();
+
+                let mut request = [0u8; 4];
+                client.read_exact(&mut request).await.unwrap();
+                let host = match request[3] {
+                    1 => {
+                        let mut ip = [0u8; 4];
+                        client.read_exact(&mut ip).await.unwrap();
+                        std::net::Ipv4Addr::from(ip).to_string()
+                    }
+                    3 => {
+                        let len = client.read_u8().await.unwrap();
+                        let mut name = vec![0u8; len as usize];
+                        client.read_exact(&mut name).await.unwrap();
+                        String::from_utf8(name).unwrap()
+                    }
+                    other => panic!("unexpected address type {}", other),
+                };
+                let port = client.read_u16().await.unwrap();
+                let target = format!("{}:{}", host, port);
+                log.lock().unwrap().push(target.clone());
+
+                let mut upstream = TcpStream::connect(&target).await.unwrap();
+                client
+                    .write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0])
+                    .await
+                    .unwrap();
+                let _ = tokio::io::copy_bidirectional(&mut client, &mut upstream).await;
+            });
+        }
+    });
+
+    (format!("socks5h://{}", addr), log)
+}
+
+#[tokio::test]
+async fn websocket_and_token_requests_use_http_proxy() {
+    let server = MockAsrServer::start(MockScript::recognize("你好"))
+        .await
+        .unwrap();
+    let (proxy_url, proxy_log) = http_proxy().await;
+    let proxy = ProxySettings::new(
+        Proxy::parse(&proxy_url)
+            .unwrap()
+            .with_auth("user", "secret"),
+    );
+
+    let client = AsrClient::new(test_credentials())
+        .with_ws_url(server.url())
+        .with_proxy(proxy.clone());
+    let responses = collect(client.start_realtime(audio_frames(12)).await.unwrap()).await;
+    assert!(responses
+        .iter()
+        .any(|r| r.response_type == ResponseType::FinalResult && r.text == "你好"));
+
+    let connect = proxy_log.lock().unwrap()[0].clone();
+    assert!(connect.starts_with(&format!("CONNECT {} HTTP/1.1", server.addr())));
+    assert!(connect.contains("Proxy-Authorization: Basic dXNlcjpzZWNyZXQ="));
+
+    // Token requests take the same route
+    let credentials_path =
+        std::env::temp_dir().join(format!("doubao-credentials-{}.json", uuid::Uuid::new_v4()));
+    let settings_url = settings_server("proxied-token").await;
+    let store = CredentialStore::with_path(credentials_path.clone(), Some(test_credentials()))
+        .with_endpoints(Endpoints {
+            settings_url: settings_url.clone(),
+            ..Default::default()
+        })
+        .with_proxy(proxy);
+    assert_eq!(
+        store.validate_credentials().await.unwrap(),
+        CredentialStatus::Refreshed
+    );
+    assert_eq!(store.current().await.unwrap().token, "proxied-token");
+    assert!(proxy_log
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|head| head.contains(&settings_url)));
+    let _ = std::fs::remove_file(credentials_path);
+}
+
+#[tokio::test]
+async fn websocket_uses_socks5_proxy() {
+    let server = MockAsrServer::start(MockScript::recognize("你好"))
+        .await
+        .unwrap();
+    let (proxy_url, proxy_log) = socks5_proxy().await;
+    let client = AsrClient::new(test_credentials())
+        .with_ws_url(server.url())
+        .with_proxy(ProxySettings::new(Proxy::parse(&proxy_url).unwrap()));
+
+    let responses = collect(client.start_realtime(audio_frames(12)).await.unwrap()).await;
+    assert!(responses
+        .iter()
+        .any(|r| r.response_type == ResponseType::FinalResult));
+    assert_eq!(*proxy_log.lock().unwrap(), vec![server.addr().to_string()]);
+}
+
+#[tokio::test]
+async fn no_proxy_hosts_are_dialed_directly() {
+    let server = MockAsrServer::start(MockScript::recognize("你好"))
+        .await
+        .unwrap();
+    // Nothing listens on the proxy port; only a bypass can succeed
+    let proxy = ProxySettings::new(Proxy::parse("http://127.0.0.1:1").unwrap())
+        .with_no_proxy(["localhost", "127.0.0.1:9999"]);
+    let client = AsrClient::new(test_credentials())
+        .with_ws_url(server.url())
+        .with_proxy(proxy);
+    let responses = collect(client.start_realtime(audio_frames(12)).await.unwrap()).await;
+    assert!(responses
+        .iter()
+        .any(|r| r.response_type == ResponseType::FinalResult));
+
+    let proxy = ProxySettings::new(Proxy::parse("socks5://proxy.corp:1080").unwrap())
+        .with_no_proxy([".doubao.com"]);
+    assert!(proxy
+        .proxy_for("wss://frontier-audio-ime-ws.doubao.com/ocean/api/v1/ws")
+        .is_none());
+    assert!(proxy.proxy_for("wss://doubao.com/").is_none());
+    assert!(proxy.proxy_for("wss://notdoubao.com/").is_some());
+}
@@ -0,0 +1,131 @@
+//! Opus encoder settings: `[audio.opus]` parsing, the presets compared by
+//! `opus-bench`, and benchmarking a fixture.
+
+use std::time::Duration;
+
+use doubao_voice_input::audio::{
+    bench_opus, opus_bench_presets, read_fixture, speech_like_fixture, OpusApplication,
+    OpusEncoder, OpusSettings,
+};
+use doubao_voice_input::AppConfig;
+
+#[test]
+fn opus_config_maps_to_encoder_settings() {
+    let config: AppConfig = toml::from_str(
+        r#"
+        [audio.opus]
+        application = "VoIP"
+        bitrate_kbps = 16
+        vbr = false
+        complexity = 12
+        fec = true
+        packet_loss_percent = 150
+        dtx = true
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        config.audio.opus.settings(),
+        OpusSettings {
+            application: OpusApplication::Voip,
+            bitrate: Some(16000),
+            vbr: false,
+            complexity: Some(10),
+            fec: true,
+            packet_loss_percent: 100,
+            dtx: true,
+        }
+    );
+
+    // Without the section the encoder keeps its own defaults
+    let config: AppConfig = toml::from_str("[audio]\nalways_warm = false\n").unwrap();
+    assert_eq!(config.audio.opus.settings(), OpusSettings::default());
+
+    let config: AppConfig = toml::from_str("[audio.opus]\napplication = \"music\"\n").unwrap();
+    assert_eq!(
+        config.audio.opus.settings().application,
+        OpusApplication::Audio
+    );
+}
+
+#[test]
+fn settings_describe_themselves() {
+    assert_eq!(OpusSettings::default().describe(), "audio auto");
+    let settings = OpusSettings {
+        application: OpusApplication::Voip,
+        bitrate: Some(24000),
+        vbr: false,
+        complexity: Some(5),
+        fec: true,
+        packet_loss_percent: 10,
+        dtx: true,
+    };
+    assert_eq!(settings.describe(), "voip 24kbps cbr c5 fec(10%) dtx");
+}
+
+#[test]
+fn presets_start_with_the_configured_settings() {
+    let configured = OpusSettings {
+        application: OpusApplication::Voip,
+        bitrate: Some(20000),
+        ..OpusSettings::default()
+    };
+    let presets = opus_bench_presets(&configured);
+    assert_eq!(presets[0], configured);
+    assert!(presets.contains(&OpusSettings::default()));
+    assert!(presets.iter().any(|p| p.dtx) && presets.iter().any(|p| p.fec));
+
+    // A configured preset is not listed twice
+    let presets = opus_bench_presets(&OpusSettings::default());
+    assert_eq!(presets[0], OpusSettings::default());
+    for (i, settings) in presets.iter().enumerate() {
+        assert!(!presets[i + 1..].contains(settings), "{:?}", settings);
+    }
+}
+
+#[test]
+fn every_preset_encodes_the_fixture() {
+    let pcm = speech_like_fixture(Duration::from_secs(2));
+    assert_eq!(pcm.len(), 32000);
+    // Speech, then a near-silent pause
+    assert!(pcm[..24000].iter().any(|s| s.unsigned_abs() > 3000));
+    assert!(pcm[24000..].iter().all(|s| s.unsigned_abs() < 100));
+
+    for settings in opus_bench_presets(&OpusSettings::default()) {
+        let result = bench_opus(&pcm, &settings).unwrap();
+        assert_eq!(result.packets, 100);
+        assert_eq!(result.audio, Duration::from_secs(2));
+        assert!(result.bytes > 0);
+        assert!((result.mb_per_hour() - result.kbps() * 0.45).abs() < 1e-9);
+        assert!(result.cpu_percent() >= 0.0);
+    }
+}
+
+#[test]
+fn fixtures_are_read_as_16khz_mono() {
+    let path = std::env::temp_dir().join(format!("opus-fixture-{}.wav", std::process::id()));
+    let spec = hound::WavSpec {
+        channels: 2,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+    for i in 0..44100 * 2 {
+        writer.write_sample((i % 300) as i16).unwrap();
+    }
+    writer.finalize().unwrap();
+
+    let pcm = read_fixture(&path).unwrap();
+    let frame = OpusEncoder::new(16000, 1).unwrap().frame_size();
+    assert_eq!(pcm.len() % frame, 0);
+    // One second, give or take the resampler's latency
+    assert!((15000..=16640).contains(&pcm.len()), "{}", pcm.len());
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn invalid_channel_counts_are_rejected_with_settings() {
+    assert!(OpusEncoder::with_settings(16000, 3, &OpusSettings::default()).is_err());
+}
@@ -0,0 +1,103 @@
+//! Resampling to 16kHz measured on synthetic tones: the passband keeps its
+//! level, tones above 8kHz do not alias down into it, and non-integer
+//! ratios do not drift.
+
+use std::f64::consts::TAU;
+
+use doubao_voice_input::audio::FrameResampler;
+
+const AMPLITUDE: f64 = 0.5;
+
+/// Resample `seconds` of a sine tone in 20ms chunks, as the capture
+/// pipeline does
+fn resample_tone(rate: u32, freq: f64, seconds: u32) -> Vec<f64> {
+    let chunk = rate as usize / 50;
+    let mut resampler = FrameResampler::new(rate, chunk);
+    let mut input = vec![0.0f32; chunk];
+    let mut frame = [0i16; 320];
+    let mut output = Vec::new();
+    for c in 0..seconds as usize * 50 {
+        for (i, s) in input.iter_mut().enumerate() {
+            let t = (c * chunk + i) as f64 / rate as f64;
+            *s = (AMPLITUDE * (TAU * freq * t).sin()) as f32;
+        }
+        resampler.push(&input);
+        while resampler.pop(&mut frame) {
+            output.extend(frame.iter().map(|&s| s as f64 / 32767.0));
+        }
+    }
+    output
+}
+
+/// Amplitude and phase of `freq` in a 16kHz signal
+fn tone(signal: &[f64], freq: f64) -> (f64, f64) {
+    let (re, im) = signal
+        .iter()
+        .enumerate()
+        .fold((0.0, 0.0), |(re, im), (n, &s)| {
+            let w = TAU * freq * n as f64 / 16000.0;
+            (re + s * w.cos(), im - s * w.sin())
+        });
+    let n = signal.len() as f64;
+    ((re * re + im * im).sqrt() * 2.0 / n, im.atan2(re))
+}
+
+fn rms(signal: &[f64]) -> f64 {
+    (signal.iter().map(|s| s * s).sum::<f64>() / signal.len() as f64).sqrt()
+}
+
+/// Skip the filter's start-up transient
+fn steady(signal: &[f64]) -> &[f64] {
+    &signal[1600..]
+}
+
+#[test]
+fn passband_tones_keep_their_level() {
+    for rate in [48000, 44100, 32000, 22050] {
+        for freq in [300.0, 1000.0, 3400.0] {
+            let output = resample_tone(rate, freq, 1);
+            let (amplitude, _) = tone(steady(&output), freq);
+            assert!(
+                (amplitude - AMPLITUDE).abs() < 0.01,
+                "{}Hz at {}Hz: amplitude {}",
+                freq,
+                rate,
+                amplitude
+            );
+        }
+    }
+}
+
+#[test]
+fn tones_above_8khz_do_not_alias() {
+    // Nearest-neighbour decimation would fold these to 4kHz, 6.1kHz and
+    // 5kHz at full level
+    for (rate, freq) in [(48000, 12000.0), (44100, 10000.0), (44100, 11000.0)] {
+        let output = resample_tone(rate, freq, 1);
+        let residual = rms(steady(&output)) / (AMPLITUDE / 2f64.sqrt());
+        let db = 20.0 * residual.log10();
+        assert!(
+            db < -60.0,
+            "{}Hz at {}Hz aliased at {:.1}dB",
+            freq,
+            rate,
+            db
+        );
+    }
+}
+
+#[test]
+fn non_integer_ratio_does_not_drift() {
+    // 44.1kHz → 16kHz is 441:160; one sample of drift would shift the
+    // phase of a 1kHz tone by 22.5°
+    let output = resample_tone(44100, 1000.0, 60);
+    assert!(output.len() >= 59 * 16000);
+
+    let (_, start) = tone(&output[16000..32000], 1000.0);
+    let (_, end) = tone(&output[output.len() - 16000..], 1000.0);
+    // Both windows start a whole number of 1kHz periods after the first
+    // sample, so the phase should be identical
+    let shift = (end - start).rem_euclid(TAU);
+    let shift = shift.min(TAU - shift).to_degrees();
+    assert!(shift < 1.0, "phase drifted by {:.2}°", shift);
+}
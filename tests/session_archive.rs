@@ -0,0 +1,199 @@
+//! Session archive: the Ogg Opus container, sidecars, retention, and a
+//! dictation session archived end to end.
+
+mod common;
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use common::{now_ms, temp_dir, test_credentials};
+use doubao_voice_input::asr::mock_server::{MockAsrServer, MockScript};
+use doubao_voice_input::asr::Language;
+use doubao_voice_input::audio::{AudioFileReader, OggOpusWriter, SyntheticSource};
+use doubao_voice_input::business::{
+    ArchiveRetention, SessionArchive, TextBuffer, TranscriptSegment,
+};
+use doubao_voice_input::{AsrClient, AudioCapture, TextInserter, VoiceController};
+
+fn read_json(path: &Path) -> serde_json::Value {
+    serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+}
+
+#[test]
+fn ogg_opus_files_carry_headers_granules_and_an_end() {
+    let dir = temp_dir("ogg");
+    let path = dir.join("test.opus");
+    let mut writer = OggOpusWriter::create(&path, 16000, 1, 20).unwrap();
+    for i in 0..120u8 {
+        writer.write_packet(&[i; 30]).unwrap();
+    }
+    assert_eq!(writer.packets(), 120);
+    assert_eq!(writer.finish().unwrap(), 120);
+
+    let mut packets = ogg::PacketReader::new(std::fs::File::open(&path).unwrap());
+    let head = packets.read_packet_expected().unwrap();
+    assert_eq!(&head.data[..8], b"OpusHead");
+    assert_eq!(head.data[9], 1, "channels");
+    assert_eq!(
+        u32::from_le_bytes(head.data[12..16].try_into().unwrap()),
+        16000
+    );
+    let tags = packets.read_packet_expected().unwrap();
+    assert_eq!(&tags.data[..8], b"OpusTags");
+
+    let mut audio = Vec::new();
+    while let Some(packet) = packets.read_packet().unwrap() {
+        audio.push(packet);
+    }
+    assert_eq!(audio.len(), 120);
+    assert_eq!(audio[7].data, vec![7; 30]);
+    let last = audio.last().unwrap();
+    assert!(last.last_in_stream());
+    assert_eq!(last.absgp_page(), 120 * 960);
+
+    // Readable as an audio source, e.g. for re-transcription
+    let reader = AudioFileReader::open(&path).unwrap();
+    assert_eq!((reader.sample_rate(), reader.channels()), (48000, 1));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn sessions_are_archived_with_a_sidecar() {
+    let dir = temp_dir("sidecar");
+    let archive = SessionArchive::new(&dir, ArchiveRetention::default());
+    let mut session = archive
+        .start(Some("Notes".to_string()), Language::ZhCn)
+        .unwrap();
+
+    let (tx, audio_rx) = mpsc::channel(16);
+    let mut rx = session.tee(audio_rx);
+    tokio::spawn(async move {
+        for i in 0..10u8 {
+            tx.send(vec![i; 20]).await.unwrap();
+        }
+    });
+    // Frames still reach the ASR client
+    let mut forwarded = 0;
+    while rx.recv().await.is_some() {
+        forwarded += 1;
+    }
+    assert_eq!(forwarded, 10);
+
+    let segments = vec![TranscriptSegment {
+        start_ms: 40,
+        end_ms: 180,
+        text: "你好。".to_string(),
+    }];
+    let sidecar = session
+        .finish("你好。".to_string(), segments)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let record = read_json(&sidecar);
+    assert_eq!(record["duration_ms"], 200);
+    assert_eq!(record["focused_app"], "Notes");
+    assert_eq!(record["language"], "zh-CN");
+    assert_eq!(record["transcript"], "你好。");
+    assert_eq!(record["segments"][0]["end_ms"], 180);
+    let audio = dir.join(record["audio_file"].as_str().unwrap());
+    assert_eq!(audio.with_extension("json"), sidecar);
+    assert!(audio.exists());
+
+    // Sessions without audio leave nothing behind
+    let empty = archive.start(None, Language::ZhCn).unwrap();
+    assert!(empty
+        .finish(String::new(), Vec::new())
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn retention_prunes_by_age_then_size() {
+    let dir = temp_dir("retention");
+    let day = 24 * 60 * 60 * 1000;
+    let now = now_ms();
+    // 40, 10, 2 and 1 days old; 300 bytes each
+    for started in [now - 40 * day, now - 10 * day, now - 2 * day, now - day] {
+        std::fs::write(dir.join(format!("session-{}.opus", started)), [0u8; 250]).unwrap();
+        std::fs::write(dir.join(format!("session-{}.json", started)), [0u8; 50]).unwrap();
+    }
+    std::fs::write(dir.join("notes.txt"), "kept").unwrap();
+
+    let remaining = |dir: &Path| std::fs::read_dir(dir).unwrap().count();
+    let by_age = SessionArchive::new(
+        &dir,
+        ArchiveRetention {
+            max_age: Some(Duration::from_secs(30 * 24 * 60 * 60)),
+            max_total_bytes: None,
+        },
+    );
+    assert_eq!(by_age.prune().unwrap(), 1);
+    assert_eq!(remaining(&dir), 7);
+
+    let by_size = SessionArchive::new(
+        &dir,
+        ArchiveRetention {
+            max_age: None,
+            max_total_bytes: Some(700),
+        },
+    );
+    assert_eq!(by_size.prune().unwrap(), 1);
+    assert!(!dir
+        .join(format!("session-{}.opus", now - 10 * day))
+        .exists());
+    assert!(dir.join(format!("session-{}.opus", now - 2 * day)).exists());
+    assert_eq!(by_size.prune().unwrap(), 0);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn dictation_sessions_are_archived_when_enabled() {
+    let server = MockAsrServer::start(MockScript::recognize("你好世界"))
+        .await
+        .unwrap();
+    let source = SyntheticSource::new(16000).tone(300.0, 0.3, Duration::from_millis(400));
+    let dir = temp_dir("dictation");
+    let buffer = TextBuffer::new();
+    let mut controller = VoiceController::new(
+        Arc::new(AsrClient::new(test_credentials()).with_ws_url(server.url())),
+        Arc::new(AudioCapture::new().unwrap().with_source(Arc::new(source))),
+        Arc::new(TextInserter::with_action(Box::new(buffer.clone()))),
+    );
+    controller.set_archive(Some(SessionArchive::new(&dir, ArchiveRetention::default())));
+    controller.start().await.unwrap();
+
+    // The sidecar is written once the session has ended
+    let sidecar = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let found = std::fs::read_dir(&dir)
+                .unwrap()
+                .flatten()
+                .map(|e| e.path())
+                .find(|p| p.extension().is_some_and(|ext| ext == "json"));
+            match found {
+                Some(path) => return path,
+                None => tokio::time::sleep(Duration::from_millis(20)).await,
+            }
+        }
+    })
+    .await
+    .unwrap();
+
+    let record = read_json(&sidecar);
+    assert_eq!(record["transcript"], buffer.text());
+    assert_eq!(record["transcript"], "你好世界");
+    let duration = record["duration_ms"].as_u64().unwrap();
+    assert!((340..=400).contains(&duration), "{}ms", duration);
+    assert!(dir.join(record["audio_file"].as_str().unwrap()).exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
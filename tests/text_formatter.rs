@@ -0,0 +1,32 @@
+//! Language-specific punctuation and number formatting.
+
+use doubao_voice_input::asr::Language;
+use doubao_voice_input::business::TextFormatter;
+
+#[test]
+fn chinese_uses_full_width_punctuation() {
+    let formatter = TextFormatter::new(Language::ZhCn);
+    assert_eq!(formatter.format("你好,今天是周五."), "你好，今天是周五。");
+    assert_eq!(formatter.format("共３.５元!"), "共3.5元！");
+    assert_eq!(formatter.format("10:30开会"), "10:30开会");
+    assert_eq!(formatter.format("用Tauri, 很方便"), "用Tauri, 很方便");
+}
+
+#[test]
+fn english_uses_ascii_punctuation_and_spacing() {
+    let formatter = TextFormatter::new(Language::EnUs);
+    assert_eq!(
+        formatter.format("Hello，world。How are you？"),
+        "Hello, world. How are you?"
+    );
+    assert_eq!(formatter.format("Room １０１（left）"), "Room 101（left）");
+}
+
+#[test]
+fn mixed_follows_the_script_and_spaces_scripts_apart() {
+    let formatter = TextFormatter::new(Language::Mixed);
+    assert_eq!(
+        formatter.format("我们用Tauri开发,OK，再加3个功能."),
+        "我们用 Tauri 开发，OK, 再加 3 个功能。"
+    );
+}
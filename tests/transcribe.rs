@@ -0,0 +1,260 @@
+//! Offline file transcription against the mock ASR server, and transcript
+//! rendering.
+
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::{temp_dir, test_credentials};
+use doubao_voice_input::asr::mock_server::{MockAsrServer, MockScript, ScriptedResult};
+use doubao_voice_input::business::{
+    audio_files_in, FileTranscriber, Transcript, TranscriptFormat, TranscriptSegment,
+};
+use doubao_voice_input::AsrClient;
+
+/// Write a mono 16-bit WAV holding `seconds` of a quiet ramp
+fn write_wav(dir: &Path, name: &str, sample_rate: u32, seconds: f64) -> PathBuf {
+    let path = dir.join(name);
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+    for i in 0..(sample_rate as f64 * seconds) as u32 {
+        writer.write_sample((i % 200) as i16).unwrap();
+    }
+    writer.finalize().unwrap();
+    path
+}
+
+fn segment(start_ms: u64, end_ms: u64, text: &str) -> TranscriptSegment {
+    TranscriptSegment {
+        start_ms,
+        end_ms,
+        text: text.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn files_are_transcribed_into_timed_segments_faster_than_real_time() {
+    let first = r#"{"text": "你好", "index": 0, "start_time": 120, "end_time": 640,
+        "is_interim": false, "is_vad_finished": true}"#;
+    let script = MockScript {
+        results: vec![
+            ScriptedResult::interim(10, "你"),
+            ScriptedResult::raw(
+                30,
+                format!(
+                    r#"{{"results": [{}, {{"text": "世", "is_interim": true}}]}}"#,
+                    first
+                ),
+            ),
+            // The first segment is resent and must not be duplicated
+            ScriptedResult::raw(
+                80,
+                format!(
+                    r#"{{"results": [{}, {{"text": "世界", "index": 1,
+                        "words": [{{"word": "世", "start_time": 1500, "end_time": 1700}},
+                                  {{"word": "界", "start_time": 1700, "end_time": 1980}}],
+                        "is_interim": false, "is_vad_finished": true}}]}}"#,
+                    first
+                ),
+            ),
+            // Never finalized before the session ends
+            ScriptedResult::interim(100, "再见"),
+        ],
+        ..Default::default()
+    };
+    let server = MockAsrServer::start(script).await.unwrap();
+    let client = AsrClient::new(test_credentials()).with_ws_url(server.url());
+
+    let dir = temp_dir("file");
+    let path = write_wav(&dir, "memo.wav", 44100, 2.5);
+    let transcript = FileTranscriber::new(Arc::new(client))
+        .transcribe(&path)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        transcript.segments,
+        vec![
+            segment(120, 640, "你好"),
+            segment(1500, 1980, "世界"),
+            segment(1980, 1980, "再见"),
+        ]
+    );
+    assert_eq!(transcript.audio_duration, Duration::from_millis(2500));
+    assert!(transcript.speed() > 1.0, "{:.2}x", transcript.speed());
+    assert!(transcript.stats.is_some());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn cues_stay_in_order_across_a_rollover() {
+    let sentence = |after_frames: u32, text: &str, start: u64, end: u64| {
+        ScriptedResult::raw(
+            after_frames,
+            format!(
+                r#"{{"results": [{{"text": "{}", "start_time": {}, "end_time": {},
+                    "is_interim": false, "is_vad_finished": true}}]}}"#,
+                text, start, end
+            ),
+        )
+    };
+    // Each server session times its results from its own first frame
+    let server = MockAsrServer::start_with_scripts(vec![
+        MockScript {
+            results: vec![sentence(30, "第一句。", 100, 600)],
+            ..Default::default()
+        },
+        MockScript {
+            results: vec![sentence(20, "第二句。", 50, 380)],
+            ..Default::default()
+        },
+        MockScript {
+            results: vec![sentence(20, "第三句。", 0, 300)],
+            ..Default::default()
+        },
+        MockScript::default(),
+    ])
+    .await
+    .unwrap();
+    // Roll over at every sentence boundary, while the file is still streaming
+    let client = AsrClient::new(test_credentials())
+        .with_ws_url(server.url())
+        .with_rollover(Duration::ZERO);
+
+    let dir = temp_dir("rollover");
+    let path = write_wav(&dir, "memo.wav", 16000, 2.5);
+    let transcript = FileTranscriber::new(Arc::new(client))
+        .with_max_speed(Some(10.0))
+        .transcribe(&path)
+        .await
+        .unwrap();
+
+    assert_eq!(transcript.stats.as_ref().unwrap().rollovers, 3);
+    assert_eq!(
+        transcript.segments,
+        vec![
+            segment(100, 600, "第一句。"),
+            segment(650, 980, "第二句。"),
+            segment(980, 1280, "第三句。"),
+        ]
+    );
+    let srt = transcript.render(TranscriptFormat::Srt);
+    assert!(srt.contains("00:00:00,650 --> 00:00:00,980"), "{}", srt);
+    assert!(transcript
+        .segments
+        .windows(2)
+        .all(|pair| pair[0].end_ms <= pair[1].start_ms));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn speed_can_be_capped() {
+    let server = MockAsrServer::start(MockScript::recognize("你好"))
+        .await
+        .unwrap();
+    let client = AsrClient::new(test_credentials()).with_ws_url(server.url());
+
+    let dir = temp_dir("capped");
+    let path = write_wav(&dir, "memo.wav", 16000, 1.0);
+    let transcript = FileTranscriber::new(Arc::new(client))
+        .with_max_speed(Some(4.0))
+        .transcribe(&path)
+        .await
+        .unwrap();
+
+    // 1.2s with the trailing silence, at 4x
+    assert!(transcript.elapsed >= Duration::from_millis(290));
+    assert!(transcript.speed() <= 4.0);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn files_at_unsupported_rates_are_rejected() {
+    let server = MockAsrServer::start(MockScript::recognize("你好"))
+        .await
+        .unwrap();
+    let client = AsrClient::new(test_credentials()).with_ws_url(server.url());
+
+    // 10Hz makes a 20ms frame zero samples long
+    let dir = temp_dir("low-rate");
+    let path = write_wav(&dir, "memo.wav", 10, 1.0);
+    let result = tokio::time::timeout(
+        Duration::from_secs(5),
+        FileTranscriber::new(Arc::new(client)).transcribe(&path),
+    )
+    .await
+    .expect("transcription hung");
+    assert!(result.is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn transcripts_render_in_every_format() {
+    let transcript = Transcript {
+        segments: vec![
+            segment(0, 1500, "第一句。"),
+            segment(3_723_004, 3_725_000, "second"),
+        ],
+        ..Default::default()
+    };
+
+    assert_eq!(
+        transcript.render(TranscriptFormat::Text),
+        "第一句。\nsecond\n"
+    );
+    assert_eq!(
+        transcript.render(TranscriptFormat::JsonLines),
+        "{\"start_ms\":0,\"end_ms\":1500,\"text\":\"第一句。\"}\n\
+         {\"start_ms\":3723004,\"end_ms\":3725000,\"text\":\"second\"}\n"
+    );
+    assert_eq!(
+        transcript.render(TranscriptFormat::Srt),
+        "1\n00:00:00,000 --> 00:00:01,500\n第一句。\n\n\
+         2\n01:02:03,004 --> 01:02:05,000\nsecond\n\n"
+    );
+    assert_eq!(
+        transcript.render(TranscriptFormat::Vtt),
+        "WEBVTT\n\n\
+         00:00:00.000 --> 00:00:01.500\n第一句。\n\n\
+         01:02:03.004 --> 01:02:05.000\nsecond\n\n"
+    );
+
+    assert_eq!(
+        TranscriptFormat::from_name("SRT"),
+        Some(TranscriptFormat::Srt)
+    );
+    assert_eq!(
+        TranscriptFormat::from_name("jsonl").map(|f| f.extension()),
+        Some("jsonl")
+    );
+    assert_eq!(TranscriptFormat::from_name("docx"), None);
+}
+
+#[test]
+fn directories_list_their_audio_files() {
+    let dir = temp_dir("batch");
+    for name in ["b.MP3", "a.wav", "c.opus", "notes.txt"] {
+        std::fs::write(dir.join(name), "").unwrap();
+    }
+    std::fs::create_dir_all(dir.join("nested.wav")).unwrap();
+
+    let files: Vec<_> = audio_files_in(&dir)
+        .unwrap()
+        .iter()
+        .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(files, ["a.wav", "b.MP3", "c.opus"]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
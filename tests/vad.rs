@@ -0,0 +1,184 @@
+//! Energy VAD on synthetic 20ms frames: onset delay, hangover and the
+//! silence timeouts that drive auto-stop, and hold-back with pre-roll.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use doubao_voice_input::audio::{frame_level_db, EnergyVad, SyntheticSource, VadSettings};
+use doubao_voice_input::{AppConfig, AudioCapture};
+
+/// 20ms of a 440Hz tone at `amplitude` (full scale 1.0)
+fn tone(amplitude: f64) -> Vec<i16> {
+    (0..320)
+        .map(|i| {
+            let t = i as f64 / 16000.0;
+            (amplitude * 32767.0 * (std::f64::consts::TAU * 440.0 * t).sin()) as i16
+        })
+        .collect()
+}
+
+fn silence() -> Vec<i16> {
+    vec![0; 320]
+}
+
+fn settings() -> VadSettings {
+    VadSettings {
+        speech_threshold_db: -40.0,
+        min_speech: Duration::from_millis(60),
+        hangover: Duration::from_millis(200),
+        silence_timeout: Some(Duration::from_millis(1000)),
+        no_speech_timeout: Some(Duration::from_millis(2000)),
+        ..VadSettings::default()
+    }
+}
+
+#[test]
+fn frame_level_is_measured_in_dbfs() {
+    assert_eq!(frame_level_db(&silence()), -100.0);
+    // A sine's RMS is 3dB below its peak
+    assert!((frame_level_db(&tone(1.0)) + 3.0).abs() < 0.1);
+    assert!((frame_level_db(&tone(0.01)) + 43.0).abs() < 0.1);
+}
+
+#[test]
+fn speech_needs_onset_and_ends_after_hangover() {
+    let mut vad = EnergyVad::new(settings());
+    let speech = tone(0.3);
+
+    // A 40ms click is shorter than min_speech
+    assert!(!vad.process(&speech));
+    assert!(!vad.process(&speech));
+    assert!(!vad.process(&silence()));
+    assert!(!vad.heard_speech());
+
+    // Speech starts on the third loud frame (60ms)
+    assert!(!vad.process(&speech));
+    assert!(!vad.process(&speech));
+    assert!(vad.process(&speech));
+    assert!(vad.heard_speech());
+
+    // Quiet frames below the threshold are bridged for 200ms
+    let quiet = tone(0.005);
+    for _ in 0..10 {
+        assert!(vad.process(&quiet));
+    }
+    assert!(!vad.process(&quiet));
+    assert_eq!(vad.trailing_silence(), Duration::from_millis(20));
+}
+
+#[test]
+fn silence_times_out_after_speech_or_without_it() {
+    let mut vad = EnergyVad::new(settings());
+    for _ in 0..99 {
+        vad.process(&silence());
+    }
+    assert!(!vad.timed_out());
+    vad.process(&silence());
+    assert!(vad.timed_out(), "no speech for 2s");
+
+    let mut vad = EnergyVad::new(settings());
+    for _ in 0..20 {
+        vad.process(&tone(0.3));
+    }
+    // 200ms hangover, then 1s of silence
+    for _ in 0..59 {
+        vad.process(&silence());
+    }
+    assert!(!vad.timed_out());
+    vad.process(&silence());
+    assert!(vad.timed_out());
+
+    // Speaking again resets the timeout
+    for _ in 0..3 {
+        vad.process(&tone(0.3));
+    }
+    assert!(!vad.timed_out());
+    assert_eq!(vad.trailing_silence(), Duration::ZERO);
+
+    let mut vad = EnergyVad::new(VadSettings {
+        no_speech_timeout: None,
+        ..settings()
+    });
+    for _ in 0..500 {
+        vad.process(&silence());
+    }
+    assert!(!vad.timed_out());
+}
+
+#[test]
+fn default_config_never_auto_stops() {
+    let configs = [
+        AppConfig::default(),
+        toml::from_str("[asr]\nvad_enabled = true\n").unwrap(),
+        toml::from_str("[audio.vad]\nhold_leading_silence = true\n").unwrap(),
+    ];
+    for config in configs {
+        let settings = config.vad_settings().unwrap();
+        assert_eq!(settings.silence_timeout, None);
+        assert_eq!(settings.no_speech_timeout, None);
+
+        let mut vad = EnergyVad::new(settings);
+        for _ in 0..20 {
+            vad.process(&tone(0.3));
+        }
+        // A minute of silence
+        for _ in 0..3000 {
+            vad.process(&silence());
+        }
+        assert!(!vad.timed_out());
+    }
+}
+
+#[test]
+fn configured_timeouts_stop_except_in_continuous_mode() {
+    let config: AppConfig = toml::from_str(
+        r#"
+        [audio.vad]
+        silence_timeout_ms = 3000
+        no_speech_timeout_ms = 10000
+        "#,
+    )
+    .unwrap();
+    let settings = config.vad_settings().unwrap();
+    assert_eq!(settings.silence_timeout, Some(Duration::from_secs(3)));
+    assert_eq!(settings.no_speech_timeout, Some(Duration::from_secs(10)));
+
+    // Pauses between sentences do not end a continuous dictation
+    let mut continuous = config.clone();
+    continuous.asr.continuous = true;
+    let settings = continuous.vad_settings().unwrap();
+    assert_eq!(settings.silence_timeout, None);
+    assert_eq!(settings.no_speech_timeout, Some(Duration::from_secs(10)));
+
+    let mut disabled = config;
+    disabled.asr.vad_enabled = false;
+    assert!(disabled.vad_settings().is_none());
+}
+
+#[tokio::test]
+async fn preroll_is_sent_even_when_holding_back_leading_silence() {
+    let source = SyntheticSource::new(16000).silence(Duration::from_millis(1500));
+    let capture = AudioCapture::new()
+        .unwrap()
+        .with_source(Arc::new(source))
+        .with_vad(Some(VadSettings {
+            hold_leading_silence: true,
+            leading_padding: Duration::from_millis(100),
+            ..settings()
+        }))
+        .with_preroll(Some(Duration::from_millis(200)));
+    // Let the always-warm capture fill its pre-roll
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let mut rx = capture.start().unwrap();
+    let preroll = capture.preroll_sent();
+    assert_eq!(preroll, Duration::from_millis(200));
+
+    // The rest of the source is silence and stays held back; the pre-roll
+    // the session's start time accounts for is all that arrives
+    let mut frames = 0;
+    while let Ok(Some(_)) = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await {
+        frames += 1;
+    }
+    assert_eq!(frames, 10);
+}
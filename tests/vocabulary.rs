@@ -0,0 +1,59 @@
+//! User vocabulary file handling and pinyin-aware correction.
+
+use doubao_voice_input::business::VocabularyCorrector;
+use doubao_voice_input::data::{Vocabulary, VocabularyConfig};
+
+#[test]
+fn parses_and_round_trips_the_vocabulary_file() {
+    let mut vocabulary = Vocabulary::parse("# 产品\n豆包输入法|5\n\n  张伟  \nTauri|x\n张伟|2\n");
+    let terms: Vec<(&str, Option<f32>)> = vocabulary
+        .terms()
+        .iter()
+        .map(|t| (t.term.as_str(), t.weight))
+        .collect();
+    assert_eq!(
+        terms,
+        [
+            ("豆包输入法", Some(5.0)),
+            ("张伟", Some(2.0)),
+            ("Tauri", None)
+        ]
+    );
+
+    assert!(vocabulary.remove("Tauri"));
+    assert!(!vocabulary.insert(" ", None));
+    assert_eq!(Vocabulary::parse(&vocabulary.to_text()), vocabulary);
+
+    let path = std::env::temp_dir().join(format!("vocabulary-{}.txt", std::process::id()));
+    vocabulary.save_to(&path).unwrap();
+    assert_eq!(Vocabulary::load_from(&path).unwrap(), vocabulary);
+    std::fs::remove_file(&path).unwrap();
+
+    let config = VocabularyConfig::default();
+    assert!(config.hotwords(&vocabulary).is_empty());
+    let config = VocabularyConfig {
+        send_hotwords: true,
+        ..config
+    };
+    assert_eq!(config.hotwords(&vocabulary)[0].word, "豆包输入法");
+}
+
+#[test]
+fn corrects_soundalikes_towards_vocabulary_terms() {
+    let vocabulary = Vocabulary::parse("张伟\n豆包输入法\n李娜|2\nTauri\n");
+    let corrector = VocabularyCorrector::new(&vocabulary);
+
+    // Same pinyin, different characters
+    assert_eq!(corrector.correct("我和章伟开会"), "我和张伟开会");
+    // Folded initials and finals: dou bao shu ru fa / li na vs ni la
+    assert_eq!(corrector.correct("试试都保书入法"), "试试豆包输入法");
+    assert_eq!(corrector.correct("问一下你拉"), "问一下李娜");
+    // Latin terms only match whole words
+    assert_eq!(corrector.correct("用 tauri 打包"), "用 Tauri 打包");
+    assert_eq!(corrector.correct("tauris"), "tauris");
+    // Unrelated text is left alone
+    assert_eq!(corrector.correct("今天天气不错"), "今天天气不错");
+
+    corrector.set_enabled(false);
+    assert_eq!(corrector.correct("我和章伟开会"), "我和章伟开会");
+}